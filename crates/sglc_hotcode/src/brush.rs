@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+
+use glam::{IVec3, Vec3};
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_SIZE_ONE};
+
+use crate::chunk_allocator::ChunkAllocator;
+use crate::history::Operation;
+use crate::pos_to_index::pos_to_index;
+use crate::set_voxel::voxel_at;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    Sphere { center: Vec3, radius: f32 },
+    // Both corners are included.
+    Box { min: IVec3, max: IVec3 },
+    Cylinder { a: Vec3, b: Vec3, radius: f32 },
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BrushMode {
+    // Fills the shape with the unit.
+    Add,
+    // Empties the shape.
+    Subtract,
+    // Changes the unit of voxels in the shape that aren't empty.
+    Paint,
+}
+
+impl Shape {
+    pub fn line(a: Vec3, b: Vec3) -> Self {
+        Shape::Capsule { a, b, radius: 0.5 }
+    }
+
+    pub fn bounds(&self) -> (IVec3, IVec3) {
+        let (min, max) = match *self {
+            Shape::Sphere { center, radius } => (center - radius, center + radius),
+            Shape::Box { min, max } => return (min, max),
+            Shape::Cylinder { a, b, radius } | Shape::Capsule { a, b, radius } => {
+                (a.min(b) - radius, a.max(b) + radius)
+            },
+        };
+
+        (min.floor().as_ivec3(), max.ceil().as_ivec3())
+    }
+
+    // Takes the center of a voxel, not its corner.
+    pub fn contains(&self, p: Vec3) -> bool {
+        match *self {
+            Shape::Sphere { center, radius } => p.distance_squared(center) <= radius * radius,
+            Shape::Box { min, max } => {
+                p.cmpge(min.as_vec3()).all() && p.cmple(max.as_vec3() + 1.0).all()
+            },
+            Shape::Cylinder { a, b, radius } => {
+                let axis = b - a;
+                let t = (p - a).dot(axis) / axis.length_squared().max(f32::EPSILON);
+                (0.0..=1.0).contains(&t) && p.distance_squared(a + axis * t) <= radius * radius
+            },
+            Shape::Capsule { a, b, radius } => {
+                let axis = b - a;
+                let t = ((p - a).dot(axis) / axis.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                p.distance_squared(a + axis * t) <= radius * radius
+            },
+        }
+    }
+}
+
+pub fn apply_brush(
+    shape: Shape,
+    mode: BrushMode,
+    unit: u32,
+    operation: &mut Operation,
+    allocator: &mut ChunkAllocator,
+    chunk_mapping: &mut ChunkMapping,
+    voxels: &mut Voxels,
+) {
+    let (min, max) = shape.bounds();
+    let min = min.max(IVec3::ZERO);
+    let max = max.min(chunk_mapping.size.voxels() - 1);
+    if min.cmpgt(max).any() {
+        return;
+    }
+
+    let chunk1 = min / CHUNK_SIZE_ONE as i32;
+    let chunk2 = max / CHUNK_SIZE_ONE as i32;
+
+    for cx in chunk1.x..=chunk2.x {
+        for cy in chunk1.y..=chunk2.y {
+            for cz in chunk1.z..=chunk2.z {
+                let chunk_index = pos_to_index((cx, cy, cz), chunk_mapping.size.extent());
+
+                if mode != BrushMode::Add && chunk_mapping.chunks[chunk_index] == 0 {
+                    continue;
+                }
+
+                let chunk = IVec3::new(cx, cy, cz) * CHUNK_SIZE_ONE as i32;
+                let from = chunk.max(min);
+                let to = (chunk + CHUNK_SIZE_ONE as i32 - 1).min(max);
+
+                for x in from.x..=to.x {
+                    for y in from.y..=to.y {
+                        for z in from.z..=to.z {
+                            let pos = IVec3::new(x, y, z);
+                            if !shape.contains(pos.as_vec3() + 0.5) {
+                                continue;
+                            }
+
+                            let current = voxel_at(pos, chunk_mapping, voxels);
+                            let new = match mode {
+                                BrushMode::Add => unit,
+                                BrushMode::Subtract => 0,
+                                BrushMode::Paint if current > 2 => unit,
+                                BrushMode::Paint => continue,
+                            };
+
+                            operation.set_voxel(pos, new, allocator, chunk_mapping, voxels);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// How far a fill can get from where it started, whatever bounds it is given.
+const MAX_FILL_REACH: i32 = 64;
+
+// Replaces the 6-connected region of voxels around `start` that share its
+// unit, without leaving the box given by `bounds` or going further than
+// `MAX_FILL_REACH` along any axis. Empty voxels and air chunks count as the
+// same unit. Returns how many voxels were changed.
+pub fn flood_fill(
+    start: IVec3,
+    unit: u32,
+    bounds: (IVec3, IVec3),
+    operation: &mut Operation,
+    allocator: &mut ChunkAllocator,
+    chunk_mapping: &mut ChunkMapping,
+    voxels: &mut Voxels,
+) -> usize {
+    let world = chunk_mapping.size;
+    let min = bounds.0.max(start - MAX_FILL_REACH).max(IVec3::ZERO);
+    let max = bounds.1.min(start + MAX_FILL_REACH).min(world.voxels() - 1);
+    let is_empty = |u: u32| u == 0 || u == 2;
+    let inside = |p: IVec3| world.contains(p) && p.cmpge(min).all() && p.cmple(max).all();
+
+    if !inside(start) {
+        return 0;
+    }
+
+    let target = voxel_at(start, chunk_mapping, voxels);
+    let matches = |u: u32| if is_empty(target) { is_empty(u) } else { u == target };
+
+    if matches(unit) {
+        return 0;
+    }
+
+    let size = max - min + 1;
+    let mut visited = vec![false; (size.x * size.y * size.z) as usize];
+    let visited_index = |p: IVec3| {
+        let p = p - min;
+        ((p.z * size.y + p.y) * size.x + p.x) as usize
+    };
+
+    let mut queue = VecDeque::from([start]);
+    visited[visited_index(start)] = true;
+    let mut filled = 0;
+
+    while let Some(pos) = queue.pop_front() {
+        if operation.set_voxel(pos, unit, allocator, chunk_mapping, voxels).is_some() {
+            filled += 1;
+        }
+
+        for offset in [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z] {
+            let next = pos + offset;
+            if !inside(next) || visited[visited_index(next)] {
+                continue;
+            }
+
+            visited[visited_index(next)] = true;
+            if matches(voxel_at(next, chunk_mapping, voxels)) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::WorldSize;
+
+    use super::*;
+    use crate::clear::clear;
+
+    const SHAPES: [Shape; 4] = [
+        Shape::Sphere { center: Vec3::new(12.0, 13.0, 14.0), radius: 6.5 },
+        Shape::Box { min: IVec3::new(3, 5, 7), max: IVec3::new(20, 9, 11) },
+        Shape::Cylinder { a: Vec3::new(4.0, 16.0, 16.0), b: Vec3::new(27.0, 16.0, 16.0), radius: 3.0 },
+        Shape::Capsule { a: Vec3::new(4.0, 4.0, 4.0), b: Vec3::new(24.0, 20.0, 12.0), radius: 2.5 },
+    ];
+
+    fn world() -> (ChunkAllocator, Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        (ChunkAllocator::default(), chunk_mapping, voxels)
+    }
+
+    fn positions() -> impl Iterator<Item = IVec3> {
+        (0..32).flat_map(|x| (0..32).flat_map(move |y| (0..32).map(move |z| IVec3::new(x, y, z))))
+    }
+
+    // Air chunks read as 2, which is just as empty.
+    fn unit_at(pos: IVec3, world: &(ChunkAllocator, Box<ChunkMapping>, Box<Voxels>)) -> u32 {
+        match voxel_at(pos, &world.1, &world.2) {
+            2 => 0,
+            unit => unit,
+        }
+    }
+
+    fn brush(shape: Shape, mode: BrushMode, unit: u32, world: &mut (ChunkAllocator, Box<ChunkMapping>, Box<Voxels>)) {
+        let (allocator, chunk_mapping, voxels) = world;
+        apply_brush(shape, mode, unit, &mut Operation::default(), allocator, chunk_mapping, voxels);
+    }
+
+    #[test]
+    fn add_fills_exactly_the_shape() {
+        for shape in SHAPES {
+            let mut world = world();
+            brush(shape, BrushMode::Add, 5, &mut world);
+
+            for pos in positions() {
+                let expected = if shape.contains(pos.as_vec3() + 0.5) { 5 } else { 0 };
+                assert_eq!(unit_at(pos, &world), expected, "{shape:?} at {pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_empties_the_shape_and_releases_its_chunks() {
+        for shape in SHAPES {
+            let mut world = world();
+            brush(shape, BrushMode::Add, 5, &mut world);
+            brush(Shape::Box { min: IVec3::new(0, 28, 0), max: IVec3::new(31, 31, 31) }, BrushMode::Add, 6, &mut world);
+            let kept = world.0.allocated_count();
+            brush(shape, BrushMode::Subtract, 0, &mut world);
+
+            for pos in positions() {
+                let expected = if pos.y >= 28 { 6 } else { 0 };
+                assert_eq!(unit_at(pos, &world), expected, "{shape:?} at {pos}");
+            }
+
+            // Only the chunks of the slab along the top are left.
+            assert!(world.0.allocated_count() < kept);
+            assert_eq!(world.0.allocated_count(), 16);
+        }
+    }
+
+    #[test]
+    fn paint_only_changes_voxels_that_are_there() {
+        let base = Shape::Box { min: IVec3::new(0, 0, 0), max: IVec3::new(31, 12, 31) };
+
+        for shape in SHAPES {
+            let mut world = world();
+            brush(base, BrushMode::Add, 5, &mut world);
+            let allocated = world.0.allocated_count();
+            brush(shape, BrushMode::Paint, 7, &mut world);
+
+            for pos in positions() {
+                let center = pos.as_vec3() + 0.5;
+                let expected = match (base.contains(center), shape.contains(center)) {
+                    (true, true) => 7,
+                    (true, false) => 5,
+                    (false, _) => 0,
+                };
+                assert_eq!(unit_at(pos, &world), expected, "{shape:?} at {pos}");
+            }
+
+            assert_eq!(world.0.allocated_count(), allocated);
+        }
+    }
+
+    #[test]
+    fn flood_fill_stays_inside_its_bounds() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let bounds = (IVec3::new(10, 12, 14), IVec3::new(18, 20, 22));
+        let mut operation = Operation::default();
+
+        let filled = flood_fill(IVec3::splat(16), 5, bounds, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(filled, 9 * 9 * 9);
+
+        for pos in positions() {
+            let inside = pos.cmpge(bounds.0).all() && pos.cmple(bounds.1).all();
+            assert_eq!(voxel_at(pos, &chunk_mapping, &voxels) == 5, inside, "at {pos}");
+        }
+
+        // Filling it with nothing again gives every chunk back.
+        let filled = flood_fill(IVec3::splat(16), 0, bounds, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(filled, 9 * 9 * 9);
+        assert_eq!(allocator.allocated_count(), 0);
+    }
+}
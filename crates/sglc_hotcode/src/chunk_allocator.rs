@@ -0,0 +1,100 @@
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_SIZE};
+
+// Hands out slots in `Voxels` for chunks that stop being all air. Slot 0 is
+// the shared air chunk and is never handed out. Worlds are allowed to point
+// several chunks at the same slot, so slots are reference counted and copied
+// before a shared one gets edited. The counts grow with the slots handed out,
+// so the allocator doesn't need to know how big the world is.
+#[derive(Clone, Debug)]
+pub struct ChunkAllocator {
+    next: usize,
+    free: Vec<usize>,
+    refs: Vec<u32>,
+}
+
+impl Default for ChunkAllocator {
+    fn default() -> Self {
+        Self { next: 1, free: Vec::new(), refs: Vec::new() }
+    }
+}
+
+impl ChunkAllocator {
+    pub fn from_mapping(chunk_mapping: &ChunkMapping) -> Self {
+        // Shared slots can go past the number of chunks in small worlds.
+        let slots = chunk_mapping.chunks.iter().max().map_or(1, |&slot| slot as usize + 1);
+        let mut refs = vec![0; slots.max(chunk_mapping.chunks.len())];
+        let mut next = 1;
+
+        for &slot in chunk_mapping.chunks.iter() {
+            if slot != 0 {
+                refs[slot as usize] += 1;
+                next = next.max(slot as usize + 1);
+            }
+        }
+
+        let free = (1..next).rev().filter(|&slot| refs[slot] == 0).collect();
+
+        Self { next, free, refs }
+    }
+
+    pub fn allocate(&mut self, voxels: &mut Voxels) -> Option<usize> {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None if self.next < voxels.0.len() => {
+                self.next += 1;
+                self.next - 1
+            },
+            None => return None,
+        };
+
+        if self.refs.len() <= slot {
+            self.refs.resize(voxels.0.len(), 0);
+        }
+
+        voxels.0[slot] = [0; CHUNK_SIZE];
+        self.refs[slot] = 1;
+
+        Some(slot)
+    }
+
+    // Drops one chunk's reference to the slot, freeing it once nothing points
+    // at it anymore.
+    pub fn release(&mut self, slot: usize, voxels: &mut Voxels) {
+        debug_assert!(slot != 0, "the air chunk can't be released");
+
+        self.refs[slot] = self.refs[slot].saturating_sub(1);
+        if self.refs[slot] == 0 {
+            voxels.0[slot] = [0; CHUNK_SIZE];
+            self.free.push(slot);
+        }
+    }
+
+    pub fn is_shared(&self, slot: usize) -> bool {
+        slot != 0 && self.refs.get(slot).is_some_and(|&refs| refs > 1)
+    }
+
+    // Gives the chunk a slot of its own if it shares one with other chunks,
+    // keeping its voxels. Returns the slot the chunk ends up with.
+    pub fn make_unique(&mut self, chunk: usize, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> Option<usize> {
+        let slot = chunk_mapping.chunks[chunk] as usize;
+        if !self.is_shared(slot) {
+            return Some(slot);
+        }
+
+        let unique = self.allocate(voxels)?;
+        voxels.0[unique] = voxels.0[slot];
+        self.refs[slot] -= 1;
+        chunk_mapping.chunks[chunk] = unique as u32;
+
+        Some(unique)
+    }
+
+    // One past the highest slot that has ever been handed out.
+    pub fn chunk_count(&self) -> usize {
+        self.next
+    }
+
+    pub fn allocated_count(&self) -> usize {
+        self.next - 1 - self.free.len()
+    }
+}
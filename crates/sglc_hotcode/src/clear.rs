@@ -1,19 +1,17 @@
-use sglc_shared::{ChunkMapping, Voxels};
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_SIZE};
+
+// Empties every slot the mapping still points to, so that slots handed out
+// afterwards start out zeroed.
+pub fn clear(chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+    for c in &mut chunk_mapping.chunks {
+        if *c != 0 {
+            voxels.0[*c as usize] = [0; CHUNK_SIZE];
+        }
 
-pub fn clear(chunk_count: usize, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
-    for c in &mut chunk_mapping.0 {
         *c = 0;
     }
 
     for v in &mut voxels.0[0] {
         *v = 2;
     }
-
-    if chunk_count > 0 {
-        for c in &mut voxels.0[1..chunk_count] {
-            for v in c {
-                *v = 0;
-            }
-        }
-    }
 }
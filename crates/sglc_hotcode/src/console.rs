@@ -0,0 +1,214 @@
+use crate::overlay::{Canvas, CELL_HEIGHT, PANEL, WHITE};
+
+// Lines kept in the log, older ones get dropped.
+const MAX_LOG: usize = 64;
+// Log lines shown above the input line while open.
+const SHOWN_LOG: usize = 8;
+
+// The text side of the drop-down console: the line being typed, its history
+// and what commands printed. Running the commands is up to whoever owns it.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub log: Vec<String>,
+    history: Vec<String>,
+    // Where Up and Down are in the history, None while typing a new line.
+    browsing: Option<usize>,
+}
+
+impl Console {
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.log.push(line.into());
+        if self.log.len() > MAX_LOG {
+            self.log.remove(0);
+        }
+    }
+
+    // Control characters come in as keys instead, and ` opens the console.
+    pub fn type_char(&mut self, c: char) {
+        if !c.is_control() && c != '`' {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    // Takes the typed line, None if there was nothing on it.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.browsing = None;
+
+        if line.is_empty() {
+            return None;
+        }
+
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Some(line)
+    }
+
+    pub fn history_up(&mut self) {
+        let index = match self.browsing {
+            Some(index) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+
+        self.browsing = Some(index);
+        self.input = self.history[index].clone();
+    }
+
+    pub fn history_down(&mut self) {
+        let Some(index) = self.browsing else { return };
+
+        if index + 1 < self.history.len() {
+            self.browsing = Some(index + 1);
+            self.input = self.history[index + 1].clone();
+        } else {
+            self.browsing = None;
+            self.input.clear();
+        }
+    }
+
+    // Which word is being typed, counting from 0 for the command name.
+    pub fn word_index(&self) -> usize {
+        self.input.split(' ').count() - 1
+    }
+
+    // Completes the word being typed as far as the candidates agree, listing
+    // them if they don't.
+    pub fn complete(&mut self, candidates: &[&str]) {
+        let start = self.input.rfind(' ').map_or(0, |i| i + 1);
+        let word = &self.input[start..];
+        let matches = candidates.iter().filter(|c| c.starts_with(word)).collect::<Vec<_>>();
+
+        let Some(first) = matches.first() else { return };
+        let common = matches.iter().fold(first.len(), |common, candidate| {
+            common.min(first.bytes().zip(candidate.bytes()).take_while(|(a, b)| a == b).count())
+        });
+
+        let completed = if matches.len() == 1 { format!("{first} ") } else { first[..common].to_string() };
+        if matches.len() > 1 && completed == word {
+            let listed = matches.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ");
+            self.print(listed);
+        }
+
+        self.input.replace_range(start.., &completed);
+    }
+
+    // Across the top of the screen, over everything else.
+    pub fn draw(&self, canvas: &mut Canvas) {
+        let shown = &self.log[self.log.len().saturating_sub(SHOWN_LOG)..];
+        canvas.fill_rect(0, 0, canvas.width, (SHOWN_LOG + 1) * CELL_HEIGHT + 1, PANEL);
+
+        let top = (SHOWN_LOG - shown.len()) * CELL_HEIGHT;
+        for (i, line) in shown.iter().enumerate() {
+            canvas.draw_text(1, 1 + top + i * CELL_HEIGHT, line, WHITE);
+        }
+
+        canvas.draw_text(1, 1 + SHOWN_LOG * CELL_HEIGHT, &format!("> {}_", self.input), WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMANDS: [&str; 5] = ["help", "world", "seed", "save", "set"];
+
+    fn typed(text: &str) -> Console {
+        let mut console = Console::default();
+        text.chars().for_each(|c| console.type_char(c));
+        console
+    }
+
+    #[test]
+    fn unique_matches_complete_the_whole_word() {
+        let mut console = typed("wo");
+        console.complete(&COMMANDS);
+        assert_eq!(console.input, "world ");
+        assert_eq!(console.word_index(), 1);
+
+        console.type_char('h');
+        console.complete(&["hills", "spheres"]);
+        assert_eq!(console.input, "world hills ");
+        assert!(console.log.is_empty());
+    }
+
+    #[test]
+    fn ambiguous_matches_complete_the_common_prefix() {
+        let mut console = typed("sa");
+        console.complete(&["save", "saturation", "seed"]);
+        assert_eq!(console.input, "sa");
+        assert_eq!(console.log, ["save saturation"]);
+
+        let mut console = typed("h");
+        console.complete(&["heights", "height_scale"]);
+        assert_eq!(console.input, "height");
+        assert!(console.log.is_empty());
+
+        // Once there's nothing more in common, they get listed instead.
+        console.complete(&["heights", "height_scale"]);
+        assert_eq!(console.input, "height");
+        assert_eq!(console.log, ["heights height_scale"]);
+    }
+
+    #[test]
+    fn words_nothing_matches_are_left_alone() {
+        let mut console = typed("set x");
+        console.complete(&COMMANDS);
+        assert_eq!(console.input, "set x");
+        assert!(console.log.is_empty());
+    }
+
+    #[test]
+    fn history_is_browsed_with_up_and_down() {
+        let mut console = Console::default();
+        console.history_up();
+        assert_eq!(console.input, "");
+
+        for line in ["regen", "tp 1 2 3", "tp 1 2 3", "  help "] {
+            console.input = line.to_string();
+            console.submit();
+        }
+
+        // Repeats are only kept once, and lines are trimmed.
+        console.type_char('x');
+        console.history_up();
+        assert_eq!(console.input, "help");
+        console.history_up();
+        assert_eq!(console.input, "tp 1 2 3");
+        console.history_up();
+        assert_eq!(console.input, "regen");
+        console.history_up();
+        assert_eq!(console.input, "regen");
+
+        console.history_down();
+        assert_eq!(console.input, "tp 1 2 3");
+        console.history_down();
+        assert_eq!(console.input, "help");
+        console.history_down();
+        assert_eq!(console.input, "");
+
+        // Submitting starts over from the newest line.
+        console.history_up();
+        console.history_up();
+        assert_eq!(console.submit().as_deref(), Some("tp 1 2 3"));
+        console.history_up();
+        assert_eq!(console.input, "tp 1 2 3");
+        console.history_up();
+        assert_eq!(console.input, "help");
+    }
+
+    #[test]
+    fn empty_lines_are_not_submitted() {
+        let mut console = typed("   ");
+        assert_eq!(console.submit(), None);
+        console.history_up();
+        assert_eq!(console.input, "");
+    }
+}
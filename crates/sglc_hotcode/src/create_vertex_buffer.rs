@@ -1,14 +1,16 @@
 use glam::Vec3;
 
-use sglc_shared::{ChunkMapping, CHUNK_COUNT_ONE, CHUNK_SIZE_ONE, Vertices};
+use sglc_shared::{ChunkMapping, CHUNK_SIZE_ONE};
 use sglc_shared::MyVertex;
 use crate::pos_to_index::index_to_pos;
 
+// 36 vertices for the box around every chunk that isn't empty. Returns how
+// many chunks didn't fit into `vertex_buffer` and won't be drawn.
 pub fn set_vertex_buffer(
     chunk_mapping: &ChunkMapping,
-    vertex_buffer: &mut Vertices,
+    vertex_buffer: &mut [MyVertex],
     vertex_count: &mut usize,
-) {
+) -> usize {
     pub fn vert(xyz: Vec3, add: (f32, f32, f32)) -> MyVertex {
         MyVertex {
             position: (xyz + Vec3::from(add)) * CHUNK_SIZE_ONE as f32,
@@ -16,64 +18,97 @@ pub fn set_vertex_buffer(
     }
 
     let mut vertex_index = 0;
+    let mut left_out = 0;
 
-    for c in 0..chunk_mapping.0.len() {
-        if chunk_mapping.0[c] == 0 {
+    for c in 0..chunk_mapping.chunks.len() {
+        if chunk_mapping.chunks[c] == 0 {
             continue;
         }
 
-        let xyz = index_to_pos(c, CHUNK_COUNT_ONE);
-
-        vertex_buffer.0[vertex_index + 00] = vert(xyz, (1.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 01] = vert(xyz, (0.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 02] = vert(xyz, (1.0, 0.0, 0.0));
-        vertex_buffer.0[vertex_index + 03] = vert(xyz, (0.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 04] = vert(xyz, (1.0, 0.0, 0.0));
-        vertex_buffer.0[vertex_index + 05] = vert(xyz, (0.0, 0.0, 0.0));
-
-        vertex_buffer.0[vertex_index + 06] = vert(xyz, (1.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 07] = vert(xyz, (0.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 08] = vert(xyz, (1.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 09] = vert(xyz, (0.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 10] = vert(xyz, (1.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 11] = vert(xyz, (0.0, 0.0, 1.0));
-
-        vertex_buffer.0[vertex_index + 12] = vert(xyz, (0.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 13] = vert(xyz, (0.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 14] = vert(xyz, (0.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 15] = vert(xyz, (0.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 16] = vert(xyz, (0.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 17] = vert(xyz, (0.0, 0.0, 0.0));
-
-        vertex_buffer.0[vertex_index + 18] = vert(xyz, (1.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 19] = vert(xyz, (1.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 20] = vert(xyz, (1.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 21] = vert(xyz, (1.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 22] = vert(xyz, (1.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 23] = vert(xyz, (1.0, 0.0, 0.0));
-
-        vertex_buffer.0[vertex_index + 24] = vert(xyz, (1.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 25] = vert(xyz, (1.0, 0.0, 0.0));
-        vertex_buffer.0[vertex_index + 26] = vert(xyz, (0.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 27] = vert(xyz, (1.0, 0.0, 0.0));
-        vertex_buffer.0[vertex_index + 28] = vert(xyz, (0.0, 0.0, 1.0));
-        vertex_buffer.0[vertex_index + 29] = vert(xyz, (0.0, 0.0, 0.0));
-
-        vertex_buffer.0[vertex_index + 30] = vert(xyz, (1.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 31] = vert(xyz, (1.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 32] = vert(xyz, (0.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 33] = vert(xyz, (1.0, 1.0, 0.0));
-        vertex_buffer.0[vertex_index + 34] = vert(xyz, (0.0, 1.0, 1.0));
-        vertex_buffer.0[vertex_index + 35] = vert(xyz, (0.0, 1.0, 0.0));
+        if vertex_index + 36 > vertex_buffer.len() {
+            left_out += 1;
+            continue;
+        }
+
+        let xyz = index_to_pos(c, chunk_mapping.size.extent());
+
+        vertex_buffer[vertex_index] = vert(xyz, (1.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 1] = vert(xyz, (0.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 2] = vert(xyz, (1.0, 0.0, 0.0));
+        vertex_buffer[vertex_index + 3] = vert(xyz, (0.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 4] = vert(xyz, (1.0, 0.0, 0.0));
+        vertex_buffer[vertex_index + 5] = vert(xyz, (0.0, 0.0, 0.0));
+
+        vertex_buffer[vertex_index + 6] = vert(xyz, (1.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 7] = vert(xyz, (0.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 8] = vert(xyz, (1.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 9] = vert(xyz, (0.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 10] = vert(xyz, (1.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 11] = vert(xyz, (0.0, 0.0, 1.0));
+
+        vertex_buffer[vertex_index + 12] = vert(xyz, (0.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 13] = vert(xyz, (0.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 14] = vert(xyz, (0.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 15] = vert(xyz, (0.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 16] = vert(xyz, (0.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 17] = vert(xyz, (0.0, 0.0, 0.0));
+
+        vertex_buffer[vertex_index + 18] = vert(xyz, (1.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 19] = vert(xyz, (1.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 20] = vert(xyz, (1.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 21] = vert(xyz, (1.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 22] = vert(xyz, (1.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 23] = vert(xyz, (1.0, 0.0, 0.0));
+
+        vertex_buffer[vertex_index + 24] = vert(xyz, (1.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 25] = vert(xyz, (1.0, 0.0, 0.0));
+        vertex_buffer[vertex_index + 26] = vert(xyz, (0.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 27] = vert(xyz, (1.0, 0.0, 0.0));
+        vertex_buffer[vertex_index + 28] = vert(xyz, (0.0, 0.0, 1.0));
+        vertex_buffer[vertex_index + 29] = vert(xyz, (0.0, 0.0, 0.0));
+
+        vertex_buffer[vertex_index + 30] = vert(xyz, (1.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 31] = vert(xyz, (1.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 32] = vert(xyz, (0.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 33] = vert(xyz, (1.0, 1.0, 0.0));
+        vertex_buffer[vertex_index + 34] = vert(xyz, (0.0, 1.0, 1.0));
+        vertex_buffer[vertex_index + 35] = vert(xyz, (0.0, 1.0, 0.0));
 
         vertex_index += 36;
     }
 
     if *vertex_count > vertex_index {
-        for v in &mut vertex_buffer.0[vertex_index..*vertex_count] {
+        for v in &mut vertex_buffer[vertex_index..*vertex_count] {
             *v = MyVertex::default();
         }
     }
 
     *vertex_count = vertex_index;
+    left_out
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::{vertex_count, WorldSize};
+
+    use super::*;
+
+    #[test]
+    fn chunks_past_the_end_of_the_buffer_are_left_out() {
+        // 64 chunks, with room for a quarter of them.
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut vertices = vec![MyVertex::default(); vertex_count(size)];
+        let mut count = 0;
+
+        chunk_mapping.chunks.fill(1);
+        assert_eq!(set_vertex_buffer(&chunk_mapping, &mut vertices, &mut count), 48);
+        assert_eq!(count, 16 * 36);
+
+        chunk_mapping.chunks[10..].fill(0);
+        assert_eq!(set_vertex_buffer(&chunk_mapping, &mut vertices, &mut count), 0);
+        assert_eq!(count, 10 * 36);
+        assert!(vertices[count..].iter().all(|v| v.position == Vec3::ZERO));
+    }
 }
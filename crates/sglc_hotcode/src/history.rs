@@ -0,0 +1,550 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
+
+use glam::IVec3;
+use sglc_shared::{ChunkMapping, Voxels, WorldSize, CHUNK_EXTENT, CHUNK_SIZE, CHUNK_SIZE_ONE};
+
+use crate::chunk_allocator::ChunkAllocator;
+use crate::pos_to_index::index_to_pos;
+use crate::set_voxel::{chunk_index_of, set_voxel, voxel_index_of};
+
+const MAGIC: &[u8; 8] = b"SGLCHIST";
+const VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VoxelChange {
+    pub pos: IVec3,
+    pub before: u32,
+    pub after: u32,
+}
+
+// A whole chunk, stored when most of it changed or when it went from being an
+// air chunk to having its own slot (or back). `None` means an air chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkChange {
+    pub chunk: usize,
+    pub before: Option<Box<[u32; CHUNK_SIZE]>>,
+    pub after: Option<Box<[u32; CHUNK_SIZE]>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Operation {
+    pub voxels: Vec<VoxelChange>,
+    pub chunks: Vec<ChunkChange>,
+    allocated_before: HashMap<usize, bool>,
+}
+
+impl Operation {
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty() && self.chunks.is_empty()
+    }
+
+    pub fn set_voxel(
+        &mut self,
+        pos: IVec3,
+        unit: u32,
+        allocator: &mut ChunkAllocator,
+        chunk_mapping: &mut ChunkMapping,
+        voxels: &mut Voxels,
+    ) -> Option<usize> {
+        if !chunk_mapping.size.contains(pos) {
+            return None;
+        }
+
+        let chunk = chunk_index_of(pos, &chunk_mapping.size);
+        let slot = chunk_mapping.chunks[chunk] as usize;
+        let before = voxels.0[slot][voxel_index_of(pos)];
+
+        if before == unit {
+            return Some(slot);
+        }
+
+        let written = set_voxel(pos, unit, allocator, chunk_mapping, voxels)?;
+
+        self.allocated_before.entry(chunk).or_insert(slot != 0);
+        self.voxels.push(VoxelChange { pos, before, after: unit });
+
+        Some(written)
+    }
+
+    // Folds the recorded voxel changes into whole chunk changes where that is
+    // cheaper or where the chunk was allocated or released along the way.
+    pub fn finish(&mut self, chunk_mapping: &ChunkMapping, voxels: &Voxels) {
+        let size = chunk_mapping.size;
+        let mut by_chunk = HashMap::<usize, usize>::new();
+        for change in &self.voxels {
+            *by_chunk.entry(chunk_index_of(change.pos, &size)).or_default() += 1;
+        }
+
+        let mut snapshotted = Vec::new();
+        for (&chunk, &allocated_before) in &self.allocated_before {
+            let slot = chunk_mapping.chunks[chunk] as usize;
+            let allocated_after = slot != 0;
+            let changes = by_chunk.get(&chunk).copied().unwrap_or(0);
+
+            if !allocated_before && !allocated_after {
+                // allocated and released again, so nothing changed
+                snapshotted.push(chunk);
+                continue;
+            }
+
+            if allocated_before && allocated_after && changes <= CHUNK_SIZE / 4 {
+                continue;
+            }
+
+            let after = allocated_after.then(|| Box::new(voxels.0[slot]));
+            let before = allocated_before.then(|| {
+                let mut before = after.clone().unwrap_or_else(|| Box::new([0; CHUNK_SIZE]));
+                for change in self.voxels.iter().rev() {
+                    if chunk_index_of(change.pos, &size) == chunk {
+                        before[voxel_index_of(change.pos)] = change.before;
+                    }
+                }
+                before
+            });
+
+            snapshotted.push(chunk);
+            self.chunks.push(ChunkChange { chunk, before, after });
+        }
+
+        self.voxels.retain(|change| !snapshotted.contains(&chunk_index_of(change.pos, &size)));
+        self.chunks.sort_by_key(|change| change.chunk);
+        self.allocated_before.clear();
+    }
+
+    // Every voxel the operation touches, whole chunks included.
+    pub fn changed_positions(&self, size: &WorldSize) -> Vec<IVec3> {
+        let mut positions = self.voxels.iter().map(|change| change.pos).collect::<Vec<_>>();
+
+        for change in &self.chunks {
+            let origin = index_to_pos::<i32, IVec3>(change.chunk, size.extent()) * CHUNK_SIZE_ONE as i32;
+            positions.extend((0..CHUNK_SIZE).map(|i| origin + index_to_pos::<i32, IVec3>(i, CHUNK_EXTENT)));
+        }
+
+        positions
+    }
+
+    pub fn undo(&self, allocator: &mut ChunkAllocator, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        for change in self.voxels.iter().rev() {
+            set_voxel(change.pos, change.before, allocator, chunk_mapping, voxels);
+        }
+
+        for change in &self.chunks {
+            restore_chunk(change.chunk, change.before.as_deref(), allocator, chunk_mapping, voxels);
+        }
+    }
+
+    pub fn redo(&self, allocator: &mut ChunkAllocator, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        for change in &self.chunks {
+            restore_chunk(change.chunk, change.after.as_deref(), allocator, chunk_mapping, voxels);
+        }
+
+        for change in &self.voxels {
+            set_voxel(change.pos, change.after, allocator, chunk_mapping, voxels);
+        }
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        let chunk_data = self.chunks.iter()
+            .map(|c| c.before.is_some() as usize + c.after.is_some() as usize)
+            .sum::<usize>() * std::mem::size_of::<[u32; CHUNK_SIZE]>();
+
+        std::mem::size_of::<Self>()
+            + self.voxels.len() * std::mem::size_of::<VoxelChange>()
+            + self.chunks.len() * std::mem::size_of::<ChunkChange>()
+            + chunk_data
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(&(self.voxels.len() as u32).to_le_bytes())?;
+        for change in &self.voxels {
+            for v in change.pos.to_array() {
+                w.write_all(&v.to_le_bytes())?;
+            }
+            w.write_all(&change.before.to_le_bytes())?;
+            w.write_all(&change.after.to_le_bytes())?;
+        }
+
+        w.write_all(&(self.chunks.len() as u32).to_le_bytes())?;
+        for change in &self.chunks {
+            w.write_all(&(change.chunk as u32).to_le_bytes())?;
+            let flags = change.before.is_some() as u8 | (change.after.is_some() as u8) << 1;
+            w.write_all(&[flags])?;
+            for data in [&change.before, &change.after].into_iter().flatten() {
+                w.write_all(bytemuck::cast_slice(&data[..]))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Chunk indices are checked against `size`, the world the operation
+    // will be replayed in.
+    pub fn read_from(r: &mut impl Read, size: &WorldSize) -> io::Result<Self> {
+        let mut operation = Operation::default();
+
+        for _ in 0..read_u32(r)? {
+            let pos = IVec3::new(read_u32(r)? as i32, read_u32(r)? as i32, read_u32(r)? as i32);
+            let before = read_u32(r)?;
+            let after = read_u32(r)?;
+            operation.voxels.push(VoxelChange { pos, before, after });
+        }
+
+        for _ in 0..read_u32(r)? {
+            let chunk = read_u32(r)? as usize;
+            if chunk >= size.chunk_count() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("chunk {chunk} is outside of a {size} world"),
+                ));
+            }
+
+            let mut flags = [0];
+            r.read_exact(&mut flags)?;
+
+            let mut read_chunk = |present: bool| -> io::Result<Option<Box<[u32; CHUNK_SIZE]>>> {
+                if !present {
+                    return Ok(None);
+                }
+                let mut data = Box::new([0u32; CHUNK_SIZE]);
+                r.read_exact(bytemuck::cast_slice_mut(&mut data[..]))?;
+                Ok(Some(data))
+            };
+
+            let before = read_chunk(flags[0] & 1 != 0)?;
+            let after = read_chunk(flags[0] & 2 != 0)?;
+            operation.chunks.push(ChunkChange { chunk, before, after });
+        }
+
+        Ok(operation)
+    }
+}
+
+fn restore_chunk(
+    chunk: usize,
+    data: Option<&[u32; CHUNK_SIZE]>,
+    allocator: &mut ChunkAllocator,
+    chunk_mapping: &mut ChunkMapping,
+    voxels: &mut Voxels,
+) {
+    let slot = chunk_mapping.chunks[chunk] as usize;
+
+    match data {
+        None if slot != 0 => {
+            allocator.release(slot, voxels);
+            chunk_mapping.chunks[chunk] = 0;
+        },
+        None => {},
+        Some(data) => {
+            let slot = if slot == 0 {
+                let Some(slot) = allocator.allocate(voxels) else { return };
+                chunk_mapping.chunks[chunk] = slot as u32;
+                slot
+            } else {
+                let Some(slot) = allocator.make_unique(chunk, chunk_mapping, voxels) else { return };
+                slot
+            };
+
+            voxels.0[slot] = *data;
+        },
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// Undo/redo journal of edits, forgetting the oldest operations once the ones
+// it holds take up more than `budget` bytes.
+#[derive(Clone, Debug)]
+pub struct History {
+    undo: VecDeque<Operation>,
+    redo: Vec<Operation>,
+    budget: usize,
+    used: usize,
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            budget,
+            used: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.used = 0;
+    }
+
+    pub fn push(&mut self, operation: Operation) {
+        if operation.is_empty() {
+            return;
+        }
+
+        for dropped in self.redo.drain(..) {
+            self.used -= dropped.size_in_bytes();
+        }
+
+        self.used += operation.size_in_bytes();
+        self.undo.push_back(operation);
+
+        while self.used > self.budget && self.undo.len() > 1 {
+            let dropped = self.undo.pop_front().unwrap();
+            self.used -= dropped.size_in_bytes();
+        }
+    }
+
+    pub fn undo(&mut self, allocator: &mut ChunkAllocator, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool {
+        let Some(operation) = self.undo.pop_back() else { return false };
+
+        operation.undo(allocator, chunk_mapping, voxels);
+        self.redo.push(operation);
+
+        true
+    }
+
+    pub fn redo(&mut self, allocator: &mut ChunkAllocator, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool {
+        let Some(operation) = self.redo.pop() else { return false };
+
+        operation.redo(allocator, chunk_mapping, voxels);
+        self.undo.push_back(operation);
+
+        true
+    }
+
+    // The operation the next `undo` would revert, which is also the one the
+    // last `redo` applied.
+    pub fn last_undo(&self) -> Option<&Operation> {
+        self.undo.back()
+    }
+
+    // The operation the last `undo` reverted.
+    pub fn last_redo(&self) -> Option<&Operation> {
+        self.redo.last()
+    }
+
+    pub fn undo_count(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_count(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    // Only the operations that can still be undone are written, oldest first,
+    // so replaying them needs the world as it was before the first of them.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        w.write_all(&(self.undo.len() as u32).to_le_bytes())?;
+
+        for operation in &self.undo {
+            operation.write_to(w)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from(r: &mut impl Read, budget: usize, size: &WorldSize) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an edit history"));
+        }
+
+        let version = read_u32(r)?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported edit history version {version}"),
+            ));
+        }
+
+        let mut history = History::new(budget);
+        for _ in 0..read_u32(r)? {
+            let operation = Operation::read_from(r, size)?;
+            history.used += operation.size_in_bytes();
+            history.undo.push_back(operation);
+        }
+
+        // Dropping the oldest operations like `push` does would leave the rest
+        // without the world they start from, so it is all or nothing.
+        if history.used > budget && history.undo.len() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("edit history takes up {} bytes, over the budget of {budget}", history.used),
+            ));
+        }
+
+        Ok(history)
+    }
+
+    pub fn replay(&self, allocator: &mut ChunkAllocator, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        for operation in &self.undo {
+            operation.redo(allocator, chunk_mapping, voxels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+
+    use super::*;
+    use crate::clear::clear;
+    use crate::set_voxel::voxel_at;
+
+    fn world() -> (ChunkAllocator, Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        (ChunkAllocator::default(), chunk_mapping, voxels)
+    }
+
+    fn edit(
+        positions: &[IVec3],
+        unit: u32,
+        allocator: &mut ChunkAllocator,
+        chunk_mapping: &mut ChunkMapping,
+        voxels: &mut Voxels,
+    ) -> Operation {
+        let mut operation = Operation::default();
+        for &pos in positions {
+            operation.set_voxel(pos, unit, allocator, chunk_mapping, voxels);
+        }
+        operation.finish(chunk_mapping, voxels);
+        operation
+    }
+
+    #[test]
+    fn undo_and_redo_allocate_and_release_chunks() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let mut history = History::new(1 << 20);
+        let (a, b) = (IVec3::new(1, 2, 3), IVec3::new(20, 4, 4));
+        let chunk_b = chunk_index_of(b, &chunk_mapping.size);
+
+        history.push(edit(&[a], 5, &mut allocator, &mut chunk_mapping, &mut voxels));
+        history.push(edit(&[b], 6, &mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(allocator.allocated_count(), 2);
+
+        // Emptying the chunk again releases it, and undoing that allocates it.
+        history.push(edit(&[b], 0, &mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(chunk_mapping.chunks[chunk_b], 0);
+        assert_eq!(allocator.allocated_count(), 1);
+
+        assert!(history.undo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(voxel_at(b, &chunk_mapping, &voxels), 6);
+        assert_eq!(allocator.allocated_count(), 2);
+
+        assert!(history.undo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert!(history.undo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert!(!history.undo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert!(chunk_mapping.chunks.iter().all(|&slot| slot == 0));
+        assert_eq!(allocator.allocated_count(), 0);
+
+        assert!(history.redo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert!(history.redo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(voxel_at(a, &chunk_mapping, &voxels), 5);
+        assert_eq!(voxel_at(b, &chunk_mapping, &voxels), 6);
+        assert_eq!(allocator.allocated_count(), 2);
+
+        assert!(history.redo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert!(!history.redo(&mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(voxel_at(b, &chunk_mapping, &voxels), 2);
+        assert_eq!(allocator.allocated_count(), 1);
+    }
+
+    #[test]
+    fn push_forgets_the_oldest_operations_over_budget() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        // With the chunk already there every edit is a single voxel change,
+        // so they all take up the same room.
+        set_voxel(IVec3::splat(7), 3, &mut allocator, &mut chunk_mapping, &mut voxels);
+        let one = edit(&[IVec3::ZERO], 5, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(one.voxels.len(), 1);
+        let mut history = History::new(one.size_in_bytes() * 3);
+
+        for x in 0..7 {
+            history.push(edit(&[IVec3::new(x, 1, 0)], 6, &mut allocator, &mut chunk_mapping, &mut voxels));
+            assert!(history.used_bytes() <= one.size_in_bytes() * 3);
+        }
+
+        assert_eq!(history.undo_count(), 3);
+        assert_eq!(history.last_undo().unwrap().voxels[0].pos, IVec3::new(6, 1, 0));
+
+        // One operation bigger than the budget is still kept.
+        let mut history = History::new(1);
+        history.push(edit(&[IVec3::ONE], 7, &mut allocator, &mut chunk_mapping, &mut voxels));
+        assert_eq!(history.undo_count(), 1);
+    }
+
+    #[test]
+    fn push_clears_redo() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let mut history = History::new(1 << 20);
+
+        history.push(edit(&[IVec3::ZERO], 5, &mut allocator, &mut chunk_mapping, &mut voxels));
+        history.push(edit(&[IVec3::ONE], 5, &mut allocator, &mut chunk_mapping, &mut voxels));
+        history.undo(&mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(history.redo_count(), 1);
+        let used = history.used_bytes();
+
+        let operation = edit(&[IVec3::X], 6, &mut allocator, &mut chunk_mapping, &mut voxels);
+        let size = operation.size_in_bytes();
+        history.push(operation);
+        assert_eq!(history.redo_count(), 0);
+        assert_eq!(history.undo_count(), 2);
+        assert!(history.used_bytes() < used + size);
+        assert!(!history.redo(&mut allocator, &mut chunk_mapping, &mut voxels));
+    }
+
+    #[test]
+    fn read_from_rejects_histories_over_budget() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let mut history = History::new(1 << 20);
+        for x in 0..4 {
+            history.push(edit(&[IVec3::new(x, 0, 0)], 5, &mut allocator, &mut chunk_mapping, &mut voxels));
+        }
+
+        let mut bytes = Vec::new();
+        history.write_to(&mut bytes).unwrap();
+
+        let size = chunk_mapping.size;
+        let loaded = History::read_from(&mut bytes.as_slice(), history.used_bytes(), &size).unwrap();
+        assert_eq!(loaded.undo_count(), 4);
+        assert_eq!(loaded.used_bytes(), history.used_bytes());
+
+        let error = History::read_from(&mut bytes.as_slice(), history.used_bytes() - 1, &size).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_from_rejects_chunks_outside_of_the_world() {
+        let size = WorldSize::from_voxels(glam::UVec3::splat(16)).unwrap();
+        let mut operation = Operation::default();
+        operation.chunks.push(ChunkChange { chunk: size.chunk_count(), before: None, after: None });
+
+        let mut bytes = Vec::new();
+        operation.write_to(&mut bytes).unwrap();
+
+        let error = Operation::read_from(&mut bytes.as_slice(), &size).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        operation.chunks[0].chunk = size.chunk_count() - 1;
+        bytes.clear();
+        operation.write_to(&mut bytes).unwrap();
+        assert_eq!(Operation::read_from(&mut bytes.as_slice(), &size).unwrap(), operation);
+    }
+}
@@ -2,3 +2,16 @@ pub mod place_one_sphere;
 pub mod pos_to_index;
 pub mod create_vertex_buffer;
 pub mod clear;
+pub mod chunk_allocator;
+pub mod set_voxel;
+pub mod raycast;
+pub mod history;
+pub mod brush;
+pub mod prefab;
+pub mod world_file;
+pub mod shade;
+pub mod light;
+pub mod post;
+pub mod mesh;
+pub mod overlay;
+pub mod console;
@@ -0,0 +1,448 @@
+use std::collections::VecDeque;
+
+use glam::IVec3;
+use sglc_shared::light::{LightChunks, LightMapping, LIGHT_CHUNK_CAPACITY, LIGHT_WORDS};
+use sglc_shared::palette::Palette;
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_EXTENT, CHUNK_SIZE_ONE};
+
+use crate::pos_to_index::index_to_pos;
+use crate::set_voxel::{chunk_index_of, voxel_at, voxel_index_of};
+
+// Minecraft style block light: emissive voxels are sources, and light floods
+// out of them through air and glass, one level darker with every step.
+
+pub const MAX_LIGHT: u8 = 15;
+
+const NEIGHBORS: [IVec3; 6] = [
+    IVec3::X, IVec3::NEG_X,
+    IVec3::Y, IVec3::NEG_Y,
+    IVec3::Z, IVec3::NEG_Z,
+];
+
+// Hands out slots in `LightChunks`. Slot 0 is the dark chunk and is never
+// handed out, and chunks that go dark again give their slot back. Once all of
+// them are in use, light that would need another one is dropped.
+#[derive(Clone, Debug)]
+pub struct LightAllocator {
+    next: usize,
+    free: Vec<usize>,
+}
+
+impl Default for LightAllocator {
+    fn default() -> Self {
+        Self { next: 1, free: Vec::new() }
+    }
+}
+
+impl LightAllocator {
+    fn allocate(&mut self, light_chunks: &mut LightChunks) -> Option<usize> {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None if self.next < LIGHT_CHUNK_CAPACITY => {
+                self.next += 1;
+                self.next - 1
+            },
+            None => return None,
+        };
+
+        light_chunks.0[slot] = [0; LIGHT_WORDS];
+
+        Some(slot)
+    }
+
+    // Slots are only given back once they are all dark, so they are zeroed
+    // already.
+    fn release(&mut self, slot: usize) {
+        self.free.push(slot);
+    }
+
+    // Makes everything dark again.
+    pub fn clear(&mut self, light_mapping: &mut LightMapping, light_chunks: &mut LightChunks) {
+        light_mapping.chunks.fill(0);
+        for chunk in &mut light_chunks.0[..self.next] {
+            *chunk = [0; LIGHT_WORDS];
+        }
+
+        self.next = 1;
+        self.free.clear();
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.next - 1 - self.free.len()
+    }
+}
+
+pub fn light_at(pos: IVec3, light_mapping: &LightMapping, light_chunks: &LightChunks) -> u8 {
+    if !light_mapping.size.contains(pos) {
+        return 0;
+    }
+
+    let slot = light_mapping.chunks[chunk_index_of(pos, &light_mapping.size)] as usize;
+    let index = voxel_index_of(pos);
+
+    (light_chunks.0[slot][index / 4] >> (index % 4 * 8)) as u8
+}
+
+fn set_light(
+    pos: IVec3,
+    level: u8,
+    allocator: &mut LightAllocator,
+    light_mapping: &mut LightMapping,
+    light_chunks: &mut LightChunks,
+) {
+    if !light_mapping.size.contains(pos) {
+        return;
+    }
+
+    let chunk_index = chunk_index_of(pos, &light_mapping.size);
+    let mut slot = light_mapping.chunks[chunk_index] as usize;
+
+    if slot == 0 {
+        if level == 0 {
+            return;
+        }
+
+        let Some(allocated) = allocator.allocate(light_chunks) else { return };
+        slot = allocated;
+        light_mapping.chunks[chunk_index] = slot as u32;
+    }
+
+    let index = voxel_index_of(pos);
+    let word = &mut light_chunks.0[slot][index / 4];
+    let shift = index % 4 * 8;
+    *word = *word & !(0xff << shift) | (level as u32) << shift;
+
+    if level == 0 && light_chunks.0[slot].iter().all(|&word| word == 0) {
+        allocator.release(slot);
+        light_mapping.chunks[chunk_index] = 0;
+    }
+}
+
+// The level a unit lights itself up with.
+fn emission(unit: u32, palette: &Palette) -> u8 {
+    if unit <= 2 {
+        return 0;
+    }
+
+    palette.0.get(unit as usize).map_or(0, |material| {
+        (material.emissive * MAX_LIGHT as f32).round().clamp(0.0, MAX_LIGHT as f32) as u8
+    })
+}
+
+fn lets_light_through(pos: IVec3, palette: &Palette, chunk_mapping: &ChunkMapping, voxels: &Voxels) -> bool {
+    match voxel_at(pos, chunk_mapping, voxels) {
+        0 | 2 => true,
+        1 => false,
+        unit => palette.0.get(unit as usize).is_some_and(|material| material.transparency > 0.0),
+    }
+}
+
+struct Propagation<'a> {
+    palette: &'a Palette,
+    chunk_mapping: &'a ChunkMapping,
+    voxels: &'a Voxels,
+    allocator: &'a mut LightAllocator,
+    light_mapping: &'a mut LightMapping,
+    light_chunks: &'a mut LightChunks,
+}
+
+impl Propagation<'_> {
+    fn light_at(&self, pos: IVec3) -> u8 {
+        light_at(pos, self.light_mapping, self.light_chunks)
+    }
+
+    fn set_light(&mut self, pos: IVec3, level: u8) {
+        set_light(pos, level, self.allocator, self.light_mapping, self.light_chunks);
+    }
+
+    fn emission_at(&self, pos: IVec3) -> u8 {
+        emission(voxel_at(pos, self.chunk_mapping, self.voxels), self.palette)
+    }
+
+    // Lights up a source and queues it to spread from.
+    fn seed(&mut self, pos: IVec3, spread: &mut VecDeque<IVec3>) {
+        let level = self.emission_at(pos);
+        if level > self.light_at(pos) {
+            self.set_light(pos, level);
+            spread.push_back(pos);
+        }
+    }
+
+    fn spread(&mut self, mut queue: VecDeque<IVec3>) {
+        while let Some(pos) = queue.pop_front() {
+            let level = self.light_at(pos);
+            if level <= 1 {
+                continue;
+            }
+
+            for neighbor in NEIGHBORS.map(|n| pos + n) {
+                if self.light_at(neighbor) + 1 < level
+                    && lets_light_through(neighbor, self.palette, self.chunk_mapping, self.voxels)
+                {
+                    self.set_light(neighbor, level - 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    // Takes away the light that came from `positions`, queueing up the
+    // light around the darkened area that has to flow back into it.
+    fn darken(&mut self, positions: &[IVec3], spread: &mut VecDeque<IVec3>) {
+        let mut queue = VecDeque::new();
+
+        for &pos in positions {
+            let level = self.light_at(pos);
+            if level > 0 {
+                self.set_light(pos, 0);
+                queue.push_back((pos, level));
+            }
+        }
+
+        while let Some((pos, level)) = queue.pop_front() {
+            for neighbor in NEIGHBORS.map(|n| pos + n) {
+                let neighbor_level = self.light_at(neighbor);
+                if neighbor_level == 0 {
+                    continue;
+                }
+
+                if neighbor_level < level {
+                    self.set_light(neighbor, 0);
+                    queue.push_back((neighbor, neighbor_level));
+                    self.seed(neighbor, spread);
+                } else {
+                    spread.push_back(neighbor);
+                }
+            }
+        }
+    }
+}
+
+// Throws the light away and floods it again from every emissive voxel. Needed
+// after the world or the palette got replaced.
+pub fn relight(
+    palette: &Palette,
+    chunk_mapping: &ChunkMapping,
+    voxels: &Voxels,
+    allocator: &mut LightAllocator,
+    light_mapping: &mut LightMapping,
+    light_chunks: &mut LightChunks,
+) {
+    allocator.clear(light_mapping, light_chunks);
+
+    let emitting = (0..palette.0.len() as u32).map(|unit| emission(unit, palette)).collect::<Vec<_>>();
+    if emitting.iter().all(|&level| level == 0) {
+        return;
+    }
+
+    let mut propagation = Propagation { palette, chunk_mapping, voxels, allocator, light_mapping, light_chunks };
+    let mut spread = VecDeque::new();
+
+    for (chunk, &slot) in chunk_mapping.chunks.iter().enumerate() {
+        if slot == 0 {
+            continue;
+        }
+
+        let origin = index_to_pos::<i32, IVec3>(chunk, chunk_mapping.size.extent()) * CHUNK_SIZE_ONE as i32;
+        for (i, &unit) in voxels.0[slot as usize].iter().enumerate() {
+            if emitting.get(unit as usize).is_some_and(|&level| level > 0) {
+                propagation.seed(origin + index_to_pos::<i32, IVec3>(i, CHUNK_EXTENT), &mut spread);
+            }
+        }
+    }
+
+    propagation.spread(spread);
+}
+
+// Brings the light up to date after the voxels at `changed` were edited,
+// only touching the area the edit can have an effect on.
+pub fn update_light(
+    changed: &[IVec3],
+    palette: &Palette,
+    chunk_mapping: &ChunkMapping,
+    voxels: &Voxels,
+    allocator: &mut LightAllocator,
+    light_mapping: &mut LightMapping,
+    light_chunks: &mut LightChunks,
+) {
+    let mut propagation = Propagation { palette, chunk_mapping, voxels, allocator, light_mapping, light_chunks };
+    let mut spread = VecDeque::new();
+
+    propagation.darken(changed, &mut spread);
+
+    for &pos in changed {
+        propagation.seed(pos, &mut spread);
+
+        // Removed voxels let the light around them in.
+        for neighbor in NEIGHBORS.map(|n| pos + n) {
+            if propagation.light_at(neighbor) > 1 {
+                spread.push_back(neighbor);
+            }
+        }
+    }
+
+    propagation.spread(spread);
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::WorldSize;
+
+    use super::*;
+    use crate::chunk_allocator::ChunkAllocator;
+    use crate::clear::clear;
+    use crate::set_voxel::set_voxel;
+
+    const LAMP: u32 = 9;
+    const STONE: u32 = 5;
+    const SOURCE: IVec3 = IVec3::new(8, 8, 8);
+
+    // A 32³ world with a lamp at `SOURCE`, kept lit with `update_light`.
+    struct World {
+        palette: Palette,
+        allocator: ChunkAllocator,
+        chunk_mapping: Box<ChunkMapping>,
+        voxels: Box<Voxels>,
+        light_allocator: LightAllocator,
+        light_mapping: Box<LightMapping>,
+        light_chunks: Box<LightChunks>,
+    }
+
+    impl World {
+        fn new() -> Self {
+            let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+            let mut palette = Palette::default();
+            palette.0[LAMP as usize].emissive = 1.0;
+
+            let mut world = Self {
+                palette,
+                allocator: ChunkAllocator::default(),
+                chunk_mapping: ChunkMapping::new(size),
+                voxels: Voxels::new(size),
+                light_allocator: LightAllocator::default(),
+                light_mapping: LightMapping::new(size),
+                light_chunks: LightChunks::new(),
+            };
+            clear(&mut world.chunk_mapping, &mut world.voxels);
+            world.set(&[SOURCE], LAMP);
+            world
+        }
+
+        fn set(&mut self, positions: &[IVec3], unit: u32) {
+            for &pos in positions {
+                set_voxel(pos, unit, &mut self.allocator, &mut self.chunk_mapping, &mut self.voxels);
+            }
+
+            update_light(
+                positions,
+                &self.palette,
+                &self.chunk_mapping,
+                &self.voxels,
+                &mut self.light_allocator,
+                &mut self.light_mapping,
+                &mut self.light_chunks,
+            );
+        }
+
+        fn light(&self, pos: IVec3) -> u8 {
+            light_at(pos, &self.light_mapping, &self.light_chunks)
+        }
+
+        // The same world lit from scratch, which incremental updates have to match.
+        fn assert_matches_relight(&self) {
+            let mut allocator = LightAllocator::default();
+            let mut light_mapping = LightMapping::new(self.chunk_mapping.size);
+            let mut light_chunks = LightChunks::new();
+            relight(
+                &self.palette,
+                &self.chunk_mapping,
+                &self.voxels,
+                &mut allocator,
+                &mut light_mapping,
+                &mut light_chunks,
+            );
+
+            for pos in every_pos() {
+                assert_eq!(self.light(pos), light_at(pos, &light_mapping, &light_chunks), "at {pos}");
+            }
+
+            // Chunks that went dark along the way don't hold on to a slot.
+            assert_eq!(self.light_allocator.chunk_count(), allocator.chunk_count());
+        }
+    }
+
+    fn every_pos() -> impl Iterator<Item = IVec3> {
+        (0..32 * 32 * 32).map(|i| index_to_pos::<i32, IVec3>(i, [32; 3]))
+    }
+
+    fn wall(x: i32) -> Vec<IVec3> {
+        (0..32).flat_map(|y| (0..32).map(move |z| IVec3::new(x, y, z))).collect()
+    }
+
+    #[test]
+    fn light_falls_off_one_level_per_step() {
+        let world = World::new();
+
+        assert_eq!(world.light(SOURCE), MAX_LIGHT);
+        for step in 1..=MAX_LIGHT as i32 {
+            assert_eq!(world.light(SOURCE + IVec3::X * step), MAX_LIGHT - step as u8);
+        }
+        assert_eq!(world.light(SOURCE + IVec3::new(3, -3, 0)), MAX_LIGHT - 6);
+        assert_eq!(world.light(SOURCE + IVec3::new(1, 2, 3)), MAX_LIGHT - 6);
+    }
+
+    #[test]
+    fn solid_voxels_stop_light() {
+        let mut world = World::new();
+        world.set(&wall(12), STONE);
+
+        assert_eq!(world.light(IVec3::new(11, 8, 8)), MAX_LIGHT - 3);
+        assert_eq!(world.light(IVec3::new(12, 8, 8)), 0);
+        assert!(wall(13).into_iter().all(|pos| world.light(pos) == 0));
+        world.assert_matches_relight();
+    }
+
+    #[test]
+    fn update_light_follows_removed_and_placed_blockers() {
+        let mut world = World::new();
+        world.set(&wall(12), STONE);
+
+        // Light comes through a hole, going around to the rest of the other side.
+        let hole = IVec3::new(12, 8, 8);
+        world.set(&[hole], 0);
+        assert_eq!(world.light(hole), MAX_LIGHT - 4);
+        assert_eq!(world.light(IVec3::new(13, 8, 8)), MAX_LIGHT - 5);
+        assert_eq!(world.light(IVec3::new(13, 8, 10)), MAX_LIGHT - 7);
+        world.assert_matches_relight();
+
+        // Closing it darkens everything behind the wall again.
+        world.set(&[hole], STONE);
+        assert!(wall(13).into_iter().all(|pos| world.light(pos) == 0));
+        world.assert_matches_relight();
+
+        // And without the lamp nothing is lit.
+        world.set(&[SOURCE], 0);
+        assert!(every_pos().all(|pos| world.light(pos) == 0));
+        assert_eq!(world.light_allocator.chunk_count(), 0);
+    }
+
+    #[test]
+    fn moving_a_lamp_around_reuses_light_slots() {
+        let mut world = World::new();
+        let mut lamp = SOURCE;
+
+        for step in 1..=40 {
+            world.set(&[lamp], 0);
+            lamp = IVec3::new(step * 7 % 32, step * 3 % 32, step * 13 % 32);
+            world.set(&[lamp], LAMP);
+
+            if step % 10 == 0 {
+                world.assert_matches_relight();
+            }
+        }
+
+        world.set(&[lamp], 0);
+        assert_eq!(world.light_allocator.chunk_count(), 0);
+    }
+}
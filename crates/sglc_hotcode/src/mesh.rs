@@ -0,0 +1,47 @@
+use glam::Vec3;
+use sglc_shared::{MeshVertex, MeshVertices};
+
+// Box between the two corners, as 12 triangles.
+pub fn push_box(min: Vec3, max: Vec3, color: Vec3, vertices: &mut Vec<MeshVertex>) {
+    let corner = |x: bool, y: bool, z: bool| Vec3::new(
+        if x { max.x } else { min.x },
+        if y { max.y } else { min.y },
+        if z { max.z } else { min.z },
+    );
+
+    for axis in 0..3 {
+        for side in [false, true] {
+            // The four corners of the face, going around it.
+            let face = [(false, false), (true, false), (true, true), (false, true)].map(|(u, v)| match axis {
+                0 => corner(side, u, v),
+                1 => corner(v, side, u),
+                _ => corner(u, v, side),
+            });
+
+            for i in [0, 1, 2, 0, 2, 3] {
+                vertices.push(MeshVertex { position: face[i], color });
+            }
+        }
+    }
+}
+
+// Red, green and blue bars along x, y and z, starting at `at`.
+pub fn axes_gizmo(at: Vec3, length: f32) -> Vec<MeshVertex> {
+    let mut vertices = Vec::new();
+    let thickness = Vec3::splat(length * 0.05);
+
+    for (axis, color) in [(Vec3::X, Vec3::X), (Vec3::Y, Vec3::Y), (Vec3::Z, Vec3::Z)] {
+        push_box(at - thickness, at + axis * length + thickness, color, &mut vertices);
+    }
+
+    vertices
+}
+
+// Copies the vertices into the buffer the mesh pipeline draws, collapsing the
+// rest so it draws nothing. Vertices that don't fit are left out.
+pub fn set_mesh_buffer(vertices: &[MeshVertex], mesh_buffer: &mut MeshVertices) {
+    let count = vertices.len().min(mesh_buffer.0.len());
+
+    mesh_buffer.0[..count].copy_from_slice(&vertices[..count]);
+    mesh_buffer.0[count..].fill(MeshVertex::default());
+}
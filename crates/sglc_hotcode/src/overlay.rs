@@ -0,0 +1,168 @@
+use glam::Vec3;
+
+// Text and panels drawn on the CPU into an RGBA8 image the size of the screen,
+// which post processing puts on top of everything. Pixels are packed like
+// GLSL's `unpackUnorm4x8` reads them, red in the lowest byte.
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+// Glyphs plus the space between them.
+pub const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+pub const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+// Rows top to bottom, the leftmost pixel in the highest bit. Covers ' ' to '`'
+// and then '{' to '~', lowercase letters are drawn as uppercase ones.
+const FONT: [[u8; GLYPH_HEIGHT]; 69] = [
+    [0b000, 0b000, 0b000, 0b000, 0b000], // ' '
+    [0b010, 0b010, 0b010, 0b000, 0b010], // !
+    [0b101, 0b101, 0b000, 0b000, 0b000], // "
+    [0b101, 0b111, 0b101, 0b111, 0b101], // #
+    [0b011, 0b110, 0b010, 0b011, 0b110], // $
+    [0b101, 0b001, 0b010, 0b100, 0b101], // %
+    [0b010, 0b101, 0b010, 0b101, 0b011], // &
+    [0b010, 0b010, 0b000, 0b000, 0b000], // '
+    [0b001, 0b010, 0b010, 0b010, 0b001], // (
+    [0b100, 0b010, 0b010, 0b010, 0b100], // )
+    [0b000, 0b101, 0b010, 0b101, 0b000], // *
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+    [0b000, 0b000, 0b000, 0b010, 0b100], // ,
+    [0b000, 0b000, 0b111, 0b000, 0b000], // -
+    [0b000, 0b000, 0b000, 0b000, 0b010], // .
+    [0b001, 0b001, 0b010, 0b100, 0b100], // /
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b001, 0b001], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b000, 0b010, 0b000, 0b010, 0b000], // :
+    [0b000, 0b010, 0b000, 0b010, 0b100], // ;
+    [0b001, 0b010, 0b100, 0b010, 0b001], // <
+    [0b000, 0b111, 0b000, 0b111, 0b000], // =
+    [0b100, 0b010, 0b001, 0b010, 0b100], // >
+    [0b111, 0b001, 0b011, 0b000, 0b010], // ?
+    [0b111, 0b101, 0b111, 0b100, 0b111], // @
+    [0b010, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b011, 0b100, 0b100, 0b100, 0b011], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b110, 0b100, 0b111], // E
+    [0b111, 0b100, 0b110, 0b100, 0b100], // F
+    [0b011, 0b100, 0b101, 0b101, 0b011], // G
+    [0b101, 0b101, 0b111, 0b101, 0b101], // H
+    [0b111, 0b010, 0b010, 0b010, 0b111], // I
+    [0b001, 0b001, 0b001, 0b101, 0b010], // J
+    [0b101, 0b101, 0b110, 0b101, 0b101], // K
+    [0b100, 0b100, 0b100, 0b100, 0b111], // L
+    [0b101, 0b111, 0b111, 0b101, 0b101], // M
+    [0b110, 0b101, 0b101, 0b101, 0b101], // N
+    [0b010, 0b101, 0b101, 0b101, 0b010], // O
+    [0b110, 0b101, 0b110, 0b100, 0b100], // P
+    [0b010, 0b101, 0b101, 0b110, 0b011], // Q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // R
+    [0b011, 0b100, 0b010, 0b001, 0b110], // S
+    [0b111, 0b010, 0b010, 0b010, 0b010], // T
+    [0b101, 0b101, 0b101, 0b101, 0b111], // U
+    [0b101, 0b101, 0b101, 0b101, 0b010], // V
+    [0b101, 0b101, 0b111, 0b111, 0b101], // W
+    [0b101, 0b101, 0b010, 0b101, 0b101], // X
+    [0b101, 0b101, 0b010, 0b010, 0b010], // Y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // Z
+    [0b011, 0b010, 0b010, 0b010, 0b011], // [
+    [0b100, 0b100, 0b010, 0b001, 0b001], // \
+    [0b110, 0b010, 0b010, 0b010, 0b110], // ]
+    [0b010, 0b101, 0b000, 0b000, 0b000], // ^
+    [0b000, 0b000, 0b000, 0b000, 0b111], // _
+    [0b100, 0b010, 0b000, 0b000, 0b000], // `
+    [0b011, 0b010, 0b110, 0b010, 0b011], // {
+    [0b010, 0b010, 0b010, 0b010, 0b010], // |
+    [0b110, 0b010, 0b011, 0b010, 0b110], // }
+    [0b000, 0b011, 0b110, 0b000, 0b000], // ~
+];
+
+fn glyph(c: char) -> &'static [u8; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    match c {
+        ' '..='`' => &FONT[c as usize - ' ' as usize],
+        '{'..='~' => &FONT[c as usize - '{' as usize + 65],
+        _ => &FONT['?' as usize - ' ' as usize],
+    }
+}
+
+pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    u32::from_le_bytes([r, g, b, a])
+}
+
+pub const WHITE: u32 = rgba(255, 255, 255, 255);
+pub const PANEL: u32 = rgba(0, 0, 0, 160);
+
+// Puts an overlay pixel over a color that's already sRGB encoded, the way
+// post processing does.
+pub fn blend_overlay(display: Vec3, pixel: u32) -> Vec3 {
+    let [r, g, b, a] = pixel.to_le_bytes().map(|c| c as f32 / 255.0);
+    display.lerp(Vec3::new(r, g, b), a)
+}
+
+pub fn text_width(text: &str) -> usize {
+    text.chars().count() * CELL_WIDTH
+}
+
+pub struct Canvas<'a> {
+    pub pixels: &'a mut [u32],
+    pub width: usize,
+}
+
+impl Canvas<'_> {
+    pub fn height(&self) -> usize {
+        self.pixels.len() / self.width
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    // Clipped to the canvas.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height());
+
+        for row in y.min(y_end)..y_end {
+            self.pixels[row * self.width + x.min(x_end)..row * self.width + x_end].fill(color);
+        }
+    }
+
+    // Draws one line of text with its top left corner at `x`, `y`.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: u32) {
+        let height = self.height();
+
+        for (i, c) in text.chars().enumerate() {
+            let left = x + i * CELL_WIDTH;
+
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    let (px, py) = (left + column, y + row);
+                    if bits >> (GLYPH_WIDTH - 1 - column) & 1 == 1 && px < self.width && py < height {
+                        self.pixels[py * self.width + px] = color;
+                    }
+                }
+            }
+        }
+    }
+
+    // Lines of text on a dark panel, with a pixel of room around them.
+    // Returns where the panel ends, to put the next one below it.
+    pub fn draw_panel(&mut self, x: usize, y: usize, lines: &[String]) -> usize {
+        let width = lines.iter().map(|line| text_width(line)).max().unwrap_or(0) + 1;
+        self.fill_rect(x, y, width + 1, lines.len() * CELL_HEIGHT + 1, PANEL);
+
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_text(x + 1, y + 1 + i * CELL_HEIGHT, line, WHITE);
+        }
+
+        y + lines.len() * CELL_HEIGHT + 1
+    }
+}
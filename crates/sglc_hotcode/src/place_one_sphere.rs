@@ -1,14 +1,16 @@
-use sglc_shared::{WORLD_SIZE_ONE, CHUNK_COUNT_ONE, CHUNK_SIZE_ONE, ChunkMapping, Voxels};
+use sglc_shared::{CHUNK_EXTENT, CHUNK_SIZE_ONE, ChunkMapping, Voxels};
 use crate::pos_to_index::pos_to_index;
 use glam::IVec3;
 
 pub fn place_one_sphere(n: usize, new_chunk: &mut usize, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
-    let radius = fastrand::i32(20..60);
+    let world = chunk_mapping.size.voxels();
+    // Smaller spheres in thin worlds, so they still fit.
+    let radius = fastrand::i32(20..60).min(world.min_element() / 2 - 1);
     let radius_squared = radius * radius;
     let center = IVec3::new(
-        fastrand::i32((0 + radius)..(WORLD_SIZE_ONE as i32 - radius)),
-        fastrand::i32((0 + radius)..(WORLD_SIZE_ONE as i32 - radius)),
-        fastrand::i32((0 + radius)..(WORLD_SIZE_ONE as i32 - radius)),
+        fastrand::i32(radius..(world.x - radius)),
+        fastrand::i32(radius..(world.y - radius)),
+        fastrand::i32(radius..(world.z - radius)),
     );
 
     let corner1 = center - IVec3::ONE * radius;
@@ -19,7 +21,13 @@ pub fn place_one_sphere(n: usize, new_chunk: &mut usize, chunk_mapping: &mut Chu
     for cx in chunk1.x..=chunk2.x {
         for cy in chunk1.y..=chunk2.y {
             for cz in chunk1.z..=chunk2.z {
-                let chunk_index = pos_to_index((cx, cy, cz), CHUNK_COUNT_ONE);
+                // Every chunk a sphere touches gets a new slot, so small
+                // worlds run out of them.
+                if *new_chunk >= voxels.0.len() {
+                    return;
+                }
+
+                let chunk_index = pos_to_index((cx, cy, cz), chunk_mapping.size.extent());
                 let chunk = IVec3::new(cx, cy, cz) * CHUNK_SIZE_ONE as i32;
 
                 let mut placed_one = false;
@@ -38,7 +46,7 @@ pub fn place_one_sphere(n: usize, new_chunk: &mut usize, chunk_mapping: &mut Chu
                             let dist_squared = (voxel_in_chunk - center).length_squared();
 
                             if dist_squared < radius_squared {
-                                voxels.0[*new_chunk][pos_to_index(voxel, CHUNK_SIZE_ONE)] = 
+                                voxels.0[*new_chunk][pos_to_index(voxel, CHUNK_EXTENT)] = 
                                     ((dist_squared & 10) >> 1) as u32 + 3 + n as u32 * 2;
                                 placed_one = true;
                             }
@@ -47,7 +55,7 @@ pub fn place_one_sphere(n: usize, new_chunk: &mut usize, chunk_mapping: &mut Chu
                 }
 
                 if placed_one {
-                    chunk_mapping.0[chunk_index] = *new_chunk as u32;
+                    chunk_mapping.chunks[chunk_index] = *new_chunk as u32;
                     *new_chunk += 1;
                 }
             }
@@ -1,10 +1,11 @@
-use std::ops::{Mul, Div, Add, Rem};
 use num::{traits::cast, NumCast};
 
-pub fn index_to_pos<T: NumCast, U: From<(T, T, T)>>(c: usize, by: usize) -> U {
-    let z = c / (by * by);
-    let y = (c / by) % by;
-    let x = c % by;
+// `extent` is how many there are along x, y and z, x varies fastest.
+pub fn index_to_pos<T: NumCast, U: From<(T, T, T)>>(c: usize, extent: [usize; 3]) -> U {
+    let [ex, ey, _] = extent;
+    let z = c / (ex * ey);
+    let y = (c / ex) % ey;
+    let x = c % ex;
 
     unsafe {
         (
@@ -15,14 +16,15 @@ pub fn index_to_pos<T: NumCast, U: From<(T, T, T)>>(c: usize, by: usize) -> U {
     }
 }
 
-pub fn pos_to_index<T: Into<(U, U, U)>, U: NumCast>(c: T, by: usize) -> usize {
+pub fn pos_to_index<T: Into<(U, U, U)>, U: NumCast>(c: T, extent: [usize; 3]) -> usize {
     let (x, y, z) = c.into();
+    let [ex, ey, _] = extent;
 
     unsafe {
         let x = cast::<U, usize>(x).unwrap_unchecked();
         let y = cast::<U, usize>(y).unwrap_unchecked();
         let z = cast::<U, usize>(z).unwrap_unchecked();
         
-        z * by * by + y * by + x
+        z * ex * ey + y * ex + x
     }
 }
@@ -0,0 +1,168 @@
+use glam::{IVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
+use sglc_shared::palette::Palette;
+use sglc_shared::post_settings::PostSettings;
+
+use crate::overlay::blend_overlay;
+
+// CPU version of the post processing shader, turning the HDR colors `render`
+// returns into what ends up on screen, sRGB encoded.
+
+// Bloom gathers from a grid of taps this many pixels apart.
+const BLOOM_STEP: i32 = 2;
+const BLOOM_TAPS: i32 = 4;
+const BLOOM_SIGMA: f32 = 4.0;
+
+const BAYER: [f32; 16] = [
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0,
+];
+
+// Units below this aren't drawn, so their colors are left out of dithering.
+const FIRST_DRAWN_UNIT: usize = 3;
+
+pub fn tone_map(color: Vec3, tone_mapping: u32) -> Vec3 {
+    match tone_mapping {
+        1 => color / (color + 1.0),
+        // Narkowicz's fit of the ACES filmic curve.
+        2 => ((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)).clamp(Vec3::ZERO, Vec3::ONE),
+        _ => color.clamp(Vec3::ZERO, Vec3::ONE),
+    }
+}
+
+pub fn linear_to_srgb(color: Vec3) -> Vec3 {
+    let f = |c: f32| if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    Vec3::new(f(color.x), f(color.y), f(color.z))
+}
+
+pub fn srgb_to_linear(color: Vec3) -> Vec3 {
+    let f = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    Vec3::new(f(color.x), f(color.y), f(color.z))
+}
+
+fn bloom(pixels: &[Vec4], width: u32, height: u32, pixel: IVec2, threshold: f32) -> Vec3 {
+    let max = IVec2::new(width as i32 - 1, height as i32 - 1);
+    let mut sum = Vec3::ZERO;
+    let mut weights = 0.0;
+
+    for y in -BLOOM_TAPS..=BLOOM_TAPS {
+        for x in -BLOOM_TAPS..=BLOOM_TAPS {
+            let offset = IVec2::new(x, y) * BLOOM_STEP;
+            let at = (pixel + offset).clamp(IVec2::ZERO, max);
+            let weight = (-(offset.length_squared() as f32) / (2.0 * BLOOM_SIGMA * BLOOM_SIGMA)).exp();
+            let color = pixels[(at.x + at.y * width as i32) as usize].xyz();
+
+            sum += (color - threshold).max(Vec3::ZERO) * weight;
+            weights += weight;
+        }
+    }
+
+    sum / weights
+}
+
+// The palette color closest to `color`, palette colors counting as sRGB
+// encoded like pixel art palettes are.
+fn nearest_palette_color(color: Vec3, palette: &Palette) -> Vec3 {
+    palette.0[FIRST_DRAWN_UNIT..]
+        .iter()
+        .map(|material| Vec4::from(material.color).xyz())
+        .min_by(|a, b| a.distance_squared(color).total_cmp(&b.distance_squared(color)))
+        .unwrap_or(color)
+}
+
+pub fn post_process(
+    pixels: &[Vec4],
+    width: u32,
+    height: u32,
+    settings: &PostSettings,
+    palette: &Palette,
+    overlay: &[u32],
+) -> Vec<Vec4> {
+    let size = Vec2::new(width as f32, height as f32);
+    let exposure = settings.exposure.exp2();
+
+    (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+        let pixel = IVec2::new(x as i32, y as i32);
+        let mut color = pixels[(x + y * width) as usize].xyz();
+
+        if settings.bloom_strength > 0.0 {
+            color += bloom(pixels, width, height, pixel, settings.bloom_threshold) * settings.bloom_strength;
+        }
+
+        color = tone_map(color * exposure, settings.tone_mapping);
+
+        let centered = (pixel.as_vec2() + 0.5) / size - 0.5;
+        color *= 1.0 - settings.vignette * centered.length_squared() * 2.0;
+
+        let mut display = linear_to_srgb(color);
+
+        if settings.dither == 1 {
+            let threshold = BAYER[(x % 4 + y % 4 * 4) as usize] / 16.0;
+            display = nearest_palette_color(display + (threshold - 0.5) / 8.0, palette);
+        }
+
+        display = blend_overlay(display, overlay[(x + y * width) as usize]);
+
+        display.extend(1.0)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overlay::{rgba, Canvas, PANEL};
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 10;
+
+    // Only the overlay changes anything on screen.
+    fn plain() -> PostSettings {
+        PostSettings { tone_mapping: 0, bloom_strength: 0.0, vignette: 0.0, ..Default::default() }
+    }
+
+    #[test]
+    fn text_ends_up_on_screen() {
+        let mut overlay = vec![0; (WIDTH * HEIGHT) as usize];
+        let canvas = &mut Canvas { pixels: &mut overlay, width: WIDTH as usize };
+        canvas.draw_text(2, 3, "H1", rgba(255, 0, 0, 255));
+
+        let black = vec![Vec4::new(0.0, 0.0, 0.0, 1.0); (WIDTH * HEIGHT) as usize];
+        let out = post_process(&black, WIDTH, HEIGHT, &plain(), &Palette::default(), &overlay);
+
+        let expected = [
+            "................",
+            "................",
+            "................",
+            "..#.#..#........",
+            "..#.#.##........",
+            "..###..#........",
+            "..#.#..#........",
+            "..#.#.###.......",
+            "................",
+            "................",
+        ];
+        for (y, row) in expected.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let color = if c == '#' { Vec4::new(1.0, 0.0, 0.0, 1.0) } else { Vec4::new(0.0, 0.0, 0.0, 1.0) };
+                assert_eq!(out[x + y * WIDTH as usize], color, "at {x}, {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn panels_are_blended_over_the_scene() {
+        let mut overlay = vec![0; (WIDTH * HEIGHT) as usize];
+        let canvas = &mut Canvas { pixels: &mut overlay, width: WIDTH as usize };
+        canvas.fill_rect(0, 0, 4, 4, PANEL);
+
+        let white = vec![Vec4::ONE; (WIDTH * HEIGHT) as usize];
+        let out = post_process(&white, WIDTH, HEIGHT, &plain(), &Palette::default(), &overlay);
+
+        let alpha = 160.0 / 255.0;
+        assert!(out[0].abs_diff_eq(Vec3::splat(1.0 - alpha).extend(1.0), 1e-5), "{}", out[0]);
+        assert!(out[3 + 3 * WIDTH as usize].abs_diff_eq(out[0], 1e-5));
+        assert!(out[4].abs_diff_eq(Vec4::ONE, 1e-5), "{}", out[4]);
+        assert!(out[4 * WIDTH as usize].abs_diff_eq(Vec4::ONE, 1e-5));
+    }
+}
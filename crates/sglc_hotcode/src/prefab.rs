@@ -0,0 +1,322 @@
+use std::io::{self, Read, Write};
+
+use glam::IVec3;
+use sglc_shared::{ChunkMapping, Voxels};
+
+use crate::chunk_allocator::ChunkAllocator;
+use crate::history::Operation;
+use crate::set_voxel::voxel_at;
+
+const MAGIC: &[u8; 8] = b"SGLCPRFB";
+const VERSION: u32 = 1;
+// A quarter of a 1024³ world, a gigabyte of voxels.
+const MAX_VOXELS: u64 = 1 << 28;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PasteMode {
+    // Every voxel of the prefab is written, empty ones included.
+    Replace,
+    // Only the voxels of the prefab that aren't empty are written.
+    Merge,
+}
+
+// A standalone box of voxels, used for the clipboard and for pieces that
+// worlds stamp into themselves. Empty voxels are stored as 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prefab {
+    pub size: IVec3,
+    pub data: Vec<u32>,
+}
+
+impl Prefab {
+    pub fn new(size: IVec3) -> Self {
+        Self {
+            size,
+            data: vec![0; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    fn index(&self, pos: IVec3) -> usize {
+        ((pos.z * self.size.y + pos.y) * self.size.x + pos.x) as usize
+    }
+
+    pub fn get(&self, pos: IVec3) -> u32 {
+        self.data[self.index(pos)]
+    }
+
+    pub fn set(&mut self, pos: IVec3, unit: u32) {
+        let index = self.index(pos);
+        self.data[index] = unit;
+    }
+
+    fn positions(size: IVec3) -> impl Iterator<Item = IVec3> {
+        (0..size.z).flat_map(move |z| {
+            (0..size.y).flat_map(move |y| (0..size.x).map(move |x| IVec3::new(x, y, z)))
+        })
+    }
+
+    // Both corners are included.
+    pub fn copy(min: IVec3, max: IVec3, chunk_mapping: &ChunkMapping, voxels: &Voxels) -> Self {
+        let (min, max) = (min.min(max), min.max(max));
+        let mut prefab = Prefab::new(max - min + 1);
+
+        for pos in Self::positions(prefab.size) {
+            let unit = match voxel_at(min + pos, chunk_mapping, voxels) {
+                1 | 2 => 0,
+                unit => unit,
+            };
+            prefab.set(pos, unit);
+        }
+
+        prefab
+    }
+
+    // Turns the prefab by 90 degrees `quarter_turns` times around an axis,
+    // where 0 is x, 1 is y and 2 is z.
+    pub fn rotated(&self, axis: usize, quarter_turns: i32) -> Self {
+        let mut prefab = self.clone();
+
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            let s = prefab.size;
+            let (size, turn): (IVec3, fn(IVec3, IVec3) -> IVec3) = match axis {
+                0 => (IVec3::new(s.x, s.z, s.y), |p, s| IVec3::new(p.x, s.z - 1 - p.z, p.y)),
+                1 => (IVec3::new(s.z, s.y, s.x), |p, s| IVec3::new(s.z - 1 - p.z, p.y, p.x)),
+                _ => (IVec3::new(s.y, s.x, s.z), |p, s| IVec3::new(s.y - 1 - p.y, p.x, p.z)),
+            };
+
+            let mut turned = Prefab::new(size);
+            for pos in Self::positions(s) {
+                turned.set(turn(pos, s), prefab.get(pos));
+            }
+            prefab = turned;
+        }
+
+        prefab
+    }
+
+    pub fn mirrored(&self, axis: usize) -> Self {
+        let mut prefab = Prefab::new(self.size);
+
+        for pos in Self::positions(self.size) {
+            let mut to = pos;
+            to[axis] = self.size[axis] - 1 - pos[axis];
+            prefab.set(to, self.get(pos));
+        }
+
+        prefab
+    }
+
+    // Writes the prefab with its lowest corner at `at`.
+    pub fn paste(
+        &self,
+        at: IVec3,
+        mode: PasteMode,
+        operation: &mut Operation,
+        allocator: &mut ChunkAllocator,
+        chunk_mapping: &mut ChunkMapping,
+        voxels: &mut Voxels,
+    ) {
+        for pos in Self::positions(self.size) {
+            let unit = self.get(pos);
+            if unit == 0 && mode == PasteMode::Merge {
+                continue;
+            }
+
+            operation.set_voxel(at + pos, unit, allocator, chunk_mapping, voxels);
+        }
+    }
+
+    // For world generators, which don't keep an edit history.
+    pub fn stamp(
+        &self,
+        at: IVec3,
+        mode: PasteMode,
+        allocator: &mut ChunkAllocator,
+        chunk_mapping: &mut ChunkMapping,
+        voxels: &mut Voxels,
+    ) {
+        self.paste(at, mode, &mut Operation::default(), allocator, chunk_mapping, voxels);
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        for v in self.size.to_array() {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.write_all(bytemuck::cast_slice(&self.data))
+    }
+
+    pub fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a prefab"));
+        }
+
+        let mut header = [0u32; 4];
+        r.read_exact(bytemuck::cast_slice_mut(&mut header))?;
+        let [version, x, y, z] = header.map(u32::from_le);
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported prefab version {version}"),
+            ));
+        }
+
+        let count = x as u64 * y as u64 * z as u64;
+        if count == 0 || count > MAX_VOXELS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("prefab is {x}x{y}x{z}, which is empty or too big"),
+            ));
+        }
+
+        // Read before allocating all of it, so a broken size can't take up
+        // more memory than the file has data for.
+        let mut bytes = Vec::new();
+        r.take(count * 4).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != count * 4 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "prefab is cut short"));
+        }
+
+        Ok(Prefab {
+            size: IVec3::new(x as i32, y as i32, z as i32),
+            data: bytes.chunks_exact(4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::WorldSize;
+
+    use super::*;
+    use crate::clear::clear;
+
+    // An L of three voxels in a 2x3x4 box, so every turn and flip looks
+    // different.
+    fn ell() -> Prefab {
+        let mut prefab = Prefab::new(IVec3::new(2, 3, 4));
+        prefab.set(IVec3::new(0, 0, 0), 3);
+        prefab.set(IVec3::new(1, 0, 0), 4);
+        prefab.set(IVec3::new(0, 2, 3), 5);
+        prefab
+    }
+
+    fn world() -> (ChunkAllocator, Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        (ChunkAllocator::default(), chunk_mapping, voxels)
+    }
+
+    #[test]
+    fn rotating_turns_a_quarter_at_a_time() {
+        let prefab = ell();
+
+        for axis in 0..3 {
+            assert_eq!(prefab.rotated(axis, 4), prefab);
+            assert_eq!(prefab.rotated(axis, -1), prefab.rotated(axis, 3));
+            assert_eq!(prefab.rotated(axis, 1).rotated(axis, 1), prefab.rotated(axis, 2));
+            assert_ne!(prefab.rotated(axis, 1), prefab);
+        }
+
+        // Around y, x goes to z and z to the far end of x.
+        let turned = prefab.rotated(1, 1);
+        assert_eq!(turned.size, IVec3::new(4, 3, 2));
+        assert_eq!(turned.get(IVec3::new(3, 0, 0)), 3);
+        assert_eq!(turned.get(IVec3::new(3, 0, 1)), 4);
+        assert_eq!(turned.get(IVec3::new(0, 2, 0)), 5);
+
+        assert_eq!(prefab.rotated(0, 1).size, IVec3::new(2, 4, 3));
+        assert_eq!(prefab.rotated(2, 1).size, IVec3::new(3, 2, 4));
+        assert_eq!(prefab.rotated(2, 2).get(IVec3::new(1, 2, 0)), 3);
+    }
+
+    #[test]
+    fn mirroring_flips_one_axis() {
+        let prefab = ell();
+
+        for axis in 0..3 {
+            let mirrored = prefab.mirrored(axis);
+            assert_eq!(mirrored.size, prefab.size);
+            assert_eq!(mirrored.mirrored(axis), prefab);
+        }
+
+        let mirrored = prefab.mirrored(2);
+        assert_eq!(mirrored.get(IVec3::new(0, 0, 3)), 3);
+        assert_eq!(mirrored.get(IVec3::new(1, 0, 3)), 4);
+        assert_eq!(mirrored.get(IVec3::new(0, 2, 0)), 5);
+        assert_eq!(prefab.mirrored(0).get(IVec3::new(1, 0, 0)), 3);
+    }
+
+    #[test]
+    fn paste_merges_or_replaces() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let mut operation = Operation::default();
+        let under = IVec3::new(11, 11, 11);
+        operation.set_voxel(under, 9, &mut allocator, &mut chunk_mapping, &mut voxels);
+
+        let prefab = ell();
+        let at = IVec3::new(10, 10, 10);
+        prefab.paste(at, PasteMode::Merge, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(Prefab::copy(at, at + prefab.size - 1, &chunk_mapping, &voxels).data.iter().filter(|&&u| u != 0).count(), 4);
+        assert_eq!(voxel_at(at, &chunk_mapping, &voxels), 3);
+        assert_eq!(voxel_at(under, &chunk_mapping, &voxels), 9);
+
+        prefab.paste(at, PasteMode::Replace, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(Prefab::copy(at, at + prefab.size - 1, &chunk_mapping, &voxels), prefab);
+
+        // Replacing with nothing leaves no chunks behind.
+        Prefab::new(prefab.size).paste(at, PasteMode::Replace, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(allocator.allocated_count(), 0);
+    }
+
+    #[test]
+    fn paste_is_cut_off_at_the_edge_and_can_be_undone() {
+        let (mut allocator, mut chunk_mapping, mut voxels) = world();
+        let mut operation = Operation::default();
+        let prefab = ell().rotated(1, 1);
+
+        let at = IVec3::new(29, -1, 30);
+        prefab.paste(at, PasteMode::Merge, &mut operation, &mut allocator, &mut chunk_mapping, &mut voxels);
+        operation.finish(&chunk_mapping, &voxels);
+
+        assert_eq!(voxel_at(IVec3::new(29, 1, 30), &chunk_mapping, &voxels), 5);
+        assert_eq!(Prefab::copy(IVec3::ZERO, IVec3::splat(31), &chunk_mapping, &voxels).data.iter().filter(|&&u| u != 0).count(), 1);
+
+        operation.undo(&mut allocator, &mut chunk_mapping, &mut voxels);
+        assert!(chunk_mapping.chunks.iter().all(|&slot| slot == 0));
+        assert_eq!(allocator.allocated_count(), 0);
+    }
+
+    #[test]
+    fn read_from_rejects_bad_sizes() {
+        let mut prefab = Prefab::new(IVec3::new(2, 3, 4));
+        prefab.set(IVec3::new(1, 2, 3), 7);
+
+        let mut bytes = Vec::new();
+        prefab.write_to(&mut bytes).unwrap();
+        assert_eq!(Prefab::read_from(&mut bytes.as_slice()).unwrap(), prefab);
+
+        let header = |x: i32, y: i32, z: i32| {
+            let mut bytes = MAGIC.to_vec();
+            for v in [VERSION as i32, x, y, z] {
+                bytes.extend(v.to_le_bytes());
+            }
+            bytes
+        };
+
+        for (x, y, z) in [(0, 1, 1), (-1, 1, 1), (-1, -1, 1), (65536, 65536, 65536)] {
+            let error = Prefab::read_from(&mut header(x, y, z).as_slice()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{x}x{y}x{z}");
+        }
+
+        let error = Prefab::read_from(&mut header(1024, 1024, 64).as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
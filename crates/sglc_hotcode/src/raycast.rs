@@ -0,0 +1,196 @@
+use glam::{IVec3, Vec3};
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_SIZE_ONE};
+
+use crate::set_voxel::{chunk_index_of, voxel_at};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub voxel: IVec3,
+    pub normal: IVec3,
+    pub chunk: usize,
+    pub unit_code: u32,
+    pub distance: f32,
+}
+
+// How much work a ray took, for the debug views.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RayStats {
+    // Voxels that got looked up.
+    pub steps: u32,
+    // Voxels passed through air chunks without looking them up.
+    pub skipped: u32,
+}
+
+// CPU version of `hit_in_direction`. The ray is clipped against the world
+// bounds first, so it can start outside of the world like the camera can.
+pub fn raycast(ro: Vec3, rd: Vec3, chunk_mapping: &ChunkMapping, voxels: &Voxels) -> Option<RayHit> {
+    raycast_with_stats(ro, rd, chunk_mapping, voxels, &mut RayStats::default())
+}
+
+// Air chunks get crossed like the shader crosses them, up to 8 steps at a
+// time without looking at their voxels.
+pub fn raycast_with_stats(
+    ro: Vec3,
+    rd: Vec3,
+    chunk_mapping: &ChunkMapping,
+    voxels: &Voxels,
+    stats: &mut RayStats,
+) -> Option<RayHit> {
+    let rd = rd.normalize_or_zero();
+    if rd == Vec3::ZERO {
+        return None;
+    }
+
+    let world = chunk_mapping.size.voxels();
+    let inv = rd.recip();
+    let t0 = (Vec3::ZERO - ro) * inv;
+    let t1 = (world.as_vec3() - ro) * inv;
+    let t_near = t0.min(t1);
+    let t_far = t0.max(t1);
+    let t_enter = t_near.max_element();
+    let t_exit = t_far.min_element();
+
+    if t_exit < t_enter.max(0.0) {
+        return None;
+    }
+
+    let mut t = t_enter.max(0.0);
+    let mut normal = IVec3::ZERO;
+    if t_enter > 0.0 {
+        let axis = if t_near.x == t_enter { 0 } else if t_near.y == t_enter { 1 } else { 2 };
+        normal[axis] = -(rd[axis].signum() as i32);
+    }
+
+    let mut voxel = (ro + rd * t)
+        .floor()
+        .as_ivec3()
+        .clamp(IVec3::ZERO, world - 1);
+
+    let step = IVec3::new(rd.x.signum() as i32, rd.y.signum() as i32, rd.z.signum() as i32);
+    let t_delta = inv.abs();
+    let mut t_max = Vec3::ZERO;
+    for axis in 0..3 {
+        t_max[axis] = if rd[axis] > 0.0 {
+            (voxel[axis] as f32 + 1.0 - ro[axis]) * inv[axis]
+        } else if rd[axis] < 0.0 {
+            (voxel[axis] as f32 - ro[axis]) * inv[axis]
+        } else {
+            f32::INFINITY
+        };
+    }
+
+    let next = |voxel: &mut IVec3, t: &mut f32, t_max: &mut Vec3, normal: &mut IVec3| {
+        let axis = if t_max.x < t_max.y && t_max.x <= t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+
+        voxel[axis] += step[axis];
+        *t = t_max[axis];
+        t_max[axis] += t_delta[axis];
+        *normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    };
+
+    for _ in 0..world.x + world.y + world.z {
+        let unit_code = voxel_at(voxel, chunk_mapping, voxels);
+        stats.steps += 1;
+
+        if unit_code == 1 {
+            return None;
+        }
+
+        if unit_code > 2 {
+            return Some(RayHit {
+                voxel,
+                normal,
+                chunk: chunk_index_of(voxel, &chunk_mapping.size),
+                unit_code,
+                distance: t,
+            });
+        }
+
+        if unit_code == 2 {
+            let chunk_of = |voxel: IVec3| voxel.div_euclid(IVec3::splat(CHUNK_SIZE_ONE as i32));
+            let chunk = chunk_of(voxel);
+            for _ in 0..CHUNK_SIZE_ONE {
+                next(&mut voxel, &mut t, &mut t_max, &mut normal);
+                stats.skipped += 1;
+
+                if chunk_of(voxel) != chunk {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        next(&mut voxel, &mut t, &mut t_max, &mut normal);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::WorldSize;
+
+    use super::*;
+    use crate::chunk_allocator::ChunkAllocator;
+    use crate::clear::clear;
+    use crate::set_voxel::set_voxel;
+
+    // A 32³ world, so four chunks along each axis, that is all air apart from
+    // one voxel in the third chunk along x.
+    fn world() -> (Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+        set_voxel(IVec3::new(20, 4, 4), 5, &mut ChunkAllocator::default(), &mut chunk_mapping, &mut voxels);
+
+        (chunk_mapping, voxels)
+    }
+
+    #[test]
+    fn air_chunks_are_skipped() {
+        let (chunk_mapping, voxels) = world();
+        let mut stats = RayStats::default();
+        let hit = raycast_with_stats(Vec3::new(0.5, 4.5, 4.5), Vec3::X, &chunk_mapping, &voxels, &mut stats).unwrap();
+
+        assert_eq!(hit.voxel, IVec3::new(20, 4, 4));
+        assert_eq!(hit.normal, IVec3::NEG_X);
+        assert_eq!(hit.unit_code, 5);
+        assert_eq!(hit.distance, 19.5);
+        // One look up in each of the two air chunks, then x = 16 to 20 one by one.
+        assert_eq!(stats, RayStats { steps: 7, skipped: 16 });
+    }
+
+    #[test]
+    fn rays_from_outside_start_at_the_world_bounds() {
+        let (chunk_mapping, voxels) = world();
+        let mut stats = RayStats::default();
+        let hit = raycast_with_stats(Vec3::new(-10.0, 4.5, 4.5), Vec3::X, &chunk_mapping, &voxels, &mut stats).unwrap();
+
+        assert_eq!(hit.voxel, IVec3::new(20, 4, 4));
+        assert_eq!(hit.distance, 30.0);
+        assert_eq!(stats, RayStats { steps: 7, skipped: 16 });
+
+        let miss = raycast(Vec3::new(-10.0, 40.0, 4.5), Vec3::X, &chunk_mapping, &voxels);
+        assert_eq!(miss, None);
+    }
+
+    #[test]
+    fn misses_cross_every_chunk_without_looking_inside() {
+        let (chunk_mapping, voxels) = world();
+        let mut stats = RayStats::default();
+        let hit = raycast_with_stats(Vec3::new(0.5, 4.5, 12.5), Vec3::X, &chunk_mapping, &voxels, &mut stats);
+
+        assert_eq!(hit, None);
+        // The last look up is the first voxel past the edge of the world.
+        assert_eq!(stats, RayStats { steps: 5, skipped: 32 });
+    }
+}
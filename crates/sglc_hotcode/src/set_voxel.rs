@@ -0,0 +1,101 @@
+use glam::IVec3;
+use sglc_shared::{ChunkMapping, Voxels, WorldSize, CHUNK_EXTENT, CHUNK_SIZE_ONE};
+
+use crate::chunk_allocator::ChunkAllocator;
+use crate::pos_to_index::pos_to_index;
+
+pub fn chunk_index_of(pos: IVec3, size: &WorldSize) -> usize {
+    pos_to_index(pos / CHUNK_SIZE_ONE as i32, size.extent())
+}
+
+pub fn voxel_index_of(pos: IVec3) -> usize {
+    pos_to_index(pos % CHUNK_SIZE_ONE as i32, CHUNK_EXTENT)
+}
+
+// Same as `voxel_unit_at` in the fragment shader: 1 outside of the world, 2
+// inside of an air chunk.
+pub fn voxel_at(pos: IVec3, chunk_mapping: &ChunkMapping, voxels: &Voxels) -> u32 {
+    if !chunk_mapping.size.contains(pos) {
+        return 1;
+    }
+
+    let slot = chunk_mapping.chunks[chunk_index_of(pos, &chunk_mapping.size)] as usize;
+    voxels.0[slot][voxel_index_of(pos)]
+}
+
+pub fn chunk_is_empty(slot: usize, voxels: &Voxels) -> bool {
+    voxels.0[slot].iter().all(|&v| v == 0)
+}
+
+// Writes one voxel, giving the chunk its own slot first if it is still an air
+// chunk or shares its slot, and turning it back into an air chunk once it is
+// empty. Returns the slot that was written to.
+pub fn set_voxel(
+    pos: IVec3,
+    unit: u32,
+    allocator: &mut ChunkAllocator,
+    chunk_mapping: &mut ChunkMapping,
+    voxels: &mut Voxels,
+) -> Option<usize> {
+    if !chunk_mapping.size.contains(pos) {
+        return None;
+    }
+
+    let chunk_index = chunk_index_of(pos, &chunk_mapping.size);
+    let mut slot = chunk_mapping.chunks[chunk_index] as usize;
+
+    if slot == 0 {
+        if unit == 0 {
+            return None;
+        }
+
+        slot = allocator.allocate(voxels)?;
+        chunk_mapping.chunks[chunk_index] = slot as u32;
+    } else if allocator.is_shared(slot) {
+        slot = allocator.make_unique(chunk_index, chunk_mapping, voxels)?;
+    }
+
+    voxels.0[slot][voxel_index_of(pos)] = unit;
+
+    if unit == 0 && chunk_is_empty(slot, voxels) {
+        allocator.release(slot, voxels);
+        chunk_mapping.chunks[chunk_index] = 0;
+    }
+
+    Some(slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+
+    use super::*;
+    use crate::clear::clear;
+
+    #[test]
+    fn emptied_chunks_go_back_to_air() {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+        let mut allocator = ChunkAllocator::default();
+
+        let a = IVec3::new(20, 4, 4);
+        let b = IVec3::new(21, 4, 4);
+        let chunk = chunk_index_of(a, &size);
+        let slot = set_voxel(a, 5, &mut allocator, &mut chunk_mapping, &mut voxels).unwrap();
+        set_voxel(b, 6, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(allocator.allocated_count(), 1);
+
+        set_voxel(a, 0, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(chunk_mapping.chunks[chunk] as usize, slot);
+
+        set_voxel(b, 0, &mut allocator, &mut chunk_mapping, &mut voxels);
+        assert_eq!(chunk_mapping.chunks[chunk], 0);
+        assert_eq!(allocator.allocated_count(), 0);
+        assert_eq!(voxel_at(b, &chunk_mapping, &voxels), 2);
+
+        // The freed slot is the one the next chunk gets.
+        assert_eq!(set_voxel(a, 5, &mut allocator, &mut chunk_mapping, &mut voxels), Some(slot));
+    }
+}
@@ -0,0 +1,540 @@
+use glam::{IVec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+use sglc_shared::light::{LightChunks, LightMapping};
+use sglc_shared::palette::Palette;
+use sglc_shared::render_settings::RenderSettings;
+use sglc_shared::sky::Sky;
+use sglc_shared::sun::Sun;
+use sglc_shared::{ChunkMapping, Voxels, CHUNK_SIZE_ONE};
+
+use crate::light::{light_at, MAX_LIGHT};
+use crate::raycast::{raycast, raycast_with_stats, RayHit, RayStats};
+use crate::set_voxel::voxel_at;
+
+// CPU version of the fragment shader's shading, so renders can be checked
+// without a GPU. The random numbers come from the same hash as in the shader,
+// seeded the same way, so soft shadows jitter identically.
+
+// Everything shading reads, bundled since it gets passed all the way down.
+#[derive(Copy, Clone)]
+pub struct Scene<'a> {
+    pub sun: &'a Sun,
+    pub settings: &'a RenderSettings,
+    pub sky: &'a Sky,
+    pub palette: &'a Palette,
+    pub chunk_mapping: &'a ChunkMapping,
+    pub voxels: &'a Voxels,
+    pub light_mapping: &'a LightMapping,
+    pub light_chunks: &'a LightChunks,
+}
+
+pub fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+pub fn random(seed: &mut u32) -> f32 {
+    *seed = hash(*seed);
+    (*seed >> 8) as f32 / 16777216.0
+}
+
+pub fn pixel_seed(pixel: Vec2) -> u32 {
+    let pixel = pixel.as_uvec2();
+    hash(pixel.x.wrapping_add(pixel.y.wrapping_mul(65536)))
+}
+
+// Each accumulated path traced sample gets different random numbers.
+pub fn sample_seed(pixel: Vec2, sample: u32) -> u32 {
+    hash(pixel_seed(pixel).wrapping_add(sample))
+}
+
+pub fn sky_color(rd: Vec3, scene: Scene) -> Vec3 {
+    let (sky, sun) = (scene.sky, scene.sun);
+    let rd = rd.normalize();
+    let up = -rd.y;
+    let color = if up > 0.0 {
+        sky.horizon_color.lerp(sky.zenith_color, up.sqrt())
+    } else {
+        sky.horizon_color.lerp(sky.ground_color, (-up).sqrt())
+    };
+
+    let to_sun = rd.dot(sun.direction);
+    let disk = if to_sun > sky.sun_disk_size.cos() && up > 0.0 { 1.0 } else { 0.0 };
+    color + sun.color * sun.intensity * (disk + to_sun.max(0.0).powf(256.0) * 0.5)
+}
+
+pub fn apply_fog(color: Vec3, distance: f32, scene: Scene) -> Vec3 {
+    let sky = scene.sky;
+    let fog = 1.0 - (-sky.fog_density * (distance - sky.fog_start).max(0.0)).exp();
+    color.lerp(sky.fog_color, fog)
+}
+
+fn basis(normal: Vec3) -> (Vec3, Vec3) {
+    let tangent = normal
+        .cross(if normal.y.abs() < 0.99 { Vec3::Y } else { Vec3::X })
+        .normalize();
+
+    (tangent, normal.cross(tangent))
+}
+
+fn jittered_sun_direction(sun: &Sun, seed: &mut u32) -> Vec3 {
+    let (tangent, bitangent) = basis(sun.direction);
+
+    let radius = sun.softness * random(seed).sqrt();
+    let angle = std::f32::consts::TAU * random(seed);
+
+    (sun.direction + (tangent * angle.cos() + bitangent * angle.sin()) * radius).normalize()
+}
+
+// How much of the sun reaches `pos`, from 0 for full shadow to 1.
+pub fn sun_visibility(pos: Vec3, seed: &mut u32, scene: Scene) -> f32 {
+    let sun = scene.sun;
+    if sun.shadow_samples <= 1 {
+        return raycast(pos, sun.direction, scene.chunk_mapping, scene.voxels).map_or(1.0, |_| 0.0);
+    }
+
+    let lit = (0..sun.shadow_samples)
+        .filter(|_| raycast(pos, jittered_sun_direction(sun, seed), scene.chunk_mapping, scene.voxels).is_none())
+        .count();
+
+    lit as f32 / sun.shadow_samples as f32
+}
+
+fn solid(pos: IVec3, scene: Scene) -> f32 {
+    if voxel_at(pos, scene.chunk_mapping, scene.voxels) > 2 { 1.0 } else { 0.0 }
+}
+
+// Occlusion at the corner of a face between the directions `u` and `v`, from
+// the voxels in front of the face.
+fn vertex_ao(outside: IVec3, u: IVec3, v: IVec3, scene: Scene) -> f32 {
+    let side1 = solid(outside + u, scene);
+    let side2 = solid(outside + v, scene);
+    let corner = if scene.settings.ao_quality >= 2 { solid(outside + u + v, scene) } else { 0.0 };
+
+    if side1 + side2 == 2.0 {
+        return 0.0;
+    }
+
+    (3.0 - side1 - side2 - corner) / 3.0
+}
+
+// 1 for an open face, down to 0 in fully enclosed corners. The corners of the
+// face are blended by where on the face `pos` is.
+pub fn ambient_occlusion(pos: Vec3, hit: &RayHit, scene: Scene) -> f32 {
+    if scene.settings.ao_quality == 0 {
+        return 1.0;
+    }
+
+    let n = hit.normal;
+    let u = if n.x != 0 { IVec3::Y } else { IVec3::X };
+    let v = if n.z != 0 { IVec3::Y } else { IVec3::Z };
+    let outside = hit.voxel + n;
+
+    let in_voxel = pos - hit.voxel.as_vec3();
+    let uv = Vec2::new(in_voxel.dot(u.as_vec3()), in_voxel.dot(v.as_vec3())).clamp(Vec2::ZERO, Vec2::ONE);
+
+    let bottom = lerp(vertex_ao(outside, -u, -v, scene), vertex_ao(outside, u, -v, scene), uv.x);
+    let top = lerp(vertex_ao(outside, -u, v, scene), vertex_ao(outside, u, v, scene), uv.x);
+
+    lerp(1.0, lerp(bottom, top, uv.y), scene.settings.ao_strength)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+const BLOCK_LIGHT_COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.6);
+
+pub fn shade(ro: Vec3, rd: Vec3, hit: &RayHit, seed: &mut u32, scene: Scene) -> Vec3 {
+    let sun = scene.sun;
+    let material = &scene.palette.0[hit.unit_code as usize];
+    let color = Vec4::from(material.color).xyz();
+    let normal = hit.normal.as_vec3();
+    let pos = ro + rd.normalize() * hit.distance;
+
+    let ao = ambient_occlusion(pos, hit, scene);
+
+    let lambert = normal.dot(sun.direction).max(0.0);
+    let visibility = if lambert > 0.0 {
+        sun_visibility(pos + normal, seed, scene)
+    } else {
+        0.0
+    };
+    let sunlight = sun.color * sun.intensity * visibility;
+
+    let half_vector = (sun.direction - rd.normalize()).normalize();
+    let shininess = 256.0 + (2.0 - 256.0) * material.roughness;
+    let highlight = normal.dot(half_vector).max(0.0).powf(shininess) * (1.0 - material.roughness);
+    let specular = Vec3::ONE.lerp(color, material.metalness) * highlight;
+
+    let light = light_at(hit.voxel + hit.normal, scene.light_mapping, scene.light_chunks) as f32 / MAX_LIGHT as f32;
+    let block_light = scene.settings.block_light * light * BLOCK_LIGHT_COLOR;
+
+    let diffuse = color * (1.0 - material.metalness * 0.5);
+    diffuse * (sun.ambient + sunlight * lambert + block_light) * ao + specular * sunlight + color * material.emissive
+}
+
+fn schlick(cos: f32, ior: f32) -> f32 {
+    let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
+// Same as GLSL's `refract`, zero on total internal reflection.
+fn refract(rd: Vec3, normal: Vec3, eta: f32) -> Vec3 {
+    let cos = normal.dot(rd);
+    let k = 1.0 - eta * eta * (1.0 - cos * cos);
+    if k < 0.0 {
+        Vec3::ZERO
+    } else {
+        eta * rd - (eta * cos + k.sqrt()) * normal
+    }
+}
+
+fn reflect(rd: Vec3, normal: Vec3) -> Vec3 {
+    rd - 2.0 * normal.dot(rd) * normal
+}
+
+const MAX_MEDIUM_STEPS: usize = 64;
+
+// Marches from inside voxels of `unit` to where the ray leaves them. The
+// normal faces back into the medium.
+fn leave_medium(ro: Vec3, rd: Vec3, unit: u32, scene: Scene) -> (Vec3, Vec3) {
+    let rd = Vec3::select(rd.cmpeq(Vec3::ZERO), Vec3::splat(1e-5), rd);
+    let mut voxel = ro.floor().as_ivec3();
+    let step = rd.signum();
+    let t_delta = rd.recip().abs();
+    let mut t_max = (step * (voxel.as_vec3() - ro) + step.max(Vec3::ZERO)) * t_delta;
+    let mut normal = Vec3::ZERO;
+    let mut t = 0.0;
+
+    for _ in 0..MAX_MEDIUM_STEPS {
+        if voxel_at(voxel, scene.chunk_mapping, scene.voxels) != unit {
+            break;
+        }
+
+        let axis = if t_max.x < t_max.y && t_max.x <= t_max.z {
+            0
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+
+        t = t_max[axis];
+        t_max[axis] += t_delta[axis];
+        voxel[axis] += step[axis] as i32;
+        normal = Vec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+
+    (ro + rd * t, normal)
+}
+
+// Follows mirror reflections and refraction through glass for up to
+// `max_secondary_rays`, shading whatever the ray ends up on.
+pub fn trace(mut ro: Vec3, mut rd: Vec3, hit: &RayHit, seed: &mut u32, scene: Scene) -> Vec3 {
+    rd = rd.normalize();
+    let mut hit = *hit;
+    let mut color = Vec3::ZERO;
+    let mut weight = Vec3::ONE;
+
+    for bounce in 0..=scene.settings.max_secondary_rays {
+        let material = &scene.palette.0[hit.unit_code as usize];
+        let albedo = Vec4::from(material.color).xyz();
+        let normal = hit.normal.as_vec3();
+        let pos = ro + rd * hit.distance;
+        let local = shade(ro, rd, &hit, seed, scene);
+        let reflectivity = material.metalness * (1.0 - material.roughness);
+        let last = bounce == scene.settings.max_secondary_rays;
+
+        if material.transparency > 0.0 && !last {
+            color += weight * (1.0 - material.transparency) * local;
+            let glass = weight * material.transparency;
+            let fresnel = schlick(-normal.dot(rd), material.ior);
+
+            // The reflection off the glass is only followed one ray deep.
+            let reflected = reflect(rd, normal);
+            let reflected_origin = pos + normal * 0.01;
+            color += glass * fresnel * match raycast(reflected_origin, reflected, scene.chunk_mapping, scene.voxels) {
+                Some(hit) => shade(reflected_origin, reflected, &hit, seed, scene),
+                None => sky_color(reflected, scene),
+            };
+
+            let mut inside = refract(rd, normal, 1.0 / material.ior);
+            let mut exit = (pos - normal * 0.01, Vec3::ZERO);
+            let mut out = Vec3::ZERO;
+            for _ in 0..4 {
+                exit = leave_medium(exit.0, inside, hit.unit_code, scene);
+                out = refract(inside, exit.1, material.ior);
+                if out != Vec3::ZERO {
+                    break;
+                }
+
+                inside = reflect(inside, exit.1);
+                exit.0 += exit.1 * 0.01;
+            }
+
+            if out == Vec3::ZERO {
+                break;
+            }
+
+            weight = glass * (1.0 - fresnel) * albedo;
+            ro = exit.0 - exit.1 * 0.01;
+            rd = out;
+        } else if reflectivity > 0.0 && !last {
+            color += weight * (1.0 - reflectivity) * local;
+            weight *= reflectivity * albedo;
+            ro = pos + normal * 0.01;
+            rd = reflect(rd, normal);
+        } else {
+            color += weight * local;
+            break;
+        }
+
+        match raycast(ro, rd, scene.chunk_mapping, scene.voxels) {
+            Some(next) => hit = next,
+            None => {
+                color += weight * sky_color(rd, scene);
+                break;
+            },
+        }
+    }
+
+    color
+}
+
+fn cosine_direction(normal: Vec3, seed: &mut u32) -> Vec3 {
+    let (tangent, bitangent) = basis(normal);
+
+    let radius = random(seed).sqrt();
+    let angle = std::f32::consts::TAU * random(seed);
+
+    (tangent * radius * angle.cos() + bitangent * radius * angle.sin() + normal * (1.0 - radius * radius).sqrt())
+        .normalize()
+}
+
+// One sample of diffuse light along the ray. Emissive materials, the sun and
+// the sky are the light sources.
+pub fn path_trace(mut ro: Vec3, mut rd: Vec3, seed: &mut u32, scene: Scene) -> Vec3 {
+    let sun = scene.sun;
+    let mut throughput = Vec3::ONE;
+    let mut radiance = Vec3::ZERO;
+    let mut first_distance = None;
+
+    for _ in 0..=scene.settings.max_bounces {
+        let Some(hit) = raycast(ro, rd, scene.chunk_mapping, scene.voxels) else {
+            radiance += throughput * sky_color(rd, scene);
+            break;
+        };
+        first_distance.get_or_insert(hit.distance);
+
+        let material = &scene.palette.0[hit.unit_code as usize];
+        let color = Vec4::from(material.color).xyz();
+        let normal = hit.normal.as_vec3();
+        let pos = ro + rd.normalize() * hit.distance;
+
+        radiance += throughput * color * material.emissive;
+
+        let lambert = normal.dot(sun.direction).max(0.0);
+        if lambert > 0.0 {
+            let visibility = sun_visibility(pos + normal, seed, scene);
+            radiance += throughput * color * sun.color * sun.intensity * lambert * visibility;
+        }
+
+        throughput *= color;
+        ro = pos + normal * 0.01;
+        rd = cosine_direction(normal, seed);
+    }
+
+    match first_distance {
+        Some(distance) => apply_fog(radiance, distance, scene),
+        None => radiance,
+    }
+}
+
+// Steps at which the step heatmaps turn fully red.
+const DEBUG_MAX_STEPS: f32 = 256.0;
+// Distance at which the depth view turns black.
+const DEBUG_DEPTH_RANGE: f32 = 512.0;
+
+// Blue through green to red.
+fn heat(t: f32) -> Vec3 {
+    let t = t.clamp(0.0, 1.0);
+    Vec3::new(t, 1.0 - (t * 2.0 - 1.0).abs(), 1.0 - t)
+}
+
+// What the debug view `scene.settings.debug_mode` shows for the ray, see
+// `DEBUG_MODES`.
+pub fn debug_color(ro: Vec3, rd: Vec3, scene: Scene) -> Vec3 {
+    let mut stats = RayStats::default();
+    let hit = raycast_with_stats(ro, rd, scene.chunk_mapping, scene.voxels, &mut stats);
+    let steps = stats.steps as f32 / DEBUG_MAX_STEPS;
+
+    match (scene.settings.debug_mode, hit) {
+        (2, _) => heat(steps),
+        (5, _) => Vec3::new(steps, stats.skipped as f32 / DEBUG_MAX_STEPS, 0.0),
+        (_, None) => Vec3::ZERO,
+        (1, Some(hit)) => hit.normal.as_vec3() * 0.5 + 0.5,
+        (3, Some(hit)) => {
+            let normal = hit.normal.as_vec3();
+            let pos = ro + rd.normalize() * hit.distance;
+
+            // Distance to the nearest chunk boundary along the face.
+            let local = (pos / CHUNK_SIZE_ONE as f32).fract();
+            let edges = local.min(1.0 - local) * CHUNK_SIZE_ONE as f32;
+            let edges = Vec3::select(normal.abs().cmpgt(Vec3::ZERO), Vec3::splat(1e9), edges);
+
+            if edges.min_element() < 0.1 {
+                return Vec3::ONE;
+            }
+
+            let color = Vec4::from(scene.palette.0[hit.unit_code as usize].color).xyz();
+            color * (0.6 + 0.4 * normal.dot(scene.sun.direction).max(0.0))
+        },
+        (4, Some(hit)) => {
+            let slot = hash(scene.chunk_mapping.chunks[hit.chunk]);
+            Vec3::new((slot & 0xff) as f32, (slot >> 8 & 0xff) as f32, (slot >> 16 & 0xff) as f32) / 255.0
+        },
+        (_, Some(hit)) => Vec3::splat(1.0 - (hit.distance / DEBUG_DEPTH_RANGE).clamp(0.0, 1.0)),
+    }
+}
+
+// Renders rows top to bottom, `ray_direction` gets the center of each pixel.
+// In beauty mode every pixel averages `samples` path traced samples, which
+// matches what the GPU has after accumulating as many frames.
+pub fn render(
+    width: u32,
+    height: u32,
+    ro: Vec3,
+    ray_direction: impl Fn(Vec2) -> Vec3 + Sync,
+    samples: u32,
+    scene: Scene,
+) -> Vec<Vec4> {
+    let render_row = |y: u32| {
+        (0..width).map(|x| {
+            let pixel = Vec2::new(x as f32, y as f32) + 0.5;
+            let rd = ray_direction(pixel);
+
+            if scene.settings.debug_mode != 0 {
+                return debug_color(ro, rd, scene).extend(1.0);
+            }
+
+            if scene.settings.beauty == 1 {
+                let sum = (0..samples.max(1))
+                    .map(|sample| path_trace(ro, rd, &mut sample_seed(pixel, sample), scene))
+                    .sum::<Vec3>();
+
+                return (sum / samples.max(1) as f32).extend(1.0);
+            }
+
+            match raycast(ro, rd, scene.chunk_mapping, scene.voxels) {
+                Some(hit) => apply_fog(trace(ro, rd, &hit, &mut pixel_seed(pixel), scene), hit.distance, scene),
+                None => sky_color(rd, scene),
+            }.extend(1.0)
+        }).collect::<Vec<_>>()
+    };
+
+    // Rows are spread over all cores, path tracing takes a while.
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get()) as u32;
+    let rows = std::thread::scope(|scope| {
+        let handles = (0..threads)
+            .map(|thread| {
+                let render_row = &render_row;
+                scope.spawn(move || {
+                    (thread..height).step_by(threads as usize).map(|y| (y, render_row(y))).collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut rows = handles.into_iter().flat_map(|h| h.join().unwrap()).collect::<Vec<_>>();
+        rows.sort_unstable_by_key(|(y, _)| *y);
+        rows
+    });
+
+    rows.into_iter().flat_map(|(_, row)| row).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::UVec3;
+    use sglc_shared::WorldSize;
+
+    use super::*;
+    use crate::chunk_allocator::ChunkAllocator;
+    use crate::clear::clear;
+    use crate::set_voxel::set_voxel;
+
+    // A floor at y = 20 with a one voxel high wall on top of it along z = 20.
+    fn floor_with_wall() -> (Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        let mut allocator = ChunkAllocator::default();
+        for x in 0..32 {
+            for z in 0..32 {
+                set_voxel(IVec3::new(x, 20, z), 5, &mut allocator, &mut chunk_mapping, &mut voxels);
+            }
+            set_voxel(IVec3::new(x, 19, 20), 5, &mut allocator, &mut chunk_mapping, &mut voxels);
+        }
+
+        (chunk_mapping, voxels)
+    }
+
+    // Looks down onto the floor at `z` and returns the occlusion where the ray lands.
+    fn ao_on_floor(z: f32, settings: &RenderSettings) -> f32 {
+        let (chunk_mapping, voxels) = floor_with_wall();
+        let light_mapping = LightMapping::new(chunk_mapping.size);
+        let light_chunks = LightChunks::new();
+        let scene = Scene {
+            sun: &Sun::default(),
+            settings,
+            sky: &Sky::default(),
+            palette: &Palette::default(),
+            chunk_mapping: &chunk_mapping,
+            voxels: &voxels,
+            light_mapping: &light_mapping,
+            light_chunks: &light_chunks,
+        };
+
+        let (ro, rd) = (Vec3::new(10.5, 5.0, z), Vec3::Y);
+        let hit = raycast(ro, rd, &chunk_mapping, &voxels).unwrap();
+        assert_eq!(hit.voxel, IVec3::new(10, 20, z as i32));
+        ambient_occlusion(ro + rd * hit.distance, &hit, scene)
+    }
+
+    #[test]
+    fn open_faces_are_not_occluded() {
+        let settings = RenderSettings { ao_strength: 1.0, ..Default::default() };
+        assert_eq!(ao_on_floor(10.5, &settings), 1.0);
+        assert_eq!(ao_on_floor(18.9, &settings), 1.0);
+    }
+
+    #[test]
+    fn corners_get_darker_towards_the_wall() {
+        let settings = RenderSettings { ao_strength: 1.0, ..Default::default() };
+        let far = ao_on_floor(19.1, &settings);
+        let middle = ao_on_floor(19.5, &settings);
+        let near = ao_on_floor(19.9, &settings);
+
+        assert!(far < 1.0);
+        assert!(middle < far);
+        assert!(near < middle);
+        assert!((middle - 2.0 / 3.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn strength_and_quality_scale_occlusion() {
+        let off = RenderSettings { ao_quality: 0, ao_strength: 1.0, ..Default::default() };
+        assert_eq!(ao_on_floor(19.9, &off), 1.0);
+
+        let half = RenderSettings { ao_strength: 0.5, ..Default::default() };
+        let full = RenderSettings { ao_strength: 1.0, ..Default::default() };
+        let (half, full) = (ao_on_floor(19.5, &half), ao_on_floor(19.5, &full));
+        assert!((1.0 - half - (1.0 - full) / 2.0).abs() < 1e-4);
+    }
+}
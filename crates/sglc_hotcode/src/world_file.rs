@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use glam::UVec3;
+use sglc_shared::{ChunkMapping, Voxels, WorldSize, CHUNK_SIZE, CHUNK_SIZE_ONE};
+use sglc_shared::palette::{Material, Palette};
+
+use crate::clear::clear;
+
+// Layout, all little endian:
+//
+//   magic "SGLCWRLD", version: u32
+//   chunk counts along x, y, z: 3 * u32, chunk size: u32
+//   palette length: u32, palette: length * 9 * f32
+//   slot count: u32, then per slot a chunk, either
+//     0: u8, run count: u16, runs: run count * (length: u16, unit: u32)
+//     1: u8, unit count: u8, units: unit count * u32, packed unit indices
+//   mapping entries: u32, then per entry (chunk index: u32, slot: u32)
+//
+// Slots are numbered from 1 in the order they are stored, so loading always
+// ends up with a compact allocation, whatever holes the saved world had.
+const MAGIC: &[u8; 8] = b"SGLCWRLD";
+const VERSION: u32 = 1;
+const MATERIAL_FLOATS: usize = 9;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    r.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+const RUNS: u8 = 0;
+const PACKED: u8 = 1;
+const MAX_PACKED_UNITS: usize = 16;
+
+fn packed_bits(unit_count: usize) -> usize {
+    match unit_count {
+        0..=1 => 0,
+        2 => 1,
+        3..=4 => 2,
+        _ => 4,
+    }
+}
+
+// Chunks are stored either as runs of the same unit, or, when they only use a
+// handful of units, as indices into a small per chunk list of units packed
+// into 1, 2 or 4 bits. Whichever is smaller wins.
+fn encode_chunk(chunk: &[u32; CHUNK_SIZE], out: &mut Vec<u8>) {
+    let mut units = [0u32; MAX_PACKED_UNITS];
+    let mut unit_count = 0;
+    let mut indices = [0u8; CHUNK_SIZE];
+    let mut run_count = 0;
+
+    for (i, &voxel) in chunk.iter().enumerate() {
+        if i > 0 && voxel == chunk[i - 1] {
+            indices[i] = indices[i - 1];
+            continue;
+        }
+
+        run_count += 1;
+
+        if unit_count > MAX_PACKED_UNITS {
+            continue;
+        }
+
+        indices[i] = match units[..unit_count].iter().position(|&u| u == voxel) {
+            Some(index) => index as u8,
+            None if unit_count < MAX_PACKED_UNITS => {
+                units[unit_count] = voxel;
+                unit_count += 1;
+                unit_count as u8 - 1
+            },
+            None => {
+                unit_count += 1;
+                0
+            },
+        };
+    }
+
+    let runs_size = 2 + run_count * 6;
+    let packed_size = 1 + unit_count * 4 + CHUNK_SIZE * packed_bits(unit_count) / 8;
+
+    if unit_count <= MAX_PACKED_UNITS && packed_size < runs_size {
+        let bits = packed_bits(unit_count);
+
+        out.push(PACKED);
+        out.push(unit_count as u8);
+        for unit in &units[..unit_count] {
+            out.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        if let Some(per_byte) = 8usize.checked_div(bits) {
+            out.extend(indices.chunks_exact(per_byte).map(|indices| {
+                indices.iter().enumerate().fold(0u8, |byte, (i, &index)| byte | index << (i * bits))
+            }));
+        }
+
+        return;
+    }
+
+    out.push(RUNS);
+    out.extend_from_slice(&(run_count as u16).to_le_bytes());
+
+    let mut i = 0;
+    while i < CHUNK_SIZE {
+        let unit = chunk[i];
+        let start = i;
+        while i < CHUNK_SIZE && chunk[i] == unit {
+            i += 1;
+        }
+
+        out.extend_from_slice(&((i - start) as u16).to_le_bytes());
+        out.extend_from_slice(&unit.to_le_bytes());
+    }
+}
+
+fn decode_chunk(r: &mut impl Read, chunk: &mut [u32; CHUNK_SIZE]) -> io::Result<()> {
+    let mut tag = [0];
+    r.read_exact(&mut tag)?;
+
+    match tag[0] {
+        RUNS => {
+            let mut i = 0;
+
+            for _ in 0..read_u16(r)? {
+                let length = read_u16(r)? as usize;
+                let unit = read_u32(r)?;
+
+                if i + length > CHUNK_SIZE {
+                    return Err(invalid("chunk run goes past the end of the chunk"));
+                }
+
+                chunk[i..i + length].fill(unit);
+                i += length;
+            }
+
+            if i != CHUNK_SIZE {
+                return Err(invalid("chunk runs don't cover the whole chunk"));
+            }
+        },
+        PACKED => {
+            let mut unit_count = [0];
+            r.read_exact(&mut unit_count)?;
+            let unit_count = unit_count[0] as usize;
+            if unit_count == 0 || unit_count > MAX_PACKED_UNITS {
+                return Err(invalid(format!("packed chunk with {unit_count} units")));
+            }
+
+            let mut units = [0u32; MAX_PACKED_UNITS];
+            for unit in &mut units[..unit_count] {
+                *unit = read_u32(r)?;
+            }
+
+            let bits = packed_bits(unit_count);
+            if bits == 0 {
+                chunk.fill(units[0]);
+                return Ok(());
+            }
+
+            let mut packed = [0u8; CHUNK_SIZE / 2];
+            let packed = &mut packed[..CHUNK_SIZE * bits / 8];
+            r.read_exact(packed)?;
+
+            let mask = (1u8 << bits) - 1;
+            for (i, voxel) in chunk.iter_mut().enumerate() {
+                let index = (packed[i * bits / 8] >> (i * bits % 8) & mask) as usize;
+                if index >= unit_count {
+                    return Err(invalid(format!("packed chunk index {index} past its {unit_count} units")));
+                }
+                *voxel = units[index];
+            }
+        },
+        tag => return Err(invalid(format!("unknown chunk encoding {tag}"))),
+    }
+
+    Ok(())
+}
+
+pub fn save_world(
+    w: &mut impl Write,
+    palette: &Palette,
+    chunk_mapping: &ChunkMapping,
+    voxels: &Voxels,
+) -> io::Result<()> {
+    let mut out = Vec::with_capacity(1 << 20);
+
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    for v in chunk_mapping.size.chunks.to_array() {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out.extend_from_slice(&(CHUNK_SIZE_ONE as u32).to_le_bytes());
+
+    out.extend_from_slice(&(palette.0.len() as u32).to_le_bytes());
+    for material in &palette.0 {
+        let values: [f32; 12] = bytemuck::cast(*material);
+        for v in &values[..MATERIAL_FLOATS] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    // Chunks that share a slot in memory keep sharing it in the file.
+    let mut slots = HashMap::<u32, u32>::new();
+    let mut order = Vec::new();
+    let mut entries = Vec::new();
+    for (chunk, &slot) in chunk_mapping.chunks.iter().enumerate() {
+        if slot == 0 {
+            continue;
+        }
+
+        let saved = *slots.entry(slot).or_insert_with(|| {
+            order.push(slot);
+            order.len() as u32
+        });
+        entries.push((chunk as u32, saved));
+    }
+
+    out.extend_from_slice(&(order.len() as u32).to_le_bytes());
+
+    // Encoding is what takes time, so it is spread over all cores.
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let per_thread = order.len().div_ceil(threads).max(1);
+    let encoded = std::thread::scope(|scope| {
+        let handles = order
+            .chunks(per_thread)
+            .map(|slots| scope.spawn(move || {
+                let mut out = Vec::with_capacity(slots.len() * 256);
+                for &slot in slots {
+                    encode_chunk(&voxels.0[slot as usize], &mut out);
+                }
+                out
+            }))
+            .collect::<Vec<_>>();
+
+        handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+    });
+
+    for part in encoded {
+        out.extend_from_slice(&part);
+    }
+
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (chunk, slot) in entries {
+        out.extend_from_slice(&chunk.to_le_bytes());
+        out.extend_from_slice(&slot.to_le_bytes());
+    }
+
+    w.write_all(&out)
+}
+
+fn read_header(r: &mut impl Read) -> io::Result<WorldSize> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a world file"));
+    }
+
+    let version = read_u32(r)?;
+    if version != VERSION {
+        return Err(invalid(format!("unsupported world file version {version}")));
+    }
+
+    let chunks = UVec3::new(read_u32(r)?, read_u32(r)?, read_u32(r)?);
+    let chunk_size = read_u32(r)?;
+    if chunk_size != CHUNK_SIZE_ONE as u32 {
+        return Err(invalid(format!("world has chunks of {chunk_size}, expected {CHUNK_SIZE_ONE}")));
+    }
+
+    WorldSize::new(chunks)
+        .ok_or_else(|| invalid(format!("world is {}x{}x{} chunks, which doesn't fit", chunks.x, chunks.y, chunks.z)))
+}
+
+// Only reads as far as the size, so the buffers can be made to fit before
+// loading.
+pub fn read_world_size(r: &mut impl Read) -> io::Result<WorldSize> {
+    read_header(r)
+}
+
+// Replaces the current world with the saved one and returns its palette. The
+// world has to be as big as the saved one. Everything is read before the
+// current world is touched, so it stays as it was if the file is broken.
+pub fn load_world(
+    r: &mut impl Read,
+    chunk_mapping: &mut ChunkMapping,
+    voxels: &mut Voxels,
+) -> io::Result<Palette> {
+    let size = read_header(r)?;
+    if size != chunk_mapping.size {
+        return Err(invalid(format!("world is {size}, expected {}", chunk_mapping.size)));
+    }
+
+    let palette_length = read_u32(r)? as usize;
+    let mut palette = Palette::default();
+    for i in 0..palette_length {
+        let mut values = bytemuck::cast::<Material, [f32; 12]>(Material::default());
+        for v in &mut values[..MATERIAL_FLOATS] {
+            *v = f32::from_bits(read_u32(r)?);
+        }
+
+        if let Some(material) = palette.0.get_mut(i) {
+            *material = bytemuck::cast(values);
+        }
+    }
+
+    let slot_count = read_u32(r)? as usize;
+    if slot_count >= voxels.0.len() {
+        return Err(invalid(format!("world has {slot_count} chunks, more than fit")));
+    }
+
+    // Grown as chunks are read rather than allocated up front, so a broken
+    // count can't take more memory than the file has chunks for.
+    let mut chunks = Vec::new();
+    for _ in 0..slot_count {
+        let mut chunk = [0; CHUNK_SIZE];
+        decode_chunk(r, &mut chunk)?;
+        chunks.push(chunk);
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..read_u32(r)? {
+        let chunk = read_u32(r)? as usize;
+        let slot = read_u32(r)?;
+
+        if chunk >= chunk_mapping.chunks.len() || slot == 0 || slot as usize > slot_count {
+            return Err(invalid(format!("bad mapping entry {chunk} -> {slot}")));
+        }
+
+        entries.push((chunk, slot));
+    }
+
+    clear(chunk_mapping, voxels);
+    voxels.0[1..=slot_count].copy_from_slice(&chunks);
+    for (chunk, slot) in entries {
+        chunk_mapping.chunks[chunk] = slot;
+    }
+
+    Ok(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::IVec3;
+
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::chunk_allocator::ChunkAllocator;
+    use crate::place_one_sphere::place_one_sphere;
+    use crate::set_voxel::{set_voxel, voxel_at};
+
+    fn chunk_of(unit: impl Fn(usize) -> u32) -> [u32; CHUNK_SIZE] {
+        std::array::from_fn(unit)
+    }
+
+    fn world(units: &[(IVec3, u32)]) -> (Box<ChunkMapping>, Box<Voxels>) {
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        let mut allocator = ChunkAllocator::default();
+        for &(pos, unit) in units {
+            set_voxel(pos, unit, &mut allocator, &mut chunk_mapping, &mut voxels);
+        }
+
+        (chunk_mapping, voxels)
+    }
+
+    #[test]
+    fn broken_files_leave_the_world_alone() {
+        let (saved_mapping, saved_voxels) = world(&[(IVec3::new(1, 2, 3), 5), (IVec3::new(20, 20, 20), 6)]);
+        let mut bytes = Vec::new();
+        save_world(&mut bytes, &Palette::default(), &saved_mapping, &saved_voxels).unwrap();
+
+        let (mut chunk_mapping, mut voxels) = world(&[(IVec3::new(9, 9, 9), 7)]);
+        for end in [bytes.len() - 1, bytes.len() - 8, bytes.len() / 2] {
+            assert!(load_world(&mut &bytes[..end], &mut chunk_mapping, &mut voxels).is_err());
+            assert_eq!(voxel_at(IVec3::new(9, 9, 9), &chunk_mapping, &voxels), 7);
+            assert_eq!(voxel_at(IVec3::new(1, 2, 3), &chunk_mapping, &voxels), 2);
+        }
+
+        load_world(&mut bytes.as_slice(), &mut chunk_mapping, &mut voxels).unwrap();
+        assert_eq!(voxel_at(IVec3::new(9, 9, 9), &chunk_mapping, &voxels), 2);
+        assert_eq!(voxel_at(IVec3::new(1, 2, 3), &chunk_mapping, &voxels), 5);
+        assert_eq!(voxel_at(IVec3::new(20, 20, 20), &chunk_mapping, &voxels), 6);
+    }
+
+    #[test]
+    fn every_encoding_survives_a_round_trip() {
+        // The encoding each chunk should get, and how many bits per voxel if
+        // it is packed.
+        let chunks = [
+            (chunk_of(|i| 3 + (i / 20) as u32), RUNS, 0),
+            (chunk_of(|_| 7), PACKED, 0),
+            (chunk_of(|i| if i % 5 == 0 { 9 } else { 0 }), PACKED, 1),
+            (chunk_of(|i| 3 + (i % 3) as u32), PACKED, 2),
+            (chunk_of(|i| 3 + (i * 7 % 11) as u32), PACKED, 4),
+        ];
+
+        for (chunk, tag, bits) in &chunks {
+            let mut bytes = Vec::new();
+            encode_chunk(chunk, &mut bytes);
+            assert_eq!(bytes[0], *tag);
+            if *tag == PACKED {
+                assert_eq!(bytes.len(), 2 + bytes[1] as usize * 4 + CHUNK_SIZE * bits / 8);
+            }
+
+            let mut decoded = [0; CHUNK_SIZE];
+            decode_chunk(&mut bytes.as_slice(), &mut decoded).unwrap();
+            assert_eq!(decoded, *chunk);
+        }
+
+        let size = WorldSize::from_voxels(UVec3::splat(32)).unwrap();
+        let mut chunk_mapping = ChunkMapping::new(size);
+        let mut voxels = Voxels::new(size);
+        clear(&mut chunk_mapping, &mut voxels);
+
+        // Spread out with holes between the slots, and the last chunk shared
+        // by three chunks.
+        for (n, (chunk, _, _)) in chunks.iter().enumerate() {
+            voxels.0[n * 2 + 1] = *chunk;
+            chunk_mapping.chunks[n * 7] = n as u32 * 2 + 1;
+        }
+        chunk_mapping.chunks[40] = 9;
+        chunk_mapping.chunks[63] = 9;
+
+        let mut palette = Palette::default();
+        palette.0[5].emissive = 2.0;
+        let mut bytes = Vec::new();
+        save_world(&mut bytes, &palette, &chunk_mapping, &voxels).unwrap();
+
+        let mut loaded_mapping = ChunkMapping::new(size);
+        let mut loaded_voxels = Voxels::new(size);
+        assert_eq!(load_world(&mut bytes.as_slice(), &mut loaded_mapping, &mut loaded_voxels).unwrap(), palette);
+
+        for (chunk, (&slot, &loaded)) in chunk_mapping.chunks.iter().zip(loaded_mapping.chunks.iter()).enumerate() {
+            assert_eq!(voxels.0[slot as usize], loaded_voxels.0[loaded as usize], "chunk {chunk}");
+        }
+
+        // Loading packs the slots together and keeps the shared one shared.
+        let mut slots = loaded_mapping.chunks.iter().copied().filter(|&slot| slot != 0).collect::<Vec<_>>();
+        slots.sort_unstable();
+        slots.dedup();
+        assert_eq!(slots, [1, 2, 3, 4, 5]);
+        assert_eq!(loaded_mapping.chunks[28], loaded_mapping.chunks[40]);
+        assert_eq!(loaded_mapping.chunks[28], loaded_mapping.chunks[63]);
+    }
+
+    #[test]
+    fn packed_indices_past_the_units_are_rejected() {
+        let mut bytes = Vec::new();
+        encode_chunk(&chunk_of(|i| 3 + (i % 3) as u32), &mut bytes);
+        assert_eq!(bytes[1], 3);
+
+        // The first voxel's index, pointing at a fourth unit that isn't there.
+        bytes[2 + 3 * 4] |= 0b11;
+
+        let mut chunk = [0; CHUNK_SIZE];
+        let error = decode_chunk(&mut bytes.as_slice(), &mut chunk).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // Takes a release build to be anywhere near fast enough, so it only runs
+    // with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn a_full_size_world_saves_in_under_a_second() {
+        let mut chunk_mapping = ChunkMapping::new(WorldSize::default());
+        let mut voxels = Voxels::new(WorldSize::default());
+        clear(&mut chunk_mapping, &mut voxels);
+
+        fastrand::seed(1);
+        let mut new_chunk = 1;
+        for n in 0..200 {
+            place_one_sphere(n % 10, &mut new_chunk, &mut chunk_mapping, &mut voxels);
+        }
+
+        let start = Instant::now();
+        let mut bytes = Vec::new();
+        save_world(&mut bytes, &Palette::default(), &chunk_mapping, &voxels).unwrap();
+        let elapsed = start.elapsed();
+
+        assert!(elapsed < Duration::from_secs(1), "saving {new_chunk} chunks took {elapsed:?}");
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use vulkano::buffer::Subbuffer;
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryAutoCommandBuffer, SubpassContents,
+    RenderPassBeginInfo,
+};
+
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Queue;
+use vulkano::image::AttachmentImage;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline};
+use vulkano::render_pass::Framebuffer;
+use vulkano::sync::PipelineStage;
+
+use crate::error::RendererError;
+use crate::gpu_timer::{GpuTimer, FRAME_START, POST_END, SCENE_END};
+use crate::meshes::Meshes;
+use crate::post_processing::PostProcessing;
+use sglc_shared::MyVertex;
+
+#[allow(clippy::too_many_arguments)]
+pub fn get_command_buffers(
+    queue: &Arc<Queue>,
+    pipeline: &Arc<GraphicsPipeline>,
+    framebuffer: &Arc<Framebuffer>,
+    vertex_buffer: &Subbuffer<[MyVertex]>,
+    descriptor_sets: &(Arc<PersistentDescriptorSet>, Arc<PersistentDescriptorSet>),
+    meshes: &Meshes,
+    post_processing: &PostProcessing,
+    gpu_timer: Option<&GpuTimer>,
+    // Offscreen frames get copied from the image into the buffer at the end.
+    readback: Option<(&Arc<AttachmentImage>, &Subbuffer<[u8]>)>,
+    allocator: &StandardCommandBufferAllocator,
+) -> Result<Vec<Arc<PrimaryAutoCommandBuffer>>, RendererError> {
+    // The scene is drawn into the same HDR image whichever swapchain image
+    // it ends up in.
+    (0..post_processing.framebuffers.len())
+        .map(|image_index| -> Result<_, RendererError> {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                allocator,
+                queue.queue_family_index(),
+                CommandBufferUsage::MultipleSubmit,
+            )?;
+
+            if let Some(timer) = gpu_timer {
+                timer.reset(&mut builder)?;
+                timer.write(&mut builder, FRAME_START, PipelineStage::TopOfPipe)?;
+            }
+
+            builder
+                .begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![Some([0.1, 0.1, 0.1, 1.0].into()), Some(1.0.into())],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                    },
+                    SubpassContents::Inline,
+                )?
+                .bind_pipeline_graphics(pipeline.clone())
+                .bind_descriptor_sets(
+                    vulkano::pipeline::PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    0,
+                    descriptor_sets.clone(),
+                )
+                .bind_vertex_buffers(0, vertex_buffer.clone())
+                .draw(vertex_buffer.len() as u32, 1, 0, 0)?
+                // Instance 1 is the sky, see the vertex shader.
+                .draw(3, 1, 0, 1)?;
+
+            meshes.record(&mut builder)?;
+
+            builder.end_render_pass()?;
+
+            if let Some(timer) = gpu_timer {
+                timer.write(&mut builder, SCENE_END, PipelineStage::BottomOfPipe)?;
+            }
+
+            post_processing.record(&mut builder, image_index)?;
+
+            if let Some(timer) = gpu_timer {
+                timer.write(&mut builder, POST_END, PipelineStage::BottomOfPipe)?;
+            }
+
+            if let Some((image, pixels)) = readback {
+                builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image.clone(), pixels.clone()))?;
+            }
+
+            Ok(Arc::new(builder.build()?))
+        })
+        .collect()
+}
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use vulkano::buffer::Subbuffer;
+use vulkano::device::Device;
+use vulkano::instance::debug::{
+    DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger, DebugUtilsMessengerCreateInfo,
+};
+use vulkano::instance::{Instance, InstanceExtensions};
+use vulkano::VulkanLibrary;
+
+// Turned on with --debug, the messenger and object names need the extension
+// and the layer is what checks API usage.
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+pub const DEBUG_EXTENSIONS: InstanceExtensions = InstanceExtensions {
+    ext_debug_utils: true,
+    ..InstanceExtensions::empty()
+};
+
+// `DEBUG_EXTENSIONS` if the driver has them, otherwise nothing with a note.
+pub fn debug_extensions(library: &VulkanLibrary) -> InstanceExtensions {
+    if library.supported_extensions().ext_debug_utils {
+        DEBUG_EXTENSIONS
+    } else {
+        eprintln!("VK_EXT_debug_utils isn't supported, running without messages and object names");
+        InstanceExtensions::empty()
+    }
+}
+
+// The layers to enable, missing ones are left out with a note.
+pub fn debug_layers(library: &VulkanLibrary) -> Vec<String> {
+    let available = library
+        .layer_properties()
+        .map(|mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER))
+        .unwrap_or(false);
+
+    if available {
+        vec![VALIDATION_LAYER.to_string()]
+    } else {
+        eprintln!("{VALIDATION_LAYER} isn't installed, running without validation");
+        Vec::new()
+    }
+}
+
+// Prints what the layers and the driver have to say to stderr, tagged with
+// how severe it is. The callback only prints, it doesn't call into Vulkan.
+pub fn messenger_create_info() -> DebugUtilsMessengerCreateInfo {
+    DebugUtilsMessengerCreateInfo {
+        message_severity: DebugUtilsMessageSeverity::ERROR
+            | DebugUtilsMessageSeverity::WARNING
+            | DebugUtilsMessageSeverity::INFO,
+        message_type: DebugUtilsMessageType::GENERAL
+            | DebugUtilsMessageType::VALIDATION
+            | DebugUtilsMessageType::PERFORMANCE,
+        ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(|message| {
+            let severity = if message.severity.intersects(DebugUtilsMessageSeverity::ERROR) {
+                "error"
+            } else if message.severity.intersects(DebugUtilsMessageSeverity::WARNING) {
+                "warning"
+            } else {
+                "info"
+            };
+
+            eprintln!("vulkan {severity} [{}]: {}", message.layer_prefix.unwrap_or("?"), message.description);
+        }))
+    }
+}
+
+// Messages from everything between creating and destroying the instance,
+// dropping the messenger stops them. Those from creating and destroying it go
+// to the messengers `create_instance` chains on.
+pub fn create_messenger(instance: Arc<Instance>) -> Option<DebugUtilsMessenger> {
+    match unsafe { DebugUtilsMessenger::new(instance, messenger_create_info()) } {
+        Ok(messenger) => Some(messenger),
+        Err(e) => {
+            eprintln!("failed to create the debug messenger: {e}");
+            None
+        },
+    }
+}
+
+// Shows up in validation messages and in RenderDoc. Only works with the debug
+// extensions enabled.
+pub fn name_buffer<T: ?Sized>(device: &Device, buffer: &Subbuffer<T>, name: &str) {
+    if let Err(e) = device.set_debug_utils_object_name(buffer.buffer().as_ref(), Some(name)) {
+        eprintln!("failed to name buffer {name}: {e}");
+    }
+}
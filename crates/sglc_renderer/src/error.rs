@@ -0,0 +1,191 @@
+use std::fmt;
+
+use vulkano::buffer::BufferError;
+use vulkano::command_buffer::{
+    BuildError, CommandBufferBeginError, CommandBufferExecError, CopyError, PipelineExecutionError, QueryError,
+    RenderPassError,
+};
+use vulkano::descriptor_set::DescriptorSetCreationError;
+use vulkano::device::physical::PhysicalDeviceError;
+use vulkano::device::DeviceCreationError;
+use vulkano::image::view::ImageViewCreationError;
+use vulkano::image::ImageError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::pipeline::graphics::GraphicsPipelineCreationError;
+use vulkano::render_pass::{FramebufferCreationError, RenderPassCreationError};
+use vulkano::sampler::SamplerCreationError;
+use vulkano::shader::ShaderCreationError;
+use vulkano::swapchain::{AcquireError, SwapchainCreationError};
+use vulkano::sync::FlushError;
+use vulkano::LoadingError;
+
+// Everything that can stop the renderer, worded for whoever runs it. The
+// Vulkan error that caused it, if any, goes along as text.
+#[derive(Debug)]
+pub enum RendererError {
+    NoVulkan(String),
+    Instance(String),
+    Window(String),
+    NoDevice(String),
+    Device(String),
+    // The window's surface went away, e.g. with the window on a display that
+    // got unplugged.
+    SurfaceLost,
+    // The driver reset or crashed.
+    DeviceLost,
+    Allocation(String),
+    Shader(String),
+    // Anything else that goes wrong while setting up.
+    Setup(String),
+    // Something that goes wrong drawing a frame.
+    Frame(String),
+}
+
+impl RendererError {
+    // Starting over gets past a lost surface or device, anything else would
+    // just happen again.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, RendererError::SurfaceLost | RendererError::DeviceLost)
+    }
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RendererError::NoVulkan(e) => write!(f, "couldn't load Vulkan, is a driver installed? ({e})"),
+            RendererError::Instance(e) => write!(f, "couldn't start Vulkan: {e}"),
+            RendererError::Window(e) => write!(f, "couldn't open a window: {e}"),
+            RendererError::NoDevice(e) => write!(f, "{e}"),
+            RendererError::Device(e) => write!(f, "couldn't set up the graphics device: {e}"),
+            RendererError::SurfaceLost => write!(f, "the window's surface was lost"),
+            RendererError::DeviceLost => write!(f, "the graphics device was lost, the driver might have crashed"),
+            RendererError::Allocation(e) => write!(f, "not enough graphics memory: {e}"),
+            RendererError::Shader(e) => write!(f, "a shader failed to load: {e}"),
+            RendererError::Setup(e) => write!(f, "setting up rendering failed: {e}"),
+            RendererError::Frame(e) => write!(f, "drawing a frame failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+impl From<LoadingError> for RendererError {
+    fn from(e: LoadingError) -> Self {
+        RendererError::NoVulkan(e.to_string())
+    }
+}
+
+impl From<InstanceCreationError> for RendererError {
+    fn from(e: InstanceCreationError) -> Self {
+        RendererError::Instance(e.to_string())
+    }
+}
+
+impl From<vulkano_win::CreationError> for RendererError {
+    fn from(e: vulkano_win::CreationError) -> Self {
+        RendererError::Window(e.to_string())
+    }
+}
+
+impl From<DeviceCreationError> for RendererError {
+    fn from(e: DeviceCreationError) -> Self {
+        match e {
+            DeviceCreationError::DeviceLost => RendererError::DeviceLost,
+            e => RendererError::Device(e.to_string()),
+        }
+    }
+}
+
+impl From<PhysicalDeviceError> for RendererError {
+    fn from(e: PhysicalDeviceError) -> Self {
+        RendererError::Device(e.to_string())
+    }
+}
+
+impl From<SwapchainCreationError> for RendererError {
+    fn from(e: SwapchainCreationError) -> Self {
+        match e {
+            SwapchainCreationError::SurfaceLost => RendererError::SurfaceLost,
+            SwapchainCreationError::DeviceLost => RendererError::DeviceLost,
+            e => RendererError::Setup(format!("swapchain: {e}")),
+        }
+    }
+}
+
+impl From<BufferError> for RendererError {
+    fn from(e: BufferError) -> Self {
+        RendererError::Allocation(e.to_string())
+    }
+}
+
+impl From<ImageError> for RendererError {
+    fn from(e: ImageError) -> Self {
+        RendererError::Allocation(e.to_string())
+    }
+}
+
+impl From<ShaderCreationError> for RendererError {
+    fn from(e: ShaderCreationError) -> Self {
+        RendererError::Shader(e.to_string())
+    }
+}
+
+impl From<GraphicsPipelineCreationError> for RendererError {
+    fn from(e: GraphicsPipelineCreationError) -> Self {
+        RendererError::Shader(format!("pipeline: {e}"))
+    }
+}
+
+impl From<AcquireError> for RendererError {
+    fn from(e: AcquireError) -> Self {
+        match e {
+            AcquireError::SurfaceLost => RendererError::SurfaceLost,
+            AcquireError::DeviceLost => RendererError::DeviceLost,
+            e => RendererError::Frame(e.to_string()),
+        }
+    }
+}
+
+impl From<FlushError> for RendererError {
+    fn from(e: FlushError) -> Self {
+        match e {
+            FlushError::SurfaceLost => RendererError::SurfaceLost,
+            FlushError::DeviceLost => RendererError::DeviceLost,
+            e => RendererError::Frame(e.to_string()),
+        }
+    }
+}
+
+impl From<CommandBufferExecError> for RendererError {
+    fn from(e: CommandBufferExecError) -> Self {
+        RendererError::Frame(e.to_string())
+    }
+}
+
+// The rest only happen while building the pipelines and command buffers, and
+// mean a bug or a driver giving up.
+macro_rules! setup_errors {
+    ($($error:ty => $what:literal,)*) => {
+        $(
+            impl From<$error> for RendererError {
+                fn from(e: $error) -> Self {
+                    RendererError::Setup(format!(concat!($what, ": {}"), e))
+                }
+            }
+        )*
+    };
+}
+
+setup_errors! {
+    ImageViewCreationError => "image view",
+    RenderPassCreationError => "render pass",
+    FramebufferCreationError => "framebuffer",
+    DescriptorSetCreationError => "descriptor set",
+    SamplerCreationError => "sampler",
+    CommandBufferBeginError => "command buffer",
+    RenderPassError => "command buffer",
+    PipelineExecutionError => "command buffer",
+    QueryError => "command buffer",
+    CopyError => "command buffer",
+    BuildError => "command buffer",
+}
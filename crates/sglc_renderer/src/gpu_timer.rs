@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::device::Device;
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType};
+use vulkano::sync::PipelineStage;
+
+use crate::error::RendererError;
+
+// Where the timestamps go in the query pool.
+pub const FRAME_START: u32 = 0;
+pub const SCENE_END: u32 = 1;
+pub const POST_END: u32 = 2;
+const TIMESTAMP_COUNT: u32 = 3;
+
+// Timestamps written by the command buffers, so the time the GPU spends on
+// the scene and on post processing can be told apart from waiting on it.
+pub struct GpuTimer {
+    query_pool: Arc<QueryPool>,
+    // Nanoseconds per tick.
+    timestamp_period: f32,
+}
+
+impl GpuTimer {
+    // None if the queue can't write timestamps, or there's no memory for them.
+    pub fn new(device: Arc<Device>, queue_family_index: u32) -> Option<Self> {
+        let physical_device = device.physical_device();
+        let family = &physical_device.queue_family_properties()[queue_family_index as usize];
+        family.timestamp_valid_bits?;
+
+        let timestamp_period = physical_device.properties().timestamp_period;
+        let query_pool = QueryPool::new(
+            device,
+            QueryPoolCreateInfo {
+                query_count: TIMESTAMP_COUNT,
+                ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+            },
+        )
+        .ok()?;
+
+        Some(Self { query_pool, timestamp_period })
+    }
+
+    // Has to be recorded outside of render passes, before any `write`.
+    pub fn reset(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), RendererError> {
+        unsafe {
+            builder.reset_query_pool(self.query_pool.clone(), 0..TIMESTAMP_COUNT)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        query: u32,
+        stage: PipelineStage,
+    ) -> Result<(), RendererError> {
+        unsafe {
+            builder.write_timestamp(self.query_pool.clone(), query, stage)?;
+        }
+        Ok(())
+    }
+
+    // Milliseconds spent on the scene and on post processing in the last
+    // frame, which has to have finished.
+    pub fn read(&self) -> Option<(f32, f32)> {
+        let mut ticks = [0u64; TIMESTAMP_COUNT as usize];
+        let available = self.query_pool
+            .queries_range(0..TIMESTAMP_COUNT)
+            .unwrap()
+            .get_results(&mut ticks, QueryResultFlags::empty())
+            .ok()?;
+
+        if !available {
+            return None;
+        }
+
+        let millis = |from: u32, to: u32| {
+            ticks[to as usize].wrapping_sub(ticks[from as usize]) as f32 * self.timestamp_period / 1_000_000.0
+        };
+        Some((millis(FRAME_START, SCENE_END), millis(SCENE_END, POST_END)))
+    }
+}
@@ -0,0 +1,9 @@
+pub mod renderer;
+pub mod error;
+pub mod pick_physical_device;
+pub mod debug;
+pub mod shaders;
+pub mod post_processing;
+pub mod meshes;
+pub mod command_buffer;
+pub mod gpu_timer;
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use vulkano::buffer::Subbuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{RenderPass, Subpass};
+
+use sglc_shared::camera_data::CameraData;
+use sglc_shared::{MeshVertex, MeshVertices, MESH_VERTEX_COUNT};
+
+use crate::error::RendererError;
+use crate::shaders;
+
+// Triangle meshes like debug gizmos, drawn after the voxels in the same pass
+// so they depth test against the depth the voxels write.
+pub struct Meshes {
+    pub pipeline: Arc<GraphicsPipeline>,
+    pub vertex_buffer: Subbuffer<MeshVertices>,
+    pub descriptor_set: Arc<PersistentDescriptorSet>,
+}
+
+impl Meshes {
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<RenderPass>,
+        viewport: Viewport,
+        vertex_buffer: Subbuffer<MeshVertices>,
+        camera_data: Subbuffer<[CameraData]>,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+    ) -> Result<Self, RendererError> {
+        let vs = shaders::mesh_vs::load(device.clone())?;
+        let fs = shaders::mesh_fs::load(device.clone())?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(MeshVertex::per_vertex())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .depth_stencil_state(DepthStencilState::simple_depth_test())
+            .render_pass(Subpass::from(render_pass, 0).unwrap())
+            .build(device)?;
+
+        let descriptor_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [WriteDescriptorSet::buffer(0, camera_data)],
+        )?;
+
+        Ok(Self { pipeline, vertex_buffer, descriptor_set })
+    }
+
+    // Has to be recorded inside of the voxel render pass.
+    pub fn record(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> Result<(), RendererError> {
+        builder
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_set.clone(),
+            )
+            .bind_vertex_buffers(0, self.vertex_buffer.clone())
+            .draw(MESH_VERTEX_COUNT as u32, 1, 0, 0)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use vulkano::device::{DeviceExtensions, Features, QueueFlags};
+use vulkano::device::physical::PhysicalDeviceType;
+use vulkano::swapchain::Surface;
+use vulkano::{instance::Instance, device::physical::PhysicalDevice};
+
+use sglc_shared::light::LightChunks;
+use sglc_shared::palette::Palette;
+use sglc_shared::{WorldSize, CHUNK_SIZE};
+
+pub struct DeviceInfo {
+    pub device: Arc<PhysicalDevice>,
+    pub graphics_queue_index: u32,
+}
+
+// Only renderers that present to a window need a swapchain.
+pub fn required_extensions(presents: bool) -> DeviceExtensions {
+    DeviceExtensions {
+        khr_swapchain: presents,
+        ..DeviceExtensions::empty()
+    }
+}
+
+// The path tracer accumulates into a storage image from the fragment shader.
+pub const REQUIRED_FEATURES: Features = Features {
+    fragment_stores_and_atomics: true,
+    ..Features::empty()
+};
+
+// What was found out about a device while picking one.
+pub struct DeviceReport {
+    pub device: Arc<PhysicalDevice>,
+    pub graphics_queue_index: Option<u32>,
+    // Why the device can't be used at all.
+    pub problems: Vec<String>,
+    // Limits the buffers go over. Drivers sometimes cope anyway, so devices
+    // with only these are still picked when nothing else is left.
+    pub limit_problems: Vec<String>,
+}
+
+impl DeviceReport {
+    pub fn name(&self) -> &str {
+        &self.device.properties().device_name
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+fn mib(bytes: u64) -> u64 {
+    bytes >> 20
+}
+
+fn report(device: Arc<PhysicalDevice>, surface: Option<&Arc<Surface>>, world_size: WorldSize) -> DeviceReport {
+    let mut problems = Vec::new();
+    let mut limit_problems = Vec::new();
+
+    let missing_extensions = required_extensions(surface.is_some()).difference(device.supported_extensions());
+    if missing_extensions != DeviceExtensions::empty() {
+        problems.push(format!("missing extensions {missing_extensions:?}"));
+    }
+
+    let missing_features = REQUIRED_FEATURES.difference(device.supported_features());
+    if missing_features != Features::empty() {
+        problems.push(format!("missing features {missing_features:?}"));
+    }
+
+    // The first queue family that can both draw and present, if there's a
+    // window to present to.
+    let graphics_queue_index = device
+        .queue_family_properties()
+        .iter()
+        .enumerate()
+        .position(|(i, q)| {
+            q.queue_flags.contains(QueueFlags::GRAPHICS)
+                && surface.map_or(true, |surface| device.surface_support(i as u32, surface).unwrap_or(false))
+        })
+        .map(|q| q as u32);
+    if graphics_queue_index.is_none() {
+        problems.push(match surface {
+            Some(_) => "no queue family that draws and presents to the window".to_string(),
+            None => "no queue family that draws".to_string(),
+        });
+    }
+
+    let properties = device.properties();
+    // The mappings have the size in front of them. `Voxels` is cut down to
+    // what fits, so only the others can go over.
+    let mapping_size = std::mem::size_of::<WorldSize>() + world_size.chunk_count() * 4;
+    let voxels_size = voxel_slots(&device, world_size) * CHUNK_SIZE * 4;
+    let storage_buffers = [
+        ("ChunkMapping", mapping_size),
+        ("LightMapping", mapping_size),
+        ("LightChunks", std::mem::size_of::<LightChunks>()),
+    ];
+    for (name, size) in storage_buffers {
+        if size as u64 > properties.max_storage_buffer_range as u64 {
+            limit_problems.push(format!(
+                "{name} is {} MiB, max_storage_buffer_range is {} MiB",
+                mib(size as u64),
+                mib(properties.max_storage_buffer_range as u64),
+            ));
+        }
+    }
+
+    if std::mem::size_of::<Palette>() as u64 > properties.max_uniform_buffer_range as u64 {
+        limit_problems.push(format!(
+            "Palette is {} bytes, max_uniform_buffer_range is {} bytes",
+            std::mem::size_of::<Palette>(),
+            properties.max_uniform_buffer_range,
+        ));
+    }
+
+    let largest_heap = device.memory_properties().memory_heaps.iter().map(|heap| heap.size).max().unwrap_or(0);
+    if voxels_size as u64 > largest_heap {
+        limit_problems.push(format!(
+            "Voxels is {} MiB, the largest memory heap is {} MiB",
+            mib(voxels_size as u64),
+            mib(largest_heap),
+        ));
+    }
+
+    DeviceReport { device, graphics_queue_index, problems, limit_problems }
+}
+
+// Slots of `Voxels` that fit into what one storage buffer binding can reach,
+// up to one for every chunk. Past that allocating chunks fails, which editing
+// and generating worlds already cope with.
+pub fn voxel_slots(device: &PhysicalDevice, world_size: WorldSize) -> usize {
+    let range = device.properties().max_storage_buffer_range as usize;
+    world_size.chunk_count().min(range / (CHUNK_SIZE * 4))
+}
+
+// Every device in the order Vulkan lists them, which is what indices given
+// to `pick_physical_device` count in. The limits are checked for a world of
+// `world_size`, presenting only if there is a `surface`.
+pub fn report_physical_devices(
+    instance: &Arc<Instance>,
+    surface: Option<&Arc<Surface>>,
+    world_size: WorldSize,
+) -> Result<Vec<DeviceReport>, String> {
+    let devices = instance
+        .enumerate_physical_devices()
+        .map_err(|e| format!("could not enumerate devices: {e}"))?;
+
+    Ok(devices.map(|device| report(device, surface, world_size)).collect())
+}
+
+fn rank(device: &PhysicalDevice) -> u32 {
+    match device.properties().device_type {
+        PhysicalDeviceType::DiscreteGpu => 0,
+        PhysicalDeviceType::IntegratedGpu => 1,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 3,
+
+        // `PhysicalDeviceType` is a non-exhaustive enum. Thus, one should
+        // match wildcard `_` to catch all unknown device types.
+        _ => 4,
+    }
+}
+
+// `requested` is an index into the reports or part of a device name. If it
+// matches nothing usable, or nothing is requested, the best kind of device
+// that works gets picked, preferring ones within every limit.
+pub fn pick_physical_device(reports: &[DeviceReport], requested: Option<&str>) -> Result<DeviceInfo, String> {
+    let info = |report: &DeviceReport| DeviceInfo {
+        device: report.device.clone(),
+        graphics_queue_index: report.graphics_queue_index.unwrap(),
+    };
+
+    if let Some(requested) = requested {
+        let found = reports.iter().enumerate().find(|(i, report)| {
+            requested.parse() == Ok(*i) || report.name().to_lowercase().contains(&requested.to_lowercase())
+        });
+
+        match found {
+            Some((_, report)) if report.is_usable() => return Ok(info(report)),
+            Some((_, report)) => eprintln!(
+                "can't use the requested device {}: {}, picking another one",
+                report.name(),
+                report.problems.join(", "),
+            ),
+            None => eprintln!("no device matches {requested}, picking another one"),
+        }
+    }
+
+    let usable = reports.iter().filter(|report| report.is_usable());
+    let best = usable.clone().filter(|report| report.limit_problems.is_empty()).min_by_key(|report| rank(&report.device));
+
+    if let Some(report) = best {
+        return Ok(info(report));
+    }
+
+    if let Some(report) = usable.min_by_key(|report| rank(&report.device)) {
+        eprintln!(
+            "no device is within every limit, trying {} anyway: {}",
+            report.name(),
+            report.limit_problems.join(", "),
+        );
+        return Ok(info(report));
+    }
+
+    let reasons = reports
+        .iter()
+        .map(|report| format!("\n  {}: {}", report.name(), report.problems.join(", ")))
+        .collect::<String>();
+    Err(format!("no device can be used{}", if reports.is_empty() { ", Vulkan lists none" } else { &reasons }))
+}
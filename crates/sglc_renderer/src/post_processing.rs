@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use vulkano::buffer::Subbuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassContents};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::{Format, NumericType};
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageViewAbstract};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::VertexInputState;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::swapchain::ColorSpace;
+
+use sglc_shared::palette::Palette;
+use sglc_shared::post_settings::PostSettings;
+
+use crate::error::RendererError;
+use crate::shaders;
+
+// The scene gets rendered into an image of this format, so colors brighter
+// than 1 survive until tone mapping.
+pub const HDR_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+// Prefers formats the hardware sRGB encodes on its own, then anything shown
+// as sRGB, then whatever comes first.
+pub fn pick_surface_format(formats: &[(Format, ColorSpace)]) -> Option<(Format, ColorSpace)> {
+    let srgb = |&&(format, color_space): &&(Format, ColorSpace)| {
+        color_space == ColorSpace::SrgbNonLinear && is_srgb(format)
+    };
+
+    formats
+        .iter()
+        .find(srgb)
+        .or_else(|| formats.iter().find(|(_, color_space)| *color_space == ColorSpace::SrgbNonLinear))
+        .or(formats.first())
+        .copied()
+}
+
+pub fn is_srgb(format: Format) -> bool {
+    format.type_color() == Some(NumericType::SRGB)
+}
+
+// Tone mapping, exposure, bloom, vignette and dithering, drawn from the HDR
+// image straight into the swapchain image, or the offscreen one, with the
+// overlay on top. The HDR image gets stretched over the whole target, which
+// only windows make any other size.
+pub struct PostProcessing {
+    pub pipeline: Arc<GraphicsPipeline>,
+    // One per image frames can end up in.
+    pub framebuffers: Vec<Arc<Framebuffer>>,
+    pub descriptor_sets: (Arc<PersistentDescriptorSet>, Arc<PersistentDescriptorSet>),
+    render_pass: Arc<RenderPass>,
+}
+
+impl PostProcessing {
+    // `targets` are all `format`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: Arc<Device>,
+        format: Format,
+        targets: &[Arc<dyn ImageViewAbstract>],
+        hdr_image: Arc<ImageView<AttachmentImage>>,
+        settings: Subbuffer<PostSettings>,
+        palette: Subbuffer<Palette>,
+        overlay: Subbuffer<[u32]>,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+    ) -> Result<Self, RendererError> {
+        let render_pass = vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: format,
+                    samples: 1,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )?;
+
+        let framebuffers = get_framebuffers(&render_pass, targets)?;
+
+        let vs = shaders::post_vs::load(device.clone())?;
+        let fs = shaders::post_fs::load(device.clone())?;
+
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(VertexInputState::new())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .render_pass(Subpass::from(render_pass, 0).unwrap())
+            .build(device.clone())?;
+
+        // Pixels are read with texelFetch, so nothing gets filtered.
+        let sampler = Sampler::new(
+            device,
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        let set_layouts = pipeline.layout().set_layouts();
+        let descriptor_sets = (
+            PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                set_layouts[0].clone(),
+                [
+                    WriteDescriptorSet::image_view_sampler(0, hdr_image, sampler),
+                    WriteDescriptorSet::buffer(1, settings),
+                    WriteDescriptorSet::buffer(2, overlay),
+                ],
+            )?,
+            PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                set_layouts[1].clone(),
+                [WriteDescriptorSet::buffer(0, palette)],
+            )?,
+        );
+
+        Ok(Self { pipeline, framebuffers, descriptor_sets, render_pass })
+    }
+
+    // For when the swapchain got recreated, the images have to stay the
+    // same format.
+    pub fn set_targets(&mut self, targets: &[Arc<dyn ImageViewAbstract>]) -> Result<(), RendererError> {
+        self.framebuffers = get_framebuffers(&self.render_pass, targets)?;
+        Ok(())
+    }
+
+    pub fn record(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image_index: usize,
+    ) -> Result<(), RendererError> {
+        let [width, height] = self.framebuffers[image_index].extent();
+        let size = [width as f32, height as f32];
+
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(self.framebuffers[image_index].clone())
+                },
+                SubpassContents::Inline,
+            )?
+            .set_viewport(0, [Viewport { origin: [0.0, 0.0], dimensions: size, depth_range: 0.0..1.0 }])
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_sets.clone(),
+            )
+            .push_constants(self.pipeline.layout().clone(), 0, shaders::post_fs::Target { size })
+            .draw(3, 1, 0, 0)?
+            .end_render_pass()?;
+
+        Ok(())
+    }
+}
+
+fn get_framebuffers(
+    render_pass: &Arc<RenderPass>,
+    targets: &[Arc<dyn ImageViewAbstract>],
+) -> Result<Vec<Arc<Framebuffer>>, RendererError> {
+    targets
+        .iter()
+        .map(|target| -> Result<_, RendererError> {
+            Ok(Framebuffer::new(
+                render_pass.clone(),
+                FramebufferCreateInfo {
+                    attachments: vec![target.clone()],
+                    ..Default::default()
+                },
+            )?)
+        })
+        .collect()
+}
@@ -0,0 +1,856 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
+use vulkano::command_buffer::PrimaryAutoCommandBuffer;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo};
+use vulkano::image::view::ImageView;
+use vulkano::image::{AttachmentImage, ImageDimensions, ImageUsage, ImageViewAbstract, StorageImage, SwapchainImage};
+use vulkano::instance::debug::DebugUtilsMessenger;
+use vulkano::instance::{Instance, InstanceCreateInfo};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::Vertex;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::shader::ShaderModule;
+use vulkano::swapchain::{
+    self, AcquireError, CompositeAlpha, CompositeAlphas, Surface, SurfaceCapabilities, Swapchain, SwapchainCreateInfo,
+    SwapchainPresentInfo,
+};
+use vulkano::sync::{self, FlushError, GpuFuture};
+use vulkano::VulkanLibrary;
+
+use sglc_hotcode::create_vertex_buffer::set_vertex_buffer;
+use sglc_shared::camera_data::CameraData;
+use sglc_shared::light::{LightChunks, LightMapping};
+use sglc_shared::palette::Palette;
+use sglc_shared::post_settings::PostSettings;
+use sglc_shared::render_settings::RenderSettings;
+use sglc_shared::selection::SelectionData;
+use sglc_shared::sky::Sky;
+use sglc_shared::sun::Sun;
+use sglc_shared::{vertex_count, ChunkMapping, MeshVertices, MyVertex, Voxels, WorldSize};
+
+use crate::command_buffer::get_command_buffers;
+use crate::debug;
+use crate::error::RendererError;
+use crate::gpu_timer::GpuTimer;
+use crate::meshes::Meshes;
+use crate::pick_physical_device::{
+    pick_physical_device, report_physical_devices, required_extensions, voxel_slots, REQUIRED_FEATURES,
+};
+use crate::post_processing::{is_srgb, pick_surface_format, PostProcessing, HDR_FORMAT};
+use crate::shaders;
+
+// Voxels write the depth of their actual hit, which 16 bits can't tell apart
+// far away.
+const DEPTH_FORMAT: vulkano::format::Format = vulkano::format::Format::D32_SFLOAT;
+// Offscreen frames get sRGB encoded by post processing, like for swapchains
+// that don't do it themselves.
+const OFFSCREEN_FORMAT: vulkano::format::Format = vulkano::format::Format::R8G8B8A8_UNORM;
+
+pub struct RendererOptions {
+    pub resolution: [u32; 2],
+    // Index or part of the name of the physical device to use.
+    pub device: Option<String>,
+    // Prints validation messages and names the buffers if the instance came
+    // from `create_instance(true)` and has the debug extension.
+    pub debug: bool,
+    // The world buffers are made for this size and stay that size.
+    pub world_size: WorldSize,
+}
+
+pub struct FrameTimes {
+    // Spent waiting for the GPU to finish the frame.
+    pub wait: Duration,
+    // Milliseconds the GPU spent on the scene and on post processing, if the
+    // queue can time them.
+    pub gpu: Option<(f32, f32)>,
+}
+
+// With `debug` the instance gets the validation layer and what's needed to
+// name objects, as far as they're installed.
+pub fn create_instance(debug: bool) -> Result<Arc<Instance>, RendererError> {
+    let library = VulkanLibrary::new()?;
+    let required_extensions = vulkano_win::required_extensions(&library);
+    let (enabled_extensions, enabled_layers) = if debug {
+        (required_extensions.union(&debug::debug_extensions(&library)), debug::debug_layers(&library))
+    } else {
+        (required_extensions, Vec::new())
+    };
+
+    // Catches what's reported while the instance is created and destroyed,
+    // which the messenger the renderer makes is too late and too early for.
+    let messengers = enabled_extensions.ext_debug_utils.then(debug::messenger_create_info);
+
+    // The callback only prints, it doesn't call into Vulkan.
+    Ok(unsafe {
+        Instance::with_debug_utils_messengers(
+            library,
+            InstanceCreateInfo {
+                enabled_extensions,
+                enabled_layers,
+                ..Default::default()
+            },
+            messengers,
+        )
+    }?)
+}
+
+// Owns everything on the GPU. The world and the settings live in buffers the
+// CPU writes to directly between frames, `render_frame` waits for the GPU so
+// that's always safe.
+pub struct Renderer {
+    pub device: Arc<Device>,
+    pub queue: Arc<Queue>,
+    // None for offscreen renderers.
+    pub swapchain: Option<Arc<Swapchain>>,
+    pub resolution: [u32; 2],
+    // Set when the swapchain no longer matches the window, the next
+    // `render_frame` recreates it.
+    pub recreate_swapchain: bool,
+    // How many of the chunk vertices `upload_chunks` filled in.
+    pub vertex_count: usize,
+    // Chunks the vertex buffer had no room for, which aren't drawn.
+    pub chunks_left_out: usize,
+
+    pub chunk_mapping: Subbuffer<ChunkMapping>,
+    pub voxels: Subbuffer<Voxels>,
+    pub light_mapping: Subbuffer<LightMapping>,
+    pub light_chunks: Subbuffer<LightChunks>,
+    pub palette: Subbuffer<Palette>,
+    pub selection: Subbuffer<[SelectionData]>,
+    pub sun: Subbuffer<Sun>,
+    pub render_settings: Subbuffer<RenderSettings>,
+    pub sky: Subbuffer<Sky>,
+    pub post_settings: Subbuffer<PostSettings>,
+    // One RGBA8 pixel per screen pixel, drawn over everything else.
+    pub overlay: Subbuffer<[u32]>,
+    pub mesh_vertices: Subbuffer<MeshVertices>,
+    // Where offscreen renderers leave each frame, RGBA8 pixels in sRGB, row
+    // by row from the top.
+    pub pixels: Option<Subbuffer<[u8]>>,
+
+    vertices: Subbuffer<[MyVertex]>,
+    camera: Subbuffer<[CameraData]>,
+    recording: Recording,
+    command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>>,
+    gpu_timer: Option<GpuTimer>,
+    _debug_messenger: Option<DebugUtilsMessenger>,
+}
+
+// What the command buffers are recorded from, kept to record them again for
+// new swapchain images.
+struct Recording {
+    pipeline: Arc<GraphicsPipeline>,
+    framebuffer: Arc<Framebuffer>,
+    descriptor_sets: (Arc<PersistentDescriptorSet>, Arc<PersistentDescriptorSet>),
+    meshes: Meshes,
+    post_processing: PostProcessing,
+    offscreen_image: Option<Arc<AttachmentImage>>,
+    allocator: StandardCommandBufferAllocator,
+}
+
+impl Renderer {
+    pub fn new(surface: Arc<Surface>, options: &RendererOptions) -> Result<Self, RendererError> {
+        Self::with_target(surface.instance().clone(), Some(surface), options)
+    }
+
+    // Renders into an image instead of a window, for tools and tests that
+    // have none. Every `render_frame` leaves the frame in `pixels`.
+    pub fn new_offscreen(instance: Arc<Instance>, options: &RendererOptions) -> Result<Self, RendererError> {
+        Self::with_target(instance, None, options)
+    }
+
+    fn with_target(
+        instance: Arc<Instance>,
+        surface: Option<Arc<Surface>>,
+        options: &RendererOptions,
+    ) -> Result<Self, RendererError> {
+        let [width, height] = options.resolution;
+        let debug_utils = options.debug && instance.enabled_extensions().ext_debug_utils;
+        let debug_messenger = debug_utils.then(|| debug::create_messenger(instance.clone())).flatten();
+
+        let device_reports = report_physical_devices(&instance, surface.as_ref(), options.world_size)
+            .map_err(RendererError::NoDevice)?;
+        let physical_device = pick_physical_device(&device_reports, options.device.as_deref())
+            .map_err(RendererError::NoDevice)?;
+        eprintln!("using {}", physical_device.device.properties().device_name);
+
+        let (device, mut queues) = Device::new(
+            physical_device.device.clone(),
+            DeviceCreateInfo {
+                queue_create_infos: vec![QueueCreateInfo {
+                    queue_family_index: physical_device.graphics_queue_index,
+                    ..Default::default()
+                }],
+                enabled_extensions: required_extensions(surface.is_some()),
+                enabled_features: REQUIRED_FEATURES,
+                ..Default::default()
+            },
+        )?;
+
+        let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
+        let cmd_buffer_allocator = StandardCommandBufferAllocator::new(
+            device.clone(),
+            StandardCommandBufferAllocatorCreateInfo::default(),
+        );
+
+        let queue = queues.next().unwrap();
+
+        let dimensions = [width, height];
+        let depth_buffer = ImageView::new_default(AttachmentImage::transient(&memory_allocator, dimensions, DEPTH_FORMAT)?)?;
+
+        // Post processing draws into the swapchain images, or into one image
+        // that gets copied to `pixels` offscreen.
+        let (swapchain, targets, offscreen) = match surface {
+            Some(surface) => {
+                let (swapchain, images) = create_swapchain(&device, surface, dimensions)?;
+                let targets = image_views(&images)?;
+                (Some(swapchain), targets, None)
+            }
+            None => {
+                let image = AttachmentImage::with_usage(
+                    &memory_allocator,
+                    dimensions,
+                    OFFSCREEN_FORMAT,
+                    ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC,
+                )?;
+                let pixels = Buffer::new_slice::<u8>(
+                    &memory_allocator,
+                    BufferCreateInfo {
+                        usage: BufferUsage::TRANSFER_DST,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        usage: MemoryUsage::Download,
+                        ..Default::default()
+                    },
+                    (width * height * 4) as u64,
+                )?;
+                let target: Arc<dyn ImageViewAbstract> = ImageView::new_default(image.clone())?;
+                (None, vec![target], Some((image, pixels)))
+            }
+        };
+        let target_format = swapchain.as_ref().map_or(OFFSCREEN_FORMAT, |swapchain| swapchain.image_format());
+
+        let hdr_image = ImageView::new_default(
+            AttachmentImage::with_usage(
+                &memory_allocator,
+                dimensions,
+                HDR_FORMAT,
+                ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            )?,
+        )?;
+
+        let render_pass = get_render_pass(device.clone())?;
+        let framebuffer = Framebuffer::new(
+            render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![hdr_image.clone(), depth_buffer],
+                ..Default::default()
+            },
+        )?;
+
+        let vertex_buffer = Buffer::new_slice::<MyVertex>(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            vertex_count(options.world_size) as u64,
+        )?;
+
+        let mesh_vertex_buffer = Buffer::new_unsized::<MeshVertices>(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            std::mem::size_of::<MeshVertices>() as u64,
+        )?;
+
+        // Unsized buffers are made from the length of their slice, one per
+        // chunk for the mappings and `Voxels`, as far as the device can bind.
+        let chunk_count = options.world_size.chunk_count() as u64;
+        let voxel_slots = voxel_slots(&physical_device.device, options.world_size) as u64;
+        if voxel_slots < chunk_count {
+            eprintln!(
+                "the device only has room for {voxel_slots} of the {chunk_count} chunks, \
+                 max_storage_buffer_range is {} bytes",
+                physical_device.device.properties().max_storage_buffer_range,
+            );
+        }
+        let (chunk_mapping_buffer, voxels_buffer) = {
+            let chunk_mapping_buffer = Buffer::new_unsized::<ChunkMapping>(
+                &memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::UNIFORM_BUFFER | BufferUsage::STORAGE_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Upload,
+                    ..Default::default()
+                },
+                chunk_count,
+            )?;
+            chunk_mapping_buffer.write()?.size = options.world_size;
+
+            let voxels_buffer = Buffer::new_unsized::<Voxels>(
+                &memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::UNIFORM_BUFFER | BufferUsage::STORAGE_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Upload,
+                    ..Default::default()
+                },
+                voxel_slots,
+            )?;
+
+            (chunk_mapping_buffer, voxels_buffer)
+        };
+
+        let (light_mapping_buffer, light_chunks_buffer) = {
+            let light_mapping_buffer = Buffer::new_unsized::<LightMapping>(
+                &memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::STORAGE_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Upload,
+                    ..Default::default()
+                },
+                chunk_count,
+            )?;
+            light_mapping_buffer.write()?.size = options.world_size;
+
+            let light_chunks_buffer = Buffer::new_unsized::<LightChunks>(
+                &memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::STORAGE_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::Upload,
+                    ..Default::default()
+                },
+                std::mem::size_of::<LightChunks>() as u64,
+            )?;
+
+            (light_mapping_buffer, light_chunks_buffer)
+        };
+
+        let palette_buffer = Buffer::from_data(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            Palette::default(),
+        )?;
+
+        let camera_data_buffer = Buffer::from_iter(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            [CameraData::default()].into_iter(),
+        )?;
+
+        let selection_buffer = Buffer::from_iter(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            [SelectionData::default()].into_iter(),
+        )?;
+
+        let sun_buffer = Buffer::from_data(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            Sun::default(),
+        )?;
+
+        let render_settings_buffer = Buffer::from_data(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            RenderSettings::default(),
+        )?;
+
+        let sky_buffer = Buffer::from_data(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            Sky::default(),
+        )?;
+
+        let post_settings_buffer = Buffer::from_data(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            PostSettings {
+                srgb_swapchain: is_srgb(target_format) as u32,
+                ..Default::default()
+            },
+        )?;
+
+        let overlay_buffer = Buffer::from_iter(
+            &memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            (0..width * height).map(|_| 0u32),
+        )?;
+
+        if debug_utils {
+            debug::name_buffer(&device, &vertex_buffer, "chunk vertices");
+            debug::name_buffer(&device, &mesh_vertex_buffer, "mesh vertices");
+            debug::name_buffer(&device, &chunk_mapping_buffer, "chunk mapping");
+            debug::name_buffer(&device, &voxels_buffer, "voxels");
+            debug::name_buffer(&device, &light_mapping_buffer, "light mapping");
+            debug::name_buffer(&device, &light_chunks_buffer, "light chunks");
+            debug::name_buffer(&device, &palette_buffer, "palette");
+            debug::name_buffer(&device, &camera_data_buffer, "camera");
+            debug::name_buffer(&device, &selection_buffer, "selection");
+            debug::name_buffer(&device, &sun_buffer, "sun");
+            debug::name_buffer(&device, &render_settings_buffer, "render settings");
+            debug::name_buffer(&device, &sky_buffer, "sky");
+            debug::name_buffer(&device, &post_settings_buffer, "post settings");
+            debug::name_buffer(&device, &overlay_buffer, "overlay");
+            if let Some((_, pixels)) = &offscreen {
+                debug::name_buffer(&device, pixels, "offscreen pixels");
+            }
+        }
+
+        let accumulation_image = ImageView::new_default(
+            StorageImage::new(
+                &memory_allocator,
+                ImageDimensions::Dim2d { width, height, array_layers: 1 },
+                vulkano::format::Format::R32G32B32A32_SFLOAT,
+                Some(queue.queue_family_index()),
+            )?,
+        )?;
+
+        let vs = shaders::vs::load(device.clone())?;
+        let fs = shaders::fs::load(device.clone())?;
+
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [width as f32, height as f32],
+            depth_range: 0.0..1.0,
+        };
+
+        let pipeline = get_pipeline(
+            device.clone(),
+            vs.clone(),
+            fs.clone(),
+            render_pass.clone(),
+            viewport.clone(),
+            options.world_size,
+        )?;
+
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
+        let pipeline_layout = pipeline.layout();
+        let descriptor_set_layouts = pipeline_layout.set_layouts();
+
+        let blocks_descriptor_set = PersistentDescriptorSet::new(
+            &descriptor_set_allocator,
+            descriptor_set_layouts.get(0).unwrap().clone(),
+            [
+                WriteDescriptorSet::buffer(0, chunk_mapping_buffer.clone()),
+                WriteDescriptorSet::buffer(1, voxels_buffer.clone()),
+                WriteDescriptorSet::buffer(2, light_mapping_buffer.clone()),
+                WriteDescriptorSet::buffer(3, light_chunks_buffer.clone()),
+            ],
+        )?;
+        let render_descriptor_set = PersistentDescriptorSet::new(
+            &descriptor_set_allocator,
+            descriptor_set_layouts.get(1).unwrap().clone(),
+            [
+                WriteDescriptorSet::buffer(0, palette_buffer.clone()),
+                WriteDescriptorSet::buffer(1, camera_data_buffer.clone()),
+                WriteDescriptorSet::buffer(2, selection_buffer.clone()),
+                WriteDescriptorSet::buffer(3, sun_buffer.clone()),
+                WriteDescriptorSet::buffer(4, render_settings_buffer.clone()),
+                WriteDescriptorSet::image_view(5, accumulation_image.clone()),
+                WriteDescriptorSet::buffer(6, sky_buffer.clone()),
+            ],
+        )?;
+
+        let meshes = Meshes::new(
+            device.clone(),
+            render_pass.clone(),
+            viewport.clone(),
+            mesh_vertex_buffer.clone(),
+            camera_data_buffer.clone(),
+            &descriptor_set_allocator,
+        )?;
+
+        let post_processing = PostProcessing::new(
+            device.clone(),
+            target_format,
+            &targets,
+            hdr_image.clone(),
+            post_settings_buffer.clone(),
+            palette_buffer.clone(),
+            overlay_buffer.clone(),
+            &descriptor_set_allocator,
+        )?;
+
+        let gpu_timer = GpuTimer::new(device.clone(), queue.queue_family_index());
+        if gpu_timer.is_none() {
+            eprintln!("the queue can't write timestamps, GPU times won't be shown");
+        }
+
+        let (offscreen_image, pixels) = offscreen.unzip();
+        let recording = Recording {
+            pipeline,
+            framebuffer,
+            descriptor_sets: (blocks_descriptor_set, render_descriptor_set),
+            meshes,
+            post_processing,
+            offscreen_image,
+            allocator: cmd_buffer_allocator,
+        };
+
+        let mut renderer = Self {
+            device,
+            queue,
+            swapchain,
+            resolution: options.resolution,
+            recreate_swapchain: false,
+            vertex_count: 0,
+            chunks_left_out: 0,
+            chunk_mapping: chunk_mapping_buffer,
+            voxels: voxels_buffer,
+            light_mapping: light_mapping_buffer,
+            light_chunks: light_chunks_buffer,
+            palette: palette_buffer,
+            selection: selection_buffer,
+            sun: sun_buffer,
+            render_settings: render_settings_buffer,
+            sky: sky_buffer,
+            post_settings: post_settings_buffer,
+            overlay: overlay_buffer,
+            mesh_vertices: mesh_vertex_buffer,
+            pixels,
+            vertices: vertex_buffer,
+            camera: camera_data_buffer,
+            recording,
+            command_buffers: Vec::new(),
+            gpu_timer,
+            _debug_messenger: debug_messenger,
+        };
+        renderer.command_buffers = renderer.record_command_buffers()?;
+        Ok(renderer)
+    }
+
+    // One command buffer per image post processing draws into.
+    fn record_command_buffers(&self) -> Result<Vec<Arc<PrimaryAutoCommandBuffer>>, RendererError> {
+        let recording = &self.recording;
+        get_command_buffers(
+            &self.queue,
+            &recording.pipeline,
+            &recording.framebuffer,
+            &self.vertices,
+            &recording.descriptor_sets,
+            &recording.meshes,
+            &recording.post_processing,
+            self.gpu_timer.as_ref(),
+            recording.offscreen_image.as_ref().zip(self.pixels.as_ref()),
+            &recording.allocator,
+        )
+    }
+
+    // Replaces the swapchain with one as big as the window is now. Frames are
+    // still rendered at `resolution` and post processing stretches them over
+    // it. Minimized windows have no size, so it's left for a later frame.
+    // Offscreen renderers have nothing to recreate.
+    pub fn recreate_swapchain(&mut self) -> Result<(), RendererError> {
+        let Some(swapchain) = &self.swapchain else { return Ok(()) };
+
+        let capabilities = self
+            .device
+            .physical_device()
+            .surface_capabilities(swapchain.surface(), Default::default())?;
+        let image_extent = surface_extent(&capabilities, self.resolution);
+        if image_extent.contains(&0) {
+            return Ok(());
+        }
+
+        let (swapchain, images) = swapchain.recreate(SwapchainCreateInfo {
+            image_extent,
+            ..swapchain.create_info()
+        })?;
+        self.recording.post_processing.set_targets(&image_views(&images)?)?;
+        self.swapchain = Some(swapchain);
+        self.command_buffers = self.record_command_buffers()?;
+        self.recreate_swapchain = false;
+
+        Ok(())
+    }
+
+    // Rebuilds the boxes drawn around allocated chunks, which rays start
+    // from, after `chunk_mapping` changed.
+    pub fn upload_chunks(&mut self) {
+        let left_out = set_vertex_buffer(
+            &self.chunk_mapping.read().unwrap(),
+            &mut self.vertices.write().unwrap(),
+            &mut self.vertex_count,
+        );
+
+        // This runs every frame, so it's only said when it changes.
+        if left_out > 0 && left_out != self.chunks_left_out {
+            eprintln!("the vertex buffer has no room for {left_out} chunks, they won't be drawn");
+        }
+        self.chunks_left_out = left_out;
+    }
+
+    pub fn set_camera(&self, camera: &CameraData) {
+        self.camera.write().unwrap()[0] = *camera;
+    }
+
+    // Draws a frame from what's in the buffers and waits for the GPU to
+    // finish it. None if it was skipped because the swapchain is out of date
+    // or the window is minimized.
+    pub fn render_frame(&mut self) -> Result<Option<FrameTimes>, RendererError> {
+        if self.recreate_swapchain {
+            self.recreate_swapchain()?;
+            if self.recreate_swapchain {
+                return Ok(None);
+            }
+        }
+
+        let Some(swapchain) = self.swapchain.clone() else {
+            return self.render_offscreen().map(Some);
+        };
+
+        let (image_i, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => {
+                    self.recreate_swapchain = true;
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+        if suboptimal {
+            self.recreate_swapchain = true;
+        }
+
+        let execution = sync::now(self.device.clone())
+            .join(acquire_future)
+            .then_execute(self.queue.clone(), self.command_buffers[image_i as usize].clone())?
+            .then_swapchain_present(
+                self.queue.clone(),
+                SwapchainPresentInfo::swapchain_image_index(swapchain, image_i),
+            )
+            .then_signal_fence_and_flush();
+
+        let future = match execution {
+            Ok(future) => future,
+            Err(FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let wait_time = Instant::now();
+        future.wait(None)?;
+
+        Ok(Some(FrameTimes {
+            wait: wait_time.elapsed(),
+            gpu: self.gpu_timer.as_ref().and_then(GpuTimer::read),
+        }))
+    }
+
+    fn render_offscreen(&self) -> Result<FrameTimes, RendererError> {
+        let future = sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), self.command_buffers[0].clone())?
+            .then_signal_fence_and_flush()?;
+
+        let wait_time = Instant::now();
+        future.wait(None)?;
+
+        Ok(FrameTimes {
+            wait: wait_time.elapsed(),
+            gpu: self.gpu_timer.as_ref().and_then(GpuTimer::read),
+        })
+    }
+}
+
+// What the surface says its images have to be, otherwise `resolution` as far
+// as the surface allows.
+fn surface_extent(capabilities: &SurfaceCapabilities, resolution: [u32; 2]) -> [u32; 2] {
+    capabilities.current_extent.unwrap_or_else(|| {
+        [0, 1].map(|i| resolution[i].clamp(capabilities.min_image_extent[i], capabilities.max_image_extent[i]))
+    })
+}
+
+fn create_swapchain(
+    device: &Arc<Device>,
+    surface: Arc<Surface>,
+    resolution: [u32; 2],
+) -> Result<(Arc<Swapchain>, Vec<Arc<SwapchainImage>>), RendererError> {
+    let physical_device = device.physical_device();
+    let capabilities = physical_device.surface_capabilities(&surface, Default::default())?;
+
+    let composite_alpha = pick_best_composite_alpha(capabilities.supported_composite_alpha)
+        .ok_or_else(|| RendererError::Setup("the window can't be drawn to opaquely or blended".to_string()))?;
+    let (image_format, image_color_space) =
+        pick_surface_format(&physical_device.surface_formats(&surface, Default::default())?)
+            .ok_or_else(|| RendererError::Setup("the window supports no image formats".to_string()))?;
+    eprintln!("presenting as {image_format:?} in {image_color_space:?}");
+
+    Ok(Swapchain::new(
+        device.clone(),
+        surface,
+        SwapchainCreateInfo {
+            // How many buffers to use in the swapchain
+            min_image_count: capabilities.min_image_count + 1,
+            image_format: Some(image_format),
+            image_color_space,
+            image_extent: surface_extent(&capabilities, resolution),
+            // What the images are going to be used for
+            image_usage: ImageUsage::COLOR_ATTACHMENT,
+            composite_alpha,
+            ..Default::default()
+        },
+    )?)
+}
+
+fn image_views(images: &[Arc<SwapchainImage>]) -> Result<Vec<Arc<dyn ImageViewAbstract>>, RendererError> {
+    images
+        .iter()
+        .map(|image| -> Result<Arc<dyn ImageViewAbstract>, RendererError> {
+            Ok(ImageView::new_default(image.clone())?)
+        })
+        .collect()
+}
+
+fn get_render_pass(device: Arc<Device>) -> Result<Arc<RenderPass>, RendererError> {
+    Ok(vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: HDR_FORMAT, // post processing takes it to the swapchain from there
+                samples: 1,
+            },
+             depth: {
+                load: Clear,
+                store: DontCare,
+                format: DEPTH_FORMAT,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth},
+        },
+    )?)
+}
+
+fn get_pipeline(
+    device: Arc<Device>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    render_pass: Arc<RenderPass>,
+    viewport: Viewport,
+    world_size: WorldSize,
+) -> Result<Arc<GraphicsPipeline>, RendererError> {
+    let constants = shaders::fs::SpecializationConstants {
+        CHUNK_COUNT_X: world_size.chunks.x,
+        CHUNK_COUNT_Y: world_size.chunks.y,
+        CHUNK_COUNT_Z: world_size.chunks.z,
+    };
+
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(MyVertex::per_vertex())
+        .vertex_shader(vs.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
+        .fragment_shader(fs.entry_point("main").unwrap(), constants)
+        .depth_stencil_state(DepthStencilState::simple_depth_test())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device)?)
+}
+
+pub fn pick_best_composite_alpha(from: CompositeAlphas) -> Option<CompositeAlpha> {
+    if from.intersects(CompositeAlphas::OPAQUE) {
+        Some(CompositeAlpha::Opaque)
+    } else if from.intersects(CompositeAlphas::INHERIT) {
+        Some(CompositeAlpha::Inherit)
+    } else if from.intersects(CompositeAlphas::PRE_MULTIPLIED) {
+        Some(CompositeAlpha::PreMultiplied)
+    } else if from.intersects(CompositeAlphas::POST_MULTIPLIED) {
+        Some(CompositeAlpha::PostMultiplied)
+    } else {
+        None
+    }
+}
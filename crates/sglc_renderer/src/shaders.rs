@@ -0,0 +1,863 @@
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 0) out vec3 positionOut;
+            layout(location = 1) flat out uint skyOut;
+
+            layout(set = 1, binding = 1) uniform CameraData {
+                float aspect_ratio;
+                float yaw;
+                float pitch;
+                float focal_length;
+                vec3 position;
+                mat4 camera;
+                mat4 proj;
+                mat4 camRot;
+            } cam;
+
+            // Covers the whole screen behind everything else, so pixels
+            // without chunks still get the sky.
+            const vec2 SKY_TRIANGLE[3] = vec2[](vec2(-1.0, -1.0), vec2(3.0, -1.0), vec2(-1.0, 3.0));
+
+            void main() {
+                if (gl_InstanceIndex == 1) {
+                    positionOut = cam.position;
+                    skyOut = 1;
+                    gl_Position = vec4(SKY_TRIANGLE[gl_VertexIndex], 0.99999, 1.0);
+                    return;
+                }
+
+                vec4 cameraSpacePosition = cam.proj * cam.camera * vec4(position, 1.0);
+                
+                positionOut = position;
+                skyOut = 0;
+                gl_Position = cameraSpacePosition;
+            }
+        ",
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        include: ["../sglc_shared/src"],
+        src: "
+            #version 460
+
+            layout(location = 0) out vec4 f_color;
+            layout(location = 0) in vec3 startPos;
+            layout(location = 1) flat in uint isSky;
+
+            const uint CHUNK_SIZE_ONE = 8;
+            const uint CHUNK_SIZE = CHUNK_SIZE_ONE * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE;
+            // Chunks along each axis of the world, `WorldSize` in sglc_shared.
+            layout(constant_id = 0) const uint CHUNK_COUNT_X = 128;
+            layout(constant_id = 1) const uint CHUNK_COUNT_Y = 128;
+            layout(constant_id = 2) const uint CHUNK_COUNT_Z = 128;
+            const uvec3 WORLD_SIZE = uvec3(
+                CHUNK_COUNT_X * CHUNK_SIZE_ONE,
+                CHUNK_COUNT_Y * CHUNK_SIZE_ONE,
+                CHUNK_COUNT_Z * CHUNK_SIZE_ONE
+            );
+
+            // The mappings start with the world size, which is read from the
+            // constants above instead.
+            layout(set = 0, binding = 0) buffer ChunkMapping {
+                uvec4 size;
+                uint data[];
+            } chunk_mapping;
+
+            layout(set = 0, binding = 1) buffer Voxels {
+                uint data[];
+            } voxels;
+
+            // Mirrors light.rs in sglc_shared, a byte per voxel, four to a
+            // uint.
+            const uint MAX_LIGHT = 15;
+            const uint LIGHT_WORDS = CHUNK_SIZE / 4;
+            const uint LIGHT_CHUNK_CAPACITY = 65536;
+
+            layout(set = 0, binding = 2) buffer LightMapping {
+                uvec4 size;
+                uint data[];
+            } light_mapping;
+
+            layout(set = 0, binding = 3) buffer LightChunks {
+                uint data[LIGHT_WORDS * LIGHT_CHUNK_CAPACITY];
+            } light_chunks;
+
+            #include <palette.glsl>
+            #include <sun.glsl>
+            #include <render_settings.glsl>
+            #include <sky.glsl>
+
+            // Sums of path traced samples in rgb and how many there are in a.
+            layout(set = 1, binding = 5, rgba32f) uniform image2D accumulation;
+
+            layout(set = 1, binding = 1) uniform CameraData {
+                float aspect_ratio;
+                float yaw;
+                float pitch;
+                float focal_length;
+                vec3 position;
+                mat4 camera;
+                mat4 proj;
+                mat4 camRot;
+            } cam;
+
+            layout(set = 1, binding = 2) uniform SelectionData {
+                ivec3 voxel;
+                uint active;
+                ivec3 region_min;
+                uint region_active;
+                ivec3 region_max;
+            } selection;
+
+            uint chunk_index_of(uvec3 chunkPos) {
+                return chunkPos.x + (chunkPos.y + chunkPos.z * CHUNK_COUNT_Y) * CHUNK_COUNT_X;
+            }
+
+            uint voxel_unit_at(vec3 _pos) {
+                if (_pos.x < 0.0 || _pos.x > WORLD_SIZE.x - 1
+                    || _pos.y < 0.0 || _pos.y > WORLD_SIZE.y - 1
+                    || _pos.z < 0.0 || _pos.z > WORLD_SIZE.z - 1) {
+                    return 1;
+                }
+
+                uvec3 pos = uvec3(_pos);
+                uvec3 chunkPos = pos / CHUNK_SIZE_ONE;
+                uvec3 posInChunk = pos % CHUNK_SIZE_ONE;
+
+                uint chunk_index = chunk_index_of(chunkPos);
+
+                uint voxel_index = uint(
+                    posInChunk.x + posInChunk.y * CHUNK_SIZE_ONE + posInChunk.z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE
+                );
+
+                return 
+                    uint(voxels.data[chunk_mapping.data[chunk_index] * CHUNK_SIZE + voxel_index]);
+            }
+
+            // Block light level at the voxel, from 0 to 1.
+            float light_at(ivec3 pos) {
+                if (any(lessThan(pos, ivec3(0))) || any(greaterThanEqual(pos, ivec3(WORLD_SIZE)))) {
+                    return 0.0;
+                }
+
+                uvec3 chunk_pos = uvec3(pos) / CHUNK_SIZE_ONE;
+                uvec3 in_chunk = uvec3(pos) - chunk_pos * CHUNK_SIZE_ONE;
+                uint chunk_index = chunk_index_of(chunk_pos);
+                uint index = in_chunk.x + in_chunk.y * CHUNK_SIZE_ONE + in_chunk.z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE;
+
+                uint word = light_chunks.data[light_mapping.data[chunk_index] * LIGHT_WORDS + index / 4u];
+                return float((word >> (index % 4u * 8u)) & 0xffu) / float(MAX_LIGHT);
+            }
+
+            float size_of_min_dimension(vec3 vector) {
+                return min(vector.x, min(vector.y, vector.z));
+            }
+
+            struct hit {
+                vec3 pos;
+                vec3 normal;
+                bool air;
+
+                uint unit_code;
+                ivec3 voxel;
+            };
+
+            // Random numbers, mirrored by shade.rs in sglc_hotcode.
+            uint hash(uint x) {
+                x ^= x >> 16;
+                x *= 0x7feb352dU;
+                x ^= x >> 15;
+                x *= 0x846ca68bU;
+                x ^= x >> 16;
+                return x;
+            }
+
+            float random(inout uint seed) {
+                seed = hash(seed);
+                return float(seed >> 8) / 16777216.0;
+            }
+
+            // How much work the last hit_in_direction took, for the debug
+            // views. Mirrors RayStats in raycast.rs.
+            uint debug_steps = 0;
+            uint debug_skipped = 0;
+
+            hit hit_in_direction(vec3 ro, vec3 rd) {
+                debug_steps = 0;
+                debug_skipped = 0;
+                vec3 check_point = floor(ro);
+                float xy = rd.x / rd.y;
+                float yz = rd.y / rd.z;
+                float zx = rd.z / rd.x;
+                float xz = rd.x / rd.z;
+                float yx = rd.y / rd.x;
+                float zy = rd.z / rd.y;
+
+                vec3 ray_unit_step_size = vec3(
+                    sqrt(1 + zx * zx + yx * yx),
+                    sqrt(1 + xy * xy + zy * zy),
+                    sqrt(1 + xz * xz + yz * yz)
+                );
+                vec3 step = sign(rd);
+                vec3 ray_length = (step * (check_point - ro) + (step / 2 + 0.5)) * ray_unit_step_size;
+
+                vec3 comp;
+                uint unit_at_check_point;
+                for (int i = 0; i < WORLD_SIZE.x + WORLD_SIZE.y + WORLD_SIZE.z; i++) {
+                    comp = vec3(bvec3(
+                        ray_length.x < ray_length.y && ray_length.x <= ray_length.z,
+                        ray_length.y < ray_length.z && ray_length.y <= ray_length.x,
+                        ray_length.z < ray_length.x && ray_length.z <= ray_length.y
+                    ));
+
+                    check_point += comp * step;
+
+                    unit_at_check_point = voxel_unit_at(check_point);
+                    debug_steps++;
+                    if(unit_at_check_point >= 1) {
+                        if (unit_at_check_point == 2) {
+                            // we are in a chunk filled with air
+                            uvec3 first_chunk = uvec3(check_point) / CHUNK_SIZE_ONE;
+
+                            ray_length += comp * ray_unit_step_size;
+
+                            uvec3 current_chunk = first_chunk;
+                            uint moves = 0;
+                            while (current_chunk == first_chunk && moves < 8) { 
+                                comp = vec3(bvec3(
+                                    ray_length.x < ray_length.y && ray_length.x <= ray_length.z,
+                                    ray_length.y < ray_length.z && ray_length.y <= ray_length.x,
+                                    ray_length.z < ray_length.x && ray_length.z <= ray_length.y
+                                ));
+                                
+                                check_point += comp * step;
+                                ray_length += comp * ray_unit_step_size;
+
+                                current_chunk = uvec3(check_point) / CHUNK_SIZE_ONE;
+                                moves++;
+                                debug_skipped++;
+                            }
+
+                            continue;
+                        }
+
+                        return hit(ro + rd * size_of_min_dimension(ray_length), - comp * step, unit_at_check_point == 1, unit_at_check_point, ivec3(check_point));
+                    }
+
+                    ray_length += comp * ray_unit_step_size;
+                };
+
+                return hit(vec3(0.0), vec3(0.0), true, 0, ivec3(-1));
+            }
+            
+            vec3 sky_color(vec3 rd) {
+                float up = -rd.y;
+                vec3 color = up > 0.0
+                    ? mix(sky.horizon_color, sky.zenith_color, sqrt(up))
+                    : mix(sky.horizon_color, sky.ground_color, sqrt(-up));
+
+                float to_sun = dot(rd, sun.direction);
+                float disk = to_sun > cos(sky.sun_disk_size) && up > 0.0 ? 1.0 : 0.0;
+                return color + sun.color * sun.intensity * (disk + pow(max(to_sun, 0.0), 256.0) * 0.5);
+            }
+
+            vec3 apply_fog(vec3 color, float ray_length) {
+                float fog = 1.0 - exp(-sky.fog_density * max(ray_length - sky.fog_start, 0.0));
+                return mix(color, sky.fog_color, fog);
+            }
+
+            void basis(vec3 normal, out vec3 tangent, out vec3 bitangent) {
+                tangent = normalize(cross(normal, abs(normal.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0)));
+                bitangent = cross(normal, tangent);
+            }
+
+            vec3 jittered_sun_direction(inout uint seed) {
+                vec3 tangent, bitangent;
+                basis(sun.direction, tangent, bitangent);
+
+                float radius = sun.softness * sqrt(random(seed));
+                float angle = 6.28318530718 * random(seed);
+
+                return normalize(sun.direction + (tangent * cos(angle) + bitangent * sin(angle)) * radius);
+            }
+
+            float sun_visibility(vec3 pos, inout uint seed) {
+                if (sun.shadow_samples <= 1) {
+                    return hit_in_direction(pos, sun.direction).air ? 1.0 : 0.0;
+                }
+
+                uint lit = 0;
+                for (uint i = 0; i < sun.shadow_samples; i++) {
+                    if (hit_in_direction(pos, jittered_sun_direction(seed)).air) {
+                        lit++;
+                    }
+                }
+
+                return float(lit) / float(sun.shadow_samples);
+            }
+
+            float solid(ivec3 pos) {
+                return voxel_unit_at(vec3(pos)) > 2 ? 1.0 : 0.0;
+            }
+
+            // Occlusion at the corner of a face between the directions u
+            // and v, from the voxels in front of the face.
+            float vertex_ao(ivec3 outside, ivec3 u, ivec3 v) {
+                float side1 = solid(outside + u);
+                float side2 = solid(outside + v);
+                float corner = settings.ao_quality >= 2 ? solid(outside + u + v) : 0.0;
+
+                if (side1 + side2 == 2.0) {
+                    return 0.0;
+                }
+
+                return (3.0 - side1 - side2 - corner) / 3.0;
+            }
+
+            float ambient_occlusion(hit albedo) {
+                if (settings.ao_quality == 0) {
+                    return 1.0;
+                }
+
+                ivec3 n = ivec3(albedo.normal);
+                ivec3 u = n.x != 0 ? ivec3(0, 1, 0) : ivec3(1, 0, 0);
+                ivec3 v = n.z != 0 ? ivec3(0, 1, 0) : ivec3(0, 0, 1);
+                ivec3 outside = albedo.voxel + n;
+
+                vec3 in_voxel = albedo.pos - vec3(albedo.voxel);
+                vec2 uv = clamp(vec2(dot(in_voxel, vec3(u)), dot(in_voxel, vec3(v))), 0.0, 1.0);
+
+                float bottom = mix(vertex_ao(outside, -u, -v), vertex_ao(outside, u, -v), uv.x);
+                float top = mix(vertex_ao(outside, -u, v), vertex_ao(outside, u, v), uv.x);
+
+                return mix(1.0, mix(bottom, top, uv.y), settings.ao_strength);
+            }
+
+            const vec3 BLOCK_LIGHT_COLOR = vec3(1.0, 0.85, 0.6);
+
+            vec3 shade(vec3 rd, hit albedo, inout uint seed) {
+                Material material = palette.materials[albedo.unit_code];
+                float ao = ambient_occlusion(albedo);
+
+                float lambert = max(dot(albedo.normal, sun.direction), 0.0);
+                float visibility = lambert > 0.0 ? sun_visibility(albedo.pos + albedo.normal, seed) : 0.0;
+                vec3 sunlight = sun.color * sun.intensity * visibility;
+
+                // Shiny materials get a highlight, metals tint it with
+                // their own color.
+                vec3 half_vector = normalize(sun.direction - rd);
+                float shininess = mix(256.0, 2.0, material.roughness);
+                float highlight = pow(max(dot(albedo.normal, half_vector), 0.0), shininess) * (1.0 - material.roughness);
+                vec3 specular = mix(vec3(1.0), material.color.rgb, material.metalness) * highlight;
+
+                // Warm light from emissive voxels, taken from the air in
+                // front of the face.
+                vec3 block_light = settings.block_light * light_at(albedo.voxel + ivec3(albedo.normal)) * BLOCK_LIGHT_COLOR;
+
+                vec3 diffuse = material.color.rgb * (1.0 - material.metalness * 0.5);
+                return diffuse * (sun.ambient + sunlight * lambert + block_light) * ao + specular * sunlight
+                    + material.color.rgb * material.emissive;
+            }
+
+            float schlick(float cos_theta, float ior) {
+                float r0 = pow((1.0 - ior) / (1.0 + ior), 2.0);
+                return r0 + (1.0 - r0) * pow(1.0 - cos_theta, 5.0);
+            }
+
+            const int MAX_MEDIUM_STEPS = 64;
+
+            // Marches from inside voxels of `unit` to where the ray leaves
+            // them. The normal faces back into the medium.
+            hit leave_medium(vec3 ro, vec3 rd, uint unit) {
+                rd += vec3(equal(rd, vec3(0.0))) * 1e-5;
+                ivec3 voxel = ivec3(floor(ro));
+                vec3 step = sign(rd);
+                vec3 t_delta = abs(1.0 / rd);
+                vec3 t_max = (step * (vec3(voxel) - ro) + max(step, 0.0)) * t_delta;
+                vec3 normal = vec3(0.0);
+                float t = 0.0;
+
+                for (int i = 0; i < MAX_MEDIUM_STEPS; i++) {
+                    if (voxel_unit_at(vec3(voxel)) != unit) {
+                        break;
+                    }
+
+                    int axis = t_max.x < t_max.y && t_max.x <= t_max.z ? 0 : (t_max.y < t_max.z ? 1 : 2);
+
+                    t = t_max[axis];
+                    t_max[axis] += t_delta[axis];
+                    voxel[axis] += int(step[axis]);
+                    normal = vec3(0.0);
+                    normal[axis] = -step[axis];
+                }
+
+                return hit(ro + rd * t, normal, false, unit, voxel);
+            }
+
+            // Follows mirror reflections and refraction through glass for up
+            // to max_secondary_rays, shading whatever the ray ends up on.
+            vec3 trace(vec3 rd, hit h, inout uint seed) {
+                vec3 color = vec3(0.0);
+                vec3 weight = vec3(1.0);
+
+                for (uint bounce = 0; bounce <= settings.max_secondary_rays; bounce++) {
+                    Material material = palette.materials[h.unit_code];
+                    vec3 local = shade(rd, h, seed);
+                    float reflectivity = material.metalness * (1.0 - material.roughness);
+                    bool last = bounce == settings.max_secondary_rays;
+                    vec3 ro;
+
+                    if (material.transparency > 0.0 && !last) {
+                        color += weight * (1.0 - material.transparency) * local;
+                        vec3 glass = weight * material.transparency;
+                        float fresnel = schlick(-dot(h.normal, rd), material.ior);
+
+                        // The reflection off the glass is only followed one
+                        // ray deep.
+                        vec3 reflected = reflect(rd, h.normal);
+                        hit reflection = hit_in_direction(h.pos + h.normal * 0.01, reflected);
+                        color += glass * fresnel * (reflection.air ? sky_color(reflected) : shade(reflected, reflection, seed));
+
+                        vec3 inside = refract(rd, h.normal, 1.0 / material.ior);
+                        hit exit = hit(h.pos - h.normal * 0.01, vec3(0.0), false, h.unit_code, h.voxel);
+                        vec3 out_direction = vec3(0.0);
+                        for (int i = 0; i < 4; i++) {
+                            exit = leave_medium(exit.pos, inside, h.unit_code);
+                            out_direction = refract(inside, exit.normal, material.ior);
+                            if (out_direction != vec3(0.0)) {
+                                break;
+                            }
+
+                            inside = reflect(inside, exit.normal);
+                            exit.pos += exit.normal * 0.01;
+                        }
+
+                        if (out_direction == vec3(0.0)) {
+                            break;
+                        }
+
+                        weight = glass * (1.0 - fresnel) * material.color.rgb;
+                        ro = exit.pos - exit.normal * 0.01;
+                        rd = out_direction;
+                    } else if (reflectivity > 0.0 && !last) {
+                        color += weight * (1.0 - reflectivity) * local;
+                        weight *= reflectivity * material.color.rgb;
+                        ro = h.pos + h.normal * 0.01;
+                        rd = reflect(rd, h.normal);
+                    } else {
+                        color += weight * local;
+                        break;
+                    }
+
+                    h = hit_in_direction(ro, rd);
+                    if (h.air) {
+                        color += weight * sky_color(rd);
+                        break;
+                    }
+                }
+
+                return color;
+            }
+
+            vec3 cosine_direction(vec3 normal, inout uint seed) {
+                vec3 tangent, bitangent;
+                basis(normal, tangent, bitangent);
+
+                float radius = sqrt(random(seed));
+                float angle = 6.28318530718 * random(seed);
+
+                return normalize(tangent * radius * cos(angle) + bitangent * radius * sin(angle) + normal * sqrt(1.0 - radius * radius));
+            }
+
+            // One sample of diffuse light along the ray. Emissive materials,
+            // the sun and the sky are the light sources.
+            vec3 path_trace(vec3 ro, vec3 rd, inout uint seed) {
+                vec3 throughput = vec3(1.0);
+                vec3 radiance = vec3(0.0);
+                float first_distance = -1.0;
+
+                for (uint bounce = 0; bounce <= settings.max_bounces; bounce++) {
+                    hit h = hit_in_direction(ro, rd);
+                    if (h.air) {
+                        radiance += throughput * sky_color(rd);
+                        break;
+                    }
+
+                    if (bounce == 0) {
+                        first_distance = distance(cam.position, h.pos);
+                    }
+
+                    Material material = palette.materials[h.unit_code];
+                    radiance += throughput * material.color.rgb * material.emissive;
+
+                    float lambert = max(dot(h.normal, sun.direction), 0.0);
+                    if (lambert > 0.0) {
+                        float visibility = sun_visibility(h.pos + h.normal, seed);
+                        radiance += throughput * material.color.rgb * sun.color * sun.intensity * lambert * visibility;
+                    }
+
+                    throughput *= material.color.rgb;
+                    ro = h.pos + h.normal * 0.01;
+                    rd = cosine_direction(h.normal, seed);
+                }
+
+                return first_distance < 0.0 ? radiance : apply_fog(radiance, first_distance);
+            }
+
+            // Moves the ray's origin to where it enters the world, so every
+            // fragment of a pixel traces the exact same path.
+            vec3 enter_world(vec3 ro, vec3 rd) {
+                vec3 t0 = (vec3(0.0) - ro) / rd;
+                vec3 t1 = (vec3(WORLD_SIZE) - ro) / rd;
+                vec3 t_near = min(t0, t1);
+                float t_enter = max(max(t_near.x, t_near.y), t_near.z);
+
+                return ro + rd * (max(t_enter, 0.0) + 0.001);
+            }
+
+            // Adds this frame's sample to the accumulation. Chunk proxies
+            // overlap, so a pixel can get here more than once a frame, but
+            // every time with the same sample, which then only counts once.
+            vec4 accumulate(vec3 rd) {
+                uvec2 pixel = uvec2(gl_FragCoord.xy);
+                vec4 previous = settings.sample_count == 0 ? vec4(0.0) : imageLoad(accumulation, ivec2(pixel));
+
+                if (previous.a < float(settings.sample_count + 1)) {
+                    uint seed = hash(hash(pixel.x + pixel.y * 65536) + settings.sample_count);
+                    previous += vec4(path_trace(enter_world(cam.position, rd), rd, seed), 1.0);
+                    imageStore(accumulation, ivec2(pixel), previous);
+                }
+
+                return vec4(previous.rgb / previous.a, 1.0);
+            }
+
+            // Same depth as the vertex shader gives the sky triangle.
+            const float SKY_DEPTH = 0.99999;
+
+            // Depth of a point in the world, as the rasterizer would have
+            // written it for a triangle there.
+            float depth_at(vec3 pos) {
+                vec4 clip = cam.proj * cam.camera * vec4(pos, 1.0);
+                return clip.z / clip.w;
+            }
+
+            // Mirrors debug_color in shade.rs.
+            const float DEBUG_MAX_STEPS = 256.0;
+            const float DEBUG_DEPTH_RANGE = 512.0;
+
+            vec3 heat(float t) {
+                t = clamp(t, 0.0, 1.0);
+                return vec3(t, 1.0 - abs(t * 2.0 - 1.0), 1.0 - t);
+            }
+
+            // The debug views march from the camera like the path tracer
+            // does, so they look the same on every chunk proxy.
+            vec3 debug_color(vec3 rd) {
+                hit albedo = hit_in_direction(enter_world(cam.position, rd), rd);
+                float steps = float(debug_steps) / DEBUG_MAX_STEPS;
+
+                if (!albedo.air) {
+                    gl_FragDepth = depth_at(albedo.pos);
+                }
+
+                if (settings.debug_mode == 2) {
+                    return heat(steps);
+                }
+                if (settings.debug_mode == 5) {
+                    return vec3(steps, float(debug_skipped) / DEBUG_MAX_STEPS, 0.0);
+                }
+                if (albedo.air) {
+                    return vec3(0.0);
+                }
+                if (settings.debug_mode == 1) {
+                    return albedo.normal * 0.5 + 0.5;
+                }
+                if (settings.debug_mode == 3) {
+                    // Distance to the nearest chunk boundary along the face.
+                    vec3 local = fract(albedo.pos / float(CHUNK_SIZE_ONE));
+                    vec3 edges = mix(min(local, 1.0 - local) * float(CHUNK_SIZE_ONE), vec3(1e9), abs(albedo.normal));
+
+                    if (size_of_min_dimension(edges) < 0.1) {
+                        return vec3(1.0);
+                    }
+
+                    vec3 color = palette.materials[albedo.unit_code].color.rgb;
+                    return color * (0.6 + 0.4 * max(dot(albedo.normal, sun.direction), 0.0));
+                }
+                if (settings.debug_mode == 4) {
+                    uvec3 chunk_pos = uvec3(albedo.voxel) / CHUNK_SIZE_ONE;
+                    uint chunk_index = chunk_index_of(chunk_pos);
+                    uint slot = hash(chunk_mapping.data[chunk_index]);
+                    return vec3(slot & 0xffu, (slot >> 8) & 0xffu, (slot >> 16) & 0xffu) / 255.0;
+                }
+
+                return vec3(1.0 - clamp(distance(cam.position, albedo.pos) / DEBUG_DEPTH_RANGE, 0.0, 1.0));
+            }
+
+            void main() {
+                float fov = 1.0;
+                vec2 resolution = vec2(imageSize(accumulation));
+                vec2 screenpos = (gl_FragCoord.xy - resolution / 2.0) / (resolution.x / 2.0);
+                vec3 rd = (
+                    cam.camRot * 
+                    vec4(
+                        normalize(
+                            vec3(
+                                screenpos.xy,
+                                cam.focal_length
+                            )
+                        ), 
+                        1000.0
+                    )
+                ).xyz;
+
+                // Anything that doesn't hit a voxel ends up as far back as
+                // the sky, so meshes behind empty chunk proxies still show.
+                gl_FragDepth = SKY_DEPTH;
+
+                if (settings.debug_mode != 0) {
+                    f_color = vec4(debug_color(rd), 1.0);
+                    return;
+                }
+
+                if (isSky == 1) {
+                    f_color = settings.beauty == 1 ? accumulate(rd) : vec4(sky_color(rd), 1.0);
+                    return;
+                }
+
+                vec3 ro = cam.position;
+                ro += rd * (distance(cam.position, startPos) - 2);
+
+                hit albedo = hit_in_direction(ro, rd);
+
+                if (!albedo.air) {
+                    gl_FragDepth = depth_at(albedo.pos);
+                }
+
+                if (settings.beauty == 1) {
+                    f_color = accumulate(rd);
+                    return;
+                }
+
+                if (albedo.air) {
+                    f_color = vec4(sky_color(rd), 1.0);
+                    return;
+                }
+
+                if (albedo.unit_code > 2) {
+                    uvec2 pixel = uvec2(gl_FragCoord.xy);
+                    uint seed = hash(pixel.x + pixel.y * 65536);
+
+                    f_color = vec4(apply_fog(trace(rd, albedo, seed), distance(cam.position, albedo.pos)), 1.0);
+
+                    if (selection.region_active == 1
+                        && all(greaterThanEqual(albedo.voxel, selection.region_min))
+                        && all(lessThanEqual(albedo.voxel, selection.region_max))) {
+                        f_color.rgb = mix(f_color.rgb, vec3(0.2, 0.4, 1.0), 0.3);
+                    }
+
+                    if (selection.active == 1 && albedo.voxel == selection.voxel) {
+                        f_color.rgb = mix(f_color.rgb, vec3(1.0), 0.5);
+                    }
+
+                    return;
+                }
+            }
+        ",
+    }
+}
+
+// Ordinary triangle meshes, depth tested against the depth the voxels
+// write.
+pub mod mesh_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 position;
+            layout(location = 1) in vec3 color;
+            layout(location = 0) out vec3 colorOut;
+
+            layout(set = 0, binding = 0) uniform CameraData {
+                float aspect_ratio;
+                float yaw;
+                float pitch;
+                float focal_length;
+                vec3 position;
+                mat4 camera;
+                mat4 proj;
+                mat4 camRot;
+            } cam;
+
+            void main() {
+                colorOut = color;
+                gl_Position = cam.proj * cam.camera * vec4(position, 1.0);
+            }
+        ",
+    }
+}
+
+pub mod mesh_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+            #version 460
+
+            layout(location = 0) in vec3 color;
+            layout(location = 0) out vec4 f_color;
+
+            void main() {
+                f_color = vec4(color, 1.0);
+            }
+        ",
+    }
+}
+
+pub mod post_vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+            #version 460
+
+            const vec2 TRIANGLE[3] = vec2[](vec2(-1.0, -1.0), vec2(3.0, -1.0), vec2(-1.0, 3.0));
+
+            void main() {
+                gl_Position = vec4(TRIANGLE[gl_VertexIndex], 0.0, 1.0);
+            }
+        ",
+    }
+}
+
+// Mirrors post.rs in sglc_hotcode.
+pub mod post_fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        include: ["../sglc_shared/src"],
+        src: "
+            #version 460
+
+            layout(location = 0) out vec4 f_color;
+
+            layout(set = 0, binding = 0) uniform sampler2D hdr;
+
+            // The size of the image being drawn into, which the HDR image
+            // gets stretched over.
+            layout(push_constant) uniform Target {
+                vec2 size;
+            } target;
+
+            // Text and panels drawn on the CPU, see overlay.rs in
+            // sglc_hotcode.
+            layout(set = 0, binding = 2) readonly buffer Overlay {
+                uint pixels[];
+            } overlay;
+
+            #include <post_settings.glsl>
+            #include <palette.glsl>
+
+            const int BLOOM_STEP = 2;
+            const int BLOOM_TAPS = 4;
+            const float BLOOM_SIGMA = 4.0;
+            const uint FIRST_DRAWN_UNIT = 3;
+
+            const float BAYER[16] = float[](
+                0.0, 8.0, 2.0, 10.0,
+                12.0, 4.0, 14.0, 6.0,
+                3.0, 11.0, 1.0, 9.0,
+                15.0, 7.0, 13.0, 5.0
+            );
+
+            vec3 tone_map(vec3 color) {
+                if (post.tone_mapping == 1) {
+                    return color / (color + 1.0);
+                }
+                if (post.tone_mapping == 2) {
+                    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+                }
+                return clamp(color, 0.0, 1.0);
+            }
+
+            vec3 linear_to_srgb(vec3 color) {
+                return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, greaterThan(color, vec3(0.0031308)));
+            }
+
+            vec3 srgb_to_linear(vec3 color) {
+                return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), greaterThan(color, vec3(0.04045)));
+            }
+
+            vec3 bloom(ivec2 pixel) {
+                ivec2 max_pixel = textureSize(hdr, 0) - 1;
+                vec3 sum = vec3(0.0);
+                float weights = 0.0;
+
+                for (int y = -BLOOM_TAPS; y <= BLOOM_TAPS; y++) {
+                    for (int x = -BLOOM_TAPS; x <= BLOOM_TAPS; x++) {
+                        ivec2 offset = ivec2(x, y) * BLOOM_STEP;
+                        vec3 color = texelFetch(hdr, clamp(pixel + offset, ivec2(0), max_pixel), 0).rgb;
+                        float weight = exp(-float(dot(offset, offset)) / (2.0 * BLOOM_SIGMA * BLOOM_SIGMA));
+
+                        sum += max(color - post.bloom_threshold, 0.0) * weight;
+                        weights += weight;
+                    }
+                }
+
+                return sum / weights;
+            }
+
+            // Palette colors count as sRGB encoded, like pixel art palettes
+            // are.
+            vec3 nearest_palette_color(vec3 color) {
+                vec3 nearest = color;
+                float nearest_distance = 1e9;
+
+                for (uint i = FIRST_DRAWN_UNIT; i < 256; i++) {
+                    vec3 candidate = palette.materials[i].color.rgb;
+                    vec3 difference = candidate - color;
+                    float d = dot(difference, difference);
+                    if (d < nearest_distance) {
+                        nearest = candidate;
+                        nearest_distance = d;
+                    }
+                }
+
+                return nearest;
+            }
+
+            void main() {
+                vec2 uv = gl_FragCoord.xy / target.size;
+                ivec2 pixel = ivec2(uv * vec2(textureSize(hdr, 0)));
+                vec3 color = texelFetch(hdr, pixel, 0).rgb;
+
+                if (post.bloom_strength > 0.0) {
+                    color += bloom(pixel) * post.bloom_strength;
+                }
+
+                color = tone_map(color * exp2(post.exposure));
+
+                vec2 centered = uv - 0.5;
+                color *= 1.0 - post.vignette * dot(centered, centered) * 2.0;
+
+                vec3 display = linear_to_srgb(color);
+
+                if (post.dither == 1) {
+                    float threshold = BAYER[pixel.x % 4 + pixel.y % 4 * 4] / 16.0;
+                    display = nearest_palette_color(display + (threshold - 0.5) / 8.0);
+                }
+
+                vec4 overlay_color = unpackUnorm4x8(overlay.pixels[pixel.x + pixel.y * textureSize(hdr, 0).x]);
+                display = mix(display, overlay_color.rgb, overlay_color.a);
+
+                f_color = vec4(post.srgb_swapchain == 1 ? srgb_to_linear(display) : display, 1.0);
+            }
+        ",
+    }
+}
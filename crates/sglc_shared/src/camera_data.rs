@@ -0,0 +1,44 @@
+use glam::{Vec2, Vec3, Mat4, Quat};
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct CameraData {
+    pub aspect_ratio: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub focal_length: f32,
+    pub position: Vec3,
+    pub _padding2: f32,
+    pub camera: Mat4,
+    pub proj: Mat4,
+    pub rot: Mat4,
+}
+unsafe impl bytemuck::Zeroable for CameraData {}
+unsafe impl bytemuck::Pod for CameraData {}
+
+// How far from the eye the fragment shader puts the screen it shoots rays
+// through, for a vertical field of view in radians. The screen is 2 wide.
+pub fn focal_length(fov: f32, aspect_ratio: f32) -> f32 {
+    aspect_ratio / (fov / 2.0).tan()
+}
+
+impl CameraData {
+    pub fn quat(&self) -> Quat {
+        Quat::from_rotation_x(self.pitch) * Quat::from_rotation_y(self.yaw)
+    }
+
+    pub fn neg_quat(&self) -> Quat {
+        Quat::from_rotation_y(-self.yaw) * Quat::from_rotation_x(-self.pitch)
+    }
+
+    pub fn quat_frag(&self) -> Quat {
+        Quat::from_rotation_y(-self.yaw) * Quat::from_rotation_x(-self.pitch)
+    }
+
+    // Mirrors how the fragment shader builds its ray for a pixel, so that
+    // things picked on the CPU line up with what is on screen.
+    pub fn ray_direction(&self, pixel: Vec2, resolution: Vec2) -> Vec3 {
+        let screenpos = (pixel - resolution / 2.0) / (resolution.x / 2.0);
+        self.quat_frag() * screenpos.extend(self.focal_length).normalize()
+    }
+}
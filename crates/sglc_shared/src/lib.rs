@@ -1,37 +1,115 @@
+// Nothing here needs Vulkan, the vertices and world buffers can be handed to
+// vulkano directly with the `vulkano` feature.
+use std::alloc::Layout;
+
+#[cfg(feature = "vulkano")]
 use vulkano::buffer::BufferContents;
+#[cfg(feature = "vulkano")]
 use vulkano::pipeline::graphics::vertex_input::Vertex;
 
-#[derive(BufferContents, Vertex, Default, Copy, Clone, Debug)]
+pub mod palette;
+pub mod sun;
+pub mod render_settings;
+pub mod sky;
+pub mod light;
+pub mod post_settings;
+pub mod camera_data;
+pub mod selection;
+pub mod world_size;
+
+#[cfg_attr(feature = "vulkano", derive(BufferContents, Vertex))]
+#[derive(Default, Copy, Clone, Debug)]
 #[repr(C)]
 pub struct MyVertex {
-    #[format(R32G32B32_SFLOAT)]
+    #[cfg_attr(feature = "vulkano", format(R32G32B32_SFLOAT))]
     pub position: glam::Vec3,
 }
 
+pub use world_size::WorldSize;
+
 pub const CHUNK_SIZE_ONE: usize = 8;
 pub const CHUNK_SIZE: usize = CHUNK_SIZE_ONE.pow(3);
-pub const CHUNK_COUNT_ONE: usize = 128;
-pub const CHUNK_COUNT: usize = CHUNK_COUNT_ONE.pow(3);
-pub const WORLD_SIZE_ONE: usize = CHUNK_SIZE_ONE * CHUNK_COUNT_ONE;
-pub const WORLD_SIZE: usize = CHUNK_SIZE * CHUNK_COUNT;
+// For `pos_to_index` within a chunk.
+pub const CHUNK_EXTENT: [usize; 3] = [CHUNK_SIZE_ONE; 3];
 
+// Which slot of `Voxels` every chunk uses, x first, then y, then z. The size
+// goes in front so the mapping can be passed around on its own.
+#[cfg_attr(feature = "vulkano", derive(BufferContents))]
 #[repr(C)]
-#[derive(Copy, Clone)]
-pub struct ChunkMapping(pub [u32; CHUNK_COUNT]);
-unsafe impl bytemuck::Zeroable for ChunkMapping {}
-unsafe impl bytemuck::Pod for ChunkMapping {}
+pub struct ChunkMapping {
+    pub size: WorldSize,
+    pub chunks: [u32],
+}
 
-#[repr(C)]
-#[derive(Copy, Clone)]
-pub struct Voxels(pub [[u32; CHUNK_SIZE]; CHUNK_COUNT]);
-unsafe impl bytemuck::Zeroable for Voxels {}
-unsafe impl bytemuck::Pod for Voxels {}
+impl ChunkMapping {
+    // Every chunk starts out as air.
+    pub fn new(size: WorldSize) -> Box<Self> {
+        let len = size.chunk_count();
+        let mut mapping = zeroed_box(mapping_layout(len), |pointer| {
+            std::ptr::slice_from_raw_parts_mut(pointer as *mut u32, len) as *mut Self
+        });
+        mapping.size = size;
+        mapping
+    }
+}
 
-pub const VERTEX_COUNT: usize = CHUNK_COUNT * 72 / 8;
+// `new` makes a slot for every chunk. The renderer's buffer can have fewer
+// when the device can't bind that much, then allocating fails once they're
+// all in use.
+#[cfg_attr(feature = "vulkano", derive(BufferContents))]
 #[repr(C)]
-#[derive(Copy, Clone)]
-pub struct Vertices(pub [MyVertex; VERTEX_COUNT]);
-unsafe impl bytemuck::Zeroable for Vertices {}
-unsafe impl bytemuck::Pod for Vertices {}
+pub struct Voxels(pub [[u32; CHUNK_SIZE]]);
 
+impl Voxels {
+    pub fn new(size: WorldSize) -> Box<Self> {
+        let len = size.chunk_count();
+        zeroed_box(Layout::array::<[u32; CHUNK_SIZE]>(len).unwrap(), |pointer| {
+            std::ptr::slice_from_raw_parts_mut(pointer as *mut [u32; CHUNK_SIZE], len) as *mut Self
+        })
+    }
+}
+
+// A `WorldSize` followed by `len` u32s, like the mappings.
+pub(crate) fn mapping_layout(len: usize) -> Layout {
+    let (layout, _) = Layout::new::<WorldSize>().extend(Layout::array::<u32>(len).unwrap()).unwrap();
+    layout.pad_to_align()
+}
 
+// Buffers like `Voxels` are too big to go through the stack, or to be zeroed
+// one element at a time like `vec!` does. `cast` gives the allocation its
+// length, all zeroes have to be a valid `T`.
+pub(crate) fn zeroed_box<T: ?Sized>(layout: Layout, cast: impl FnOnce(*mut u8) -> *mut T) -> Box<T> {
+    unsafe {
+        let pointer = std::alloc::alloc_zeroed(layout);
+        if pointer.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        Box::from_raw(cast(pointer))
+    }
+}
+
+// Every chunk that isn't air takes 36, so there's room for a quarter of them.
+// Past that `set_vertex_buffer` leaves chunks out.
+pub fn vertex_count(size: WorldSize) -> usize {
+    size.chunk_count() * 72 / 8
+}
+
+
+// Vertices of ordinary triangle meshes drawn on top of the voxels, already in
+// world space.
+#[cfg_attr(feature = "vulkano", derive(BufferContents, Vertex))]
+#[derive(Default, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct MeshVertex {
+    #[cfg_attr(feature = "vulkano", format(R32G32B32_SFLOAT))]
+    pub position: glam::Vec3,
+    #[cfg_attr(feature = "vulkano", format(R32G32B32_SFLOAT))]
+    pub color: glam::Vec3,
+}
+
+pub const MESH_VERTEX_COUNT: usize = 1 << 14;
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct MeshVertices(pub [MeshVertex; MESH_VERTEX_COUNT]);
+unsafe impl bytemuck::Zeroable for MeshVertices {}
+unsafe impl bytemuck::Pod for MeshVertices {}
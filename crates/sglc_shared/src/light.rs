@@ -0,0 +1,44 @@
+#[cfg(feature = "vulkano")]
+use vulkano::buffer::BufferContents;
+
+use std::alloc::Layout;
+
+use crate::{mapping_layout, zeroed_box, WorldSize, CHUNK_SIZE};
+
+// Light levels of the voxels, laid out like `ChunkMapping` and `Voxels`: every
+// chunk that has any light gets a slot in `LightChunks`, the rest map to slot
+// 0, which stays dark. Levels are a byte each, four to a word, so the shaders
+// can read them from a plain uint array.
+
+pub const LIGHT_CHUNK_CAPACITY: usize = 1 << 16;
+pub const LIGHT_WORDS: usize = CHUNK_SIZE / 4;
+
+#[cfg_attr(feature = "vulkano", derive(BufferContents))]
+#[repr(C)]
+pub struct LightMapping {
+    pub size: WorldSize,
+    pub chunks: [u32],
+}
+
+impl LightMapping {
+    pub fn new(size: WorldSize) -> Box<Self> {
+        let len = size.chunk_count();
+        let mut mapping = zeroed_box(mapping_layout(len), |pointer| {
+            std::ptr::slice_from_raw_parts_mut(pointer as *mut u32, len) as *mut Self
+        });
+        mapping.size = size;
+        mapping
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LightChunks(pub [[u32; LIGHT_WORDS]; LIGHT_CHUNK_CAPACITY]);
+unsafe impl bytemuck::Zeroable for LightChunks {}
+unsafe impl bytemuck::Pod for LightChunks {}
+
+impl LightChunks {
+    pub fn new() -> Box<Self> {
+        zeroed_box(Layout::new::<Self>(), |pointer| pointer as *mut Self)
+    }
+}
@@ -0,0 +1,14 @@
+// Mirrors `Palette` in palette.rs.
+
+struct Material {
+    vec4 color;
+    float emissive;
+    float roughness;
+    float metalness;
+    float transparency;
+    float ior;
+};
+
+layout(set = 1, binding = 0) uniform Palette {
+    Material materials[256];
+} palette;
@@ -0,0 +1,54 @@
+// Mirrors `palette.glsl` next to this file, which the shaders include, so the
+// std140 layout only has to be kept right here.
+
+pub const PALETTE_SIZE: usize = 256;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    pub color: [f32; 4],
+    pub emissive: f32,
+    pub roughness: f32,
+    pub metalness: f32,
+    pub transparency: f32,
+    // Index of refraction, for light going through transparent materials.
+    pub ior: f32,
+    pub _padding: [f32; 3],
+}
+unsafe impl bytemuck::Zeroable for Material {}
+unsafe impl bytemuck::Pod for Material {}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            color: [1.0; 4],
+            emissive: 0.0,
+            roughness: 1.0,
+            metalness: 0.0,
+            transparency: 0.0,
+            ior: 1.5,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+impl Material {
+    pub fn from_rgba8(rgba: [u8; 4]) -> Self {
+        Self {
+            color: rgba.map(|c| c as f32 / 255.0),
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Palette(pub [Material; PALETTE_SIZE]);
+unsafe impl bytemuck::Zeroable for Palette {}
+unsafe impl bytemuck::Pod for Palette {}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self([Material::default(); PALETTE_SIZE])
+    }
+}
@@ -0,0 +1,11 @@
+// Mirrors `PostSettings` in post_settings.rs.
+
+layout(set = 0, binding = 1) uniform PostSettings {
+    float exposure;
+    uint tone_mapping;
+    float bloom_strength;
+    float bloom_threshold;
+    float vignette;
+    uint dither;
+    uint srgb_swapchain;
+} post;
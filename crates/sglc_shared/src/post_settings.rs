@@ -0,0 +1,43 @@
+// Mirrors `post_settings.glsl` next to this file, which the post processing
+// shader includes.
+
+pub const TONE_MAPPINGS: [&str; 3] = ["clamp", "reinhard", "aces"];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostSettings {
+    // In stops, 0 leaves the rendered colors as they are.
+    pub exposure: f32,
+    // Index into `TONE_MAPPINGS`.
+    pub tone_mapping: u32,
+    // 0 turns bloom off.
+    pub bloom_strength: f32,
+    // How bright a color has to be before it starts to bloom.
+    pub bloom_threshold: f32,
+    // How much darker the corners get, 0 turns the vignette off.
+    pub vignette: f32,
+    // 1 snaps every pixel to the nearest palette color, with ordered
+    // dithering in between.
+    pub dither: u32,
+    // 1 when the swapchain does the sRGB encoding itself, so the shader has to
+    // write linear colors.
+    pub srgb_swapchain: u32,
+    pub _padding: u32,
+}
+unsafe impl bytemuck::Zeroable for PostSettings {}
+unsafe impl bytemuck::Pod for PostSettings {}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tone_mapping: 2,
+            bloom_strength: 0.3,
+            bloom_threshold: 1.0,
+            vignette: 0.25,
+            dither: 0,
+            srgb_swapchain: 1,
+            _padding: 0,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// Mirrors `RenderSettings` in render_settings.rs.
+
+layout(set = 1, binding = 4) uniform RenderSettings {
+    uint ao_quality;
+    float ao_strength;
+    uint beauty;
+    uint sample_count;
+    uint max_bounces;
+    uint max_secondary_rays;
+    float block_light;
+    uint debug_mode;
+} settings;
@@ -0,0 +1,47 @@
+// Mirrors `render_settings.glsl` next to this file, which the shaders include.
+
+pub const MAX_AO_QUALITY: u32 = 2;
+
+// What `debug_mode` shows instead of the shaded scene, 0 is the scene.
+pub const DEBUG_MODES: [&str; 7] = ["off", "normals", "steps", "chunk grid", "chunk slots", "air skipping", "depth"];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderSettings {
+    // 0 turns ambient occlusion off, 1 samples the 4 voxels next to a face,
+    // 2 also samples the 4 diagonal ones.
+    pub ao_quality: u32,
+    // 0 leaves fully occluded corners as they are, 1 makes them black.
+    pub ao_strength: f32,
+    // 1 path traces instead of shading with just the sun, adding one sample
+    // per pixel each frame.
+    pub beauty: u32,
+    // Samples accumulated so far, 0 starts over.
+    pub sample_count: u32,
+    pub max_bounces: u32,
+    // How many times a ray can get reflected or refracted before the surface
+    // it hits is just shaded.
+    pub max_secondary_rays: u32,
+    // Brightness of the light flooding out of emissive voxels at its
+    // strongest, 0 turns it off.
+    pub block_light: f32,
+    // Index into `DEBUG_MODES`.
+    pub debug_mode: u32,
+}
+unsafe impl bytemuck::Zeroable for RenderSettings {}
+unsafe impl bytemuck::Pod for RenderSettings {}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            ao_quality: MAX_AO_QUALITY,
+            ao_strength: 0.6,
+            beauty: 0,
+            sample_count: 0,
+            max_bounces: 3,
+            max_secondary_rays: 4,
+            block_light: 1.5,
+            debug_mode: 0,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use glam::IVec3;
+
+// The hovered voxel and the selected region, outlined by the fragment shader.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SelectionData {
+    pub voxel: IVec3,
+    pub active: u32,
+    pub region_min: IVec3,
+    pub region_active: u32,
+    pub region_max: IVec3,
+    pub _padding: u32,
+}
+unsafe impl bytemuck::Zeroable for SelectionData {}
+unsafe impl bytemuck::Pod for SelectionData {}
@@ -0,0 +1,11 @@
+// Mirrors `Sky` in sky.rs.
+
+layout(set = 1, binding = 6) uniform Sky {
+    vec3 zenith_color;
+    float sun_disk_size;
+    vec3 horizon_color;
+    float fog_density;
+    vec3 ground_color;
+    float fog_start;
+    vec3 fog_color;
+} sky;
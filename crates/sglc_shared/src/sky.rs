@@ -0,0 +1,72 @@
+use glam::Vec3;
+
+use crate::sun::{Sun, DEFAULT_AZIMUTH};
+
+// Mirrors `sky.glsl` next to this file, which the shaders include.
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sky {
+    pub zenith_color: Vec3,
+    // Angular radius of the sun disk, in radians.
+    pub sun_disk_size: f32,
+    pub horizon_color: Vec3,
+    // 0 turns fog off.
+    pub fog_density: f32,
+    pub ground_color: Vec3,
+    // Distance in voxels before fog starts to show.
+    pub fog_start: f32,
+    pub fog_color: Vec3,
+    pub _padding: f32,
+}
+unsafe impl bytemuck::Zeroable for Sky {}
+unsafe impl bytemuck::Pod for Sky {}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            zenith_color: Vec3::new(0.25, 0.45, 0.85),
+            sun_disk_size: 0.03,
+            horizon_color: Vec3::new(0.7, 0.8, 0.9),
+            fog_density: 0.002,
+            ground_color: Vec3::new(0.3, 0.28, 0.25),
+            fog_start: 64.0,
+            fog_color: Vec3::new(0.7, 0.8, 0.9),
+            _padding: 0.0,
+        }
+    }
+}
+
+const NIGHT_ZENITH: Vec3 = Vec3::new(0.01, 0.01, 0.03);
+const SUNSET_HORIZON: Vec3 = Vec3::new(0.9, 0.5, 0.3);
+const SUNSET_SUN: Vec3 = Vec3::new(1.0, 0.6, 0.35);
+
+impl Sky {
+    // The sky as it looks at `hours` past midnight, moving the sun along with
+    // it. The sun rises at 6 and sets at 18, and the colors of `self` are the
+    // ones at noon.
+    pub fn at_time_of_day(&self, hours: f32, sun: &mut Sun) -> Sky {
+        let angle = (hours - 6.0) / 12.0 * std::f32::consts::PI;
+        let height = angle.sin();
+
+        let elevation = (std::f32::consts::FRAC_PI_2 - 0.3) * height;
+        sun.direction = Sun::direction_from_angles(DEFAULT_AZIMUTH + angle, elevation);
+
+        let t = ((height + 0.1) / 0.4).clamp(0.0, 1.0);
+        let daylight = t * t * (3.0 - 2.0 * t);
+        let warmth = (1.0 - height * 3.0).clamp(0.0, 1.0) * daylight;
+
+        sun.intensity = daylight;
+        sun.color = Vec3::ONE.lerp(SUNSET_SUN, warmth);
+
+        let horizon_color = self.horizon_color.lerp(SUNSET_HORIZON, warmth) * daylight.max(0.05);
+
+        Sky {
+            zenith_color: NIGHT_ZENITH.lerp(self.zenith_color, daylight),
+            horizon_color,
+            ground_color: self.ground_color * daylight.max(0.05),
+            fog_color: horizon_color,
+            ..*self
+        }
+    }
+}
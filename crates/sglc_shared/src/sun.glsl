@@ -0,0 +1,10 @@
+// Mirrors `Sun` in sun.rs.
+
+layout(set = 1, binding = 3) uniform Sun {
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    uint shadow_samples;
+    float softness;
+    float ambient;
+} sun;
@@ -0,0 +1,50 @@
+use glam::Vec3;
+
+// Mirrors `sun.glsl` next to this file, which the shaders include.
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sun {
+    // Points from surfaces towards the sun. -y is up.
+    pub direction: Vec3,
+    pub intensity: f32,
+    pub color: Vec3,
+    // 1 casts a single hard shadow ray, more jitter the rays for soft shadows.
+    pub shadow_samples: u32,
+    // How far shadow rays get jittered, as the tangent of the cone's angle.
+    pub softness: f32,
+    // Light that reaches surfaces the sun doesn't.
+    pub ambient: f32,
+    pub _padding: [u32; 2],
+}
+unsafe impl bytemuck::Zeroable for Sun {}
+unsafe impl bytemuck::Pod for Sun {}
+
+pub const DEFAULT_AZIMUTH: f32 = -0.75 * std::f32::consts::PI;
+pub const DEFAULT_ELEVATION: f32 = 0.615;
+
+impl Default for Sun {
+    fn default() -> Self {
+        Self {
+            direction: Sun::direction_from_angles(DEFAULT_AZIMUTH, DEFAULT_ELEVATION),
+            intensity: 1.0,
+            color: Vec3::ONE,
+            shadow_samples: 1,
+            softness: 0.05,
+            ambient: 0.3,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl Sun {
+    // Azimuth goes around the y axis starting at +x, elevation is the angle
+    // above the horizon.
+    pub fn direction_from_angles(azimuth: f32, elevation: f32) -> Vec3 {
+        Vec3::new(
+            elevation.cos() * azimuth.cos(),
+            -elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        )
+    }
+}
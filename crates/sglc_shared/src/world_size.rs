@@ -0,0 +1,67 @@
+use glam::{IVec3, UVec3};
+
+use crate::{CHUNK_SIZE, CHUNK_SIZE_ONE};
+
+// How many chunks the world has along each axis. It's picked when a world is
+// created and can't change while the renderer is running, the shaders get it
+// as specialization constants.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WorldSize {
+    pub chunks: UVec3,
+    pub _padding: u32,
+}
+unsafe impl bytemuck::Zeroable for WorldSize {}
+unsafe impl bytemuck::Pod for WorldSize {}
+
+impl Default for WorldSize {
+    fn default() -> Self {
+        Self { chunks: UVec3::splat(128), _padding: 0 }
+    }
+}
+
+impl WorldSize {
+    // None if any axis is empty or the voxels can't all be indexed with a u32,
+    // which is what the shaders use.
+    pub fn new(chunks: UVec3) -> Option<Self> {
+        let voxels = chunks.to_array().iter().map(|&c| c as u128).product::<u128>() * CHUNK_SIZE as u128;
+        if chunks.min_element() == 0 || voxels > u32::MAX as u128 {
+            return None;
+        }
+
+        Some(Self { chunks, _padding: 0 })
+    }
+
+    // The size in voxels, which has to be a multiple of the chunk size.
+    pub fn from_voxels(voxels: UVec3) -> Option<Self> {
+        if voxels % CHUNK_SIZE_ONE as u32 != UVec3::ZERO {
+            return None;
+        }
+
+        Self::new(voxels / CHUNK_SIZE_ONE as u32)
+    }
+
+    // Chunks along x, y and z, for `pos_to_index` and `index_to_pos`.
+    pub fn extent(&self) -> [usize; 3] {
+        self.chunks.to_array().map(|c| c as usize)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.extent().iter().product()
+    }
+
+    pub fn voxels(&self) -> IVec3 {
+        self.chunks.as_ivec3() * CHUNK_SIZE_ONE as i32
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(IVec3::ZERO).all() && pos.cmplt(self.voxels()).all()
+    }
+}
+
+impl std::fmt::Display for WorldSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let voxels = self.voxels();
+        write!(f, "{}x{}x{}", voxels.x, voxels.y, voxels.z)
+    }
+}
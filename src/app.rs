@@ -0,0 +1,266 @@
+use glam::{Mat4, Vec2, Vec3};
+use winit::event::ModifiersState;
+
+use sglc_hotcode::console::Console;
+use sglc_hotcode::light::{relight, update_light, LightAllocator};
+use sglc_hotcode::mesh::{axes_gizmo, set_mesh_buffer};
+use sglc_hotcode::overlay::Canvas;
+use sglc_renderer::error::RendererError;
+use sglc_renderer::renderer::Renderer;
+use sglc_shared::camera_data::{focal_length, CameraData};
+use sglc_shared::sun;
+
+use crate::cli::Options;
+use crate::editor::Editor;
+use crate::profiler::{self, millis, FrameStats, Profiler};
+use crate::{all_worlds, World};
+
+// Console commands run at startup, one per line, # starts a comment.
+const STARTUP_SCRIPT: &str = "startup.console";
+
+// Everything the window keeps track of between frames, apart from the
+// renderer, which gets replaced when it's lost.
+pub struct App {
+    pub camera_data: CameraData,
+    pub worlds: Vec<Box<dyn World>>,
+    pub world_index: usize,
+    pub time_of_day: Option<f32>,
+    pub editor: Editor,
+    pub light_allocator: LightAllocator,
+    // Set when the world or the palette got replaced, rather than edited.
+    pub relight_needed: bool,
+    pub show_gizmo: bool,
+    pub cursor_position: Vec2,
+    pub modifiers: ModifiersState,
+    pub material_property: usize,
+    pub sun_azimuth: f32,
+    pub sun_elevation: f32,
+    pub last_camera: (Vec3, f32, f32),
+    pub reset_accumulation: bool,
+    pub profiler: Profiler,
+    pub show_hud: bool,
+    pub show_profile: bool,
+    pub target_position: Vec3,
+    pub motion_speed: f32,
+    pub fov: f32,
+    pub console: Console,
+    // Typed or read from the startup script, run before the next frame.
+    pub pending_commands: Vec<String>,
+    // Given with --samples, for reference renders.
+    pub samples: Option<u32>,
+}
+
+impl App {
+    pub fn new(options: &Options) -> Self {
+        let [width, height] = options.resolution;
+        let (position, yaw, pitch) = options.camera.unwrap_or((Vec3::new(0.0, 0.0, -1.0), 0.0, 0.0));
+        let camera_data = CameraData {
+            aspect_ratio: height as f32 / width as f32, 
+            proj: Mat4::perspective_rh(0.7, std::f32::consts::FRAC_PI_2, 0.1, 1.0),
+            position,
+            yaw,
+            pitch,
+            ..Default::default()
+        };
+
+        // Options given on the command line go first, as commands as well.
+        let mut pending_commands = [
+            options.world.as_ref().map(|world| format!("world {world}")),
+            options.seed.map(|seed| format!("seed {seed}")),
+            options.load.as_ref().map(|path| format!("load {}", path.display())),
+        ].into_iter().flatten().collect::<Vec<_>>();
+
+        if let Ok(script) = std::fs::read_to_string(STARTUP_SCRIPT) {
+            pending_commands.extend(script
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(String::from));
+        }
+
+        Self {
+            camera_data,
+            worlds: all_worlds(),
+            world_index: 0,
+            time_of_day: None,
+            editor: Editor::default(),
+            light_allocator: LightAllocator::default(),
+            relight_needed: true,
+            show_gizmo: false,
+            cursor_position: Vec2::ZERO,
+            modifiers: ModifiersState::empty(),
+            material_property: 0,
+            sun_azimuth: sun::DEFAULT_AZIMUTH,
+            sun_elevation: sun::DEFAULT_ELEVATION,
+            last_camera: (Vec3::ZERO, 0.0, 0.0),
+            reset_accumulation: true,
+            profiler: Profiler::default(),
+            show_hud: true,
+            show_profile: false,
+            target_position: position,
+            motion_speed: 1.0,
+            fov: 0.7,
+            console: Console::default(),
+            pending_commands,
+            samples: options.samples,
+        }
+    }
+
+    // Runs pending commands, catches the camera, the world, the light and the
+    // overlay up and draws a frame.
+    pub fn frame(&mut self, renderer: &mut Renderer) -> Result<(), RendererError> {
+        let [width, height] = renderer.resolution;
+        let resolution = Vec2::new(width as f32, height as f32);
+        let execution_time = std::time::Instant::now();
+        let mut stats = FrameStats::default();
+
+        self.run_pending_commands(renderer);
+
+        let vertex_time = std::time::Instant::now();
+        renderer.upload_chunks();
+        stats.timings[profiler::SET_VERTEX_BUFFER] = millis(vertex_time.elapsed());
+
+        {
+            let camera_position = self.camera_data.position;
+            let camera_rotation = self.camera_data.quat();
+
+            let camera_matrix = Mat4::from_quat(camera_rotation) * Mat4::from_translation(-camera_position);
+            self.camera_data.camera = camera_matrix;
+            self.camera_data.proj = Mat4::perspective_lh(self.fov, resolution.x / resolution.y, 1.0, 10000.0);
+            self.camera_data.focal_length = focal_length(self.fov, self.camera_data.aspect_ratio);
+            self.camera_data.rot = Mat4::from_quat(self.camera_data.quat_frag());
+
+            const MOTION_SPEED: f32 = 0.3;
+            self.camera_data.position = 
+                self.camera_data.position * (1. - MOTION_SPEED) + 
+                self.target_position * MOTION_SPEED;
+
+            let camera = (self.camera_data.position, self.camera_data.yaw, self.camera_data.pitch);
+            if camera.0.distance(self.last_camera.0) > 0.001 || camera.1 != self.last_camera.1 || camera.2 != self.last_camera.2 {
+                self.reset_accumulation = true;
+            }
+            self.last_camera = camera;
+
+            let upload_time = std::time::Instant::now();
+            renderer.set_camera(&self.camera_data);
+            stats.timings[profiler::UPLOAD] += millis(upload_time.elapsed());
+        }
+
+        {
+            let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+            let voxels = &mut *renderer.voxels.write().unwrap();
+
+            let fill_in_time = std::time::Instant::now();
+            if self.worlds[self.world_index].fill_in_voxels(chunk_mapping, voxels) {
+                self.editor.world_changed(chunk_mapping);
+                self.reset_accumulation = true;
+                self.relight_needed = true;
+            }
+            stats.timings[profiler::FILL_IN_VOXELS] = millis(fill_in_time.elapsed());
+
+            let palette = &*renderer.palette.read().unwrap();
+            let light_mapping = &mut *renderer.light_mapping.write().unwrap();
+            let light_chunks = &mut *renderer.light_chunks.write().unwrap();
+            let changes = self.editor.take_changes();
+            if self.relight_needed || !changes.is_empty() {
+                self.reset_accumulation = true;
+            }
+
+            let light_time = std::time::Instant::now();
+            if self.relight_needed {
+                relight(palette, chunk_mapping, voxels, &mut self.light_allocator, light_mapping, light_chunks);
+                self.relight_needed = false;
+            } else if !changes.is_empty() {
+                update_light(&changes, palette, chunk_mapping, voxels, &mut self.light_allocator, light_mapping, light_chunks);
+            }
+            stats.timings[profiler::LIGHT] = millis(light_time.elapsed());
+
+            self.editor.hover(
+                self.camera_data.position,
+                self.camera_data.ray_direction(self.cursor_position + 0.5, resolution),
+                chunk_mapping,
+                voxels,
+            );
+
+            // The gizmo sits in the middle of the hovered voxel, so
+            // the voxels around it hide part of it.
+            let gizmo = match self.editor.hovered {
+                Some(hit) if self.show_gizmo => axes_gizmo(hit.voxel.as_vec3() + 0.5, 3.0),
+                _ => Vec::new(),
+            };
+
+            let upload_time = std::time::Instant::now();
+            renderer.selection.write().unwrap()[0] = self.editor.selection();
+            set_mesh_buffer(&gizmo, &mut renderer.mesh_vertices.write().unwrap());
+            stats.timings[profiler::UPLOAD] += millis(upload_time.elapsed());
+        }
+
+        {
+            let upload_time = std::time::Instant::now();
+            let settings = &mut *renderer.render_settings.write().unwrap();
+            settings.sample_count = if self.reset_accumulation { 0 } else { settings.sample_count + 1 };
+            self.reset_accumulation = false;
+
+            let canvas = &mut Canvas { pixels: &mut renderer.overlay.write().unwrap(), width: width as usize };
+            canvas.clear();
+
+            if self.show_hud {
+                let frame = self.profiler.average.timings[profiler::FRAME];
+                let mut lines = hud_lines(
+                    &*self.worlds[self.world_index],
+                    self.camera_data.position,
+                    &self.editor,
+                    Some(frame),
+                );
+                if settings.beauty == 1 {
+                    lines.push(format!("{} SAMPLES", settings.sample_count + 1));
+                }
+
+                let bottom = canvas.draw_panel(1, 1, &lines);
+                if self.show_profile {
+                    canvas.draw_panel(1, bottom + 1, &self.profiler.hud_lines());
+                }
+            }
+
+            if self.console.open {
+                self.console.draw(canvas);
+            }
+            stats.timings[profiler::UPLOAD] += millis(upload_time.elapsed());
+        }
+
+        // None if the swapchain was out of date and the frame got skipped.
+        if let Some(times) = renderer.render_frame()? {
+            stats.timings[profiler::WAIT] = millis(times.wait);
+            if let Some((scene, post)) = times.gpu {
+                stats.timings[profiler::GPU_SCENE] = scene;
+                stats.timings[profiler::GPU_POST] = post;
+            }
+
+            stats.timings[profiler::FRAME] = millis(execution_time.elapsed());
+            stats.allocated_chunks = self.editor.allocator.allocated_count();
+            stats.vertex_count = renderer.vertex_count;
+            stats.light_chunks = self.light_allocator.chunk_count();
+            self.profiler.record(stats);
+        }
+
+        Ok(())
+    }
+}
+
+// The panel in the top left, also drawn over screenshots, which have no frame
+// time to show.
+pub fn hud_lines(world: &dyn World, position: Vec3, editor: &Editor, frame_millis: Option<f32>) -> Vec<String> {
+    let mut lines = vec![
+        match world.seed() {
+            Some(seed) => format!("{} SEED {seed}", world.name()),
+            None => world.name().to_string(),
+        },
+        format!("{:.0} {:.0} {:.0}", position.x, position.y, position.z),
+        format!("{:?} {:?} UNIT {} R {}", editor.tool, editor.mode, editor.unit, editor.radius),
+    ];
+    if let Some(frame) = frame_millis {
+        lines.push(format!("{:.0} FPS", if frame > 0.0 { 1000.0 / frame } else { 0.0 }));
+    }
+
+    lines
+}
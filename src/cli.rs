@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+
+use glam::{UVec3, Vec3};
+use sglc_shared::WorldSize;
+
+pub const USAGE: &str = "\
+usage: supergoodlookingcubes [options]
+
+  --world <name>              world to start in, spheres or hills
+  --seed <number>             seed of the starting world
+  --world-size <x>x<y>x<z>    size of the world in voxels, multiples of 8,
+                              1024x1024x1024 or that of --load by default
+  --resolution <width>x<height>
+                              render resolution, 320x180 by default
+  --scale <number>            window size as a multiple of the resolution
+  --device <index or name>    physical device to use, see --list-devices
+  --list-devices              lists physical devices and what keeps
+                              any from being used, then exits
+  --load <path>               .vox model or saved world to start with
+  --camera <x,y,z[,yaw,pitch]>
+                              where the camera starts
+  --screenshot <path>         renders one image without a window and exits
+  --samples <number>          path traces --screenshot with this many
+                              samples per pixel, like F2 does
+  --hud                       draws the HUD over --screenshot
+  --gpu                       renders --screenshot on the GPU, the way the
+                              window does, instead of on the CPU
+  --debug                     turns on validation and names Vulkan objects
+  --help                      shows this";
+
+pub struct Options {
+    pub world: Option<String>,
+    pub seed: Option<u64>,
+    pub world_size: Option<WorldSize>,
+    pub resolution: [u32; 2],
+    pub scale: u32,
+    pub device: Option<String>,
+    pub list_devices: bool,
+    pub load: Option<PathBuf>,
+    // Position, yaw and pitch.
+    pub camera: Option<(Vec3, f32, f32)>,
+    pub screenshot: Option<PathBuf>,
+    // Screenshots only get path traced when this is given.
+    pub samples: Option<u32>,
+    pub hud: bool,
+    // Screenshots go through the offscreen renderer instead of the CPU one.
+    pub gpu: bool,
+    pub debug: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            world: None,
+            seed: None,
+            world_size: None,
+            resolution: [320, 180],
+            scale: 3,
+            device: None,
+            list_devices: false,
+            load: None,
+            camera: None,
+            screenshot: None,
+            samples: None,
+            hud: false,
+            gpu: false,
+            debug: false,
+            help: false,
+        }
+    }
+}
+
+// Samples per pixel of the P screenshots when there's no --samples.
+pub const DEFAULT_SAMPLES: u32 = 64;
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{flag} expects a number, got {value}"))
+}
+
+// Expects the arguments without the program name.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            options.help = true;
+            continue;
+        }
+        if flag == "--list-devices" {
+            options.list_devices = true;
+            continue;
+        }
+        if flag == "--hud" {
+            options.hud = true;
+            continue;
+        }
+        if flag == "--gpu" {
+            options.gpu = true;
+            continue;
+        }
+        if flag == "--debug" {
+            options.debug = true;
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("{flag} expects a value"))?;
+        match flag.as_str() {
+            "--world" => options.world = Some(value),
+            "--seed" => options.seed = Some(parse_number(&flag, &value)?),
+            "--world-size" => {
+                let numbers = value
+                    .split('x')
+                    .map(|n| parse_number::<u32>(&flag, n))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let [x, y, z] = numbers[..] else {
+                    return Err(format!("--world-size expects <x>x<y>x<z>, got {value}"));
+                };
+                options.world_size = Some(WorldSize::from_voxels(UVec3::new(x, y, z)).ok_or_else(|| {
+                    format!("--world-size has to be nonzero multiples of 8, fewer than 2^32 voxels in all, got {value}")
+                })?);
+            },
+            "--resolution" => {
+                let (width, height) = value
+                    .split_once('x')
+                    .ok_or_else(|| format!("--resolution expects <width>x<height>, got {value}"))?;
+                options.resolution = [parse_number(&flag, width)?, parse_number(&flag, height)?];
+                if options.resolution.contains(&0) {
+                    return Err("--resolution can't be zero".to_string());
+                }
+            },
+            "--scale" => options.scale = parse_number::<u32>(&flag, &value)?.max(1),
+            "--device" => options.device = Some(value),
+            "--load" => options.load = Some(value.into()),
+            "--camera" => {
+                let numbers = value
+                    .split(',')
+                    .map(|n| parse_number::<f32>(&flag, n.trim()))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                options.camera = Some(match numbers[..] {
+                    [x, y, z] => (Vec3::new(x, y, z), 0.0, 0.0),
+                    [x, y, z, yaw, pitch] => (Vec3::new(x, y, z), yaw, pitch),
+                    _ => return Err(format!("--camera expects x,y,z or x,y,z,yaw,pitch, got {value}")),
+                });
+            },
+            "--screenshot" => options.screenshot = Some(value.into()),
+            "--samples" => options.samples = Some(parse_number::<u32>(&flag, &value)?.max(1)),
+            _ => return Err(format!("unknown option {flag}, see --help")),
+        }
+    }
+
+    Ok(options)
+}
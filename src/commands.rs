@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use glam::Vec3;
+use sglc_hotcode::prefab::Prefab;
+
+use sglc_renderer::renderer::Renderer;
+
+use crate::app::App;
+use crate::{load_world_from, save_world_to};
+
+// Built in console commands and what they take, worlds add their own.
+const COMMANDS: [(&str, &str); 10] = [
+    ("help", ""),
+    ("world", "<name>"),
+    ("seed", "<number>"),
+    ("tp", "<x> <y> <z>"),
+    ("save", "<path>"),
+    ("load", "<path>"),
+    ("save_clipboard", "<path>"),
+    ("load_clipboard", "<path>"),
+    ("set", "<variable> <value>"),
+    ("regen", ""),
+];
+const VARIABLES: [&str; 4] = ["fov", "exposure", "block_light", "ao_strength"];
+
+impl App {
+    // Runs what was typed into the console or came from the startup script
+    // since the last frame, printing what went wrong to the console.
+    pub fn run_pending_commands(&mut self, renderer: &Renderer) {
+        for line in std::mem::take(&mut self.pending_commands) {
+            self.console.print(format!("> {line}"));
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let Some((&name, args)) = words.split_first() else { continue };
+            let world_commands = self.worlds[self.world_index].commands();
+
+            let result = match (name, args) {
+                ("help", []) => {
+                    for (name, usage) in COMMANDS.iter().chain(world_commands) {
+                        self.console.print(format!("{name} {usage}"));
+                    }
+                    Ok(())
+                },
+                ("world", [world_name]) => match self.worlds.iter().position(|world| world.name() == *world_name) {
+                    Some(index) => {
+                        self.world_index = index;
+                        self.worlds[self.world_index].invalidate();
+                        *renderer.sky.write().unwrap() = self.worlds[self.world_index].sky();
+                        self.time_of_day = None;
+                        Ok(())
+                    },
+                    None => Err(format!("no world called {world_name}")),
+                },
+                ("seed", [seed]) => match seed.parse() {
+                    Ok(seed) if self.worlds[self.world_index].seed().is_some() => {
+                        self.worlds[self.world_index].set_seed(seed);
+                        self.worlds[self.world_index].invalidate();
+                        Ok(())
+                    },
+                    Ok(_) => Err(format!("{} has no seed", self.worlds[self.world_index].name())),
+                    Err(e) => Err(format!("{seed}: {e}")),
+                },
+                ("tp", [x, y, z]) => match (x.parse(), y.parse(), z.parse()) {
+                    (Ok(x), Ok(y), Ok(z)) => {
+                        self.target_position = Vec3::new(x, y, z);
+                        self.camera_data.position = self.target_position;
+                        Ok(())
+                    },
+                    _ => Err("tp takes three numbers".to_string()),
+                },
+                ("save", [path]) => match save_world_to(
+                    path,
+                    &renderer.palette.read().unwrap(),
+                    &renderer.chunk_mapping.read().unwrap(),
+                    &renderer.voxels.read().unwrap(),
+                ) {
+                    Ok(()) => {
+                        self.console.print(format!("saved world to {path}"));
+                        Ok(())
+                    },
+                    Err(e) => Err(format!("failed to save world to {path}: {e}")),
+                },
+                ("load", [path]) => {
+                    let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                    let voxels = &mut *renderer.voxels.write().unwrap();
+
+                    // Generates the world now if it was about to, so
+                    // it doesn't replace what gets loaded.
+                    let generated = self.worlds[self.world_index].fill_in_voxels(chunk_mapping, voxels);
+                    let result = load_world_from(Path::new(path), chunk_mapping, voxels);
+
+                    // A failed load leaves the world as it was, which
+                    // only needs catching up on if it was generated.
+                    if result.is_ok() || generated {
+                        self.editor.world_changed(chunk_mapping);
+                        self.relight_needed = true;
+                    }
+
+                    match result {
+                        Ok(loaded) => {
+                            *renderer.palette.write().unwrap() = loaded;
+                            Ok(())
+                        },
+                        Err(e) => Err(format!("failed to load {path}: {e}")),
+                    }
+                },
+                ("save_clipboard", [path]) => match &self.editor.clipboard {
+                    Some(clipboard) => match File::create(path).and_then(|file| clipboard.write_to(&mut BufWriter::new(file))) {
+                        Ok(()) => {
+                            self.console.print(format!("saved clipboard to {path}"));
+                            Ok(())
+                        },
+                        Err(e) => Err(format!("failed to save clipboard to {path}: {e}")),
+                    },
+                    None => Err("nothing has been copied".to_string()),
+                },
+                ("load_clipboard", [path]) => match File::open(path).and_then(|file| Prefab::read_from(&mut BufReader::new(file))) {
+                    Ok(prefab) => {
+                        self.editor.clipboard = Some(prefab);
+                        Ok(())
+                    },
+                    Err(e) => Err(format!("failed to load {path}: {e}")),
+                },
+                ("set", [variable, value]) => match value.parse::<f32>() {
+                    Ok(value) => match *variable {
+                        "fov" => {
+                            self.fov = value.clamp(0.1, 3.0);
+                            Ok(())
+                        },
+                        "exposure" => {
+                            renderer.post_settings.write().unwrap().exposure = value;
+                            Ok(())
+                        },
+                        "block_light" => {
+                            renderer.render_settings.write().unwrap().block_light = value;
+                            Ok(())
+                        },
+                        "ao_strength" => {
+                            renderer.render_settings.write().unwrap().ao_strength = value;
+                            Ok(())
+                        },
+                        _ => Err(format!("no variable called {variable}, there's {}", VARIABLES.join(" "))),
+                    },
+                    Err(e) => Err(format!("{value}: {e}")),
+                },
+                ("regen", []) => {
+                    self.worlds[self.world_index].invalidate();
+                    Ok(())
+                },
+                _ if world_commands.iter().any(|(command, _)| *command == name) => {
+                    self.worlds[self.world_index].run_command(name, args)
+                },
+                _ => match COMMANDS.iter().find(|(command, _)| *command == name) {
+                    Some((_, usage)) => Err(format!("usage: {name} {usage}")),
+                    None => Err(format!("unknown command {name}, try help")),
+                },
+            };
+
+            if let Err(e) = result {
+                self.console.print(e);
+            }
+            self.reset_accumulation = true;
+        }
+    }
+
+    // Completes the command name, or the world or variable name after it.
+    pub fn complete_command(&mut self) {
+        let world_commands = self.worlds[self.world_index].commands();
+        let candidates = match (self.console.word_index(), self.console.input.split(' ').next()) {
+            (0, _) => COMMANDS.iter().chain(world_commands).map(|(name, _)| *name).collect(),
+            (1, Some("world")) => self.worlds.iter().map(|world| world.name()).collect(),
+            (1, Some("set")) => VARIABLES.to_vec(),
+            _ => Vec::new(),
+        };
+        self.console.complete(&candidates);
+    }
+}
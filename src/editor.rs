@@ -0,0 +1,224 @@
+use glam::{IVec3, Vec3};
+use sglc_hotcode::brush::{apply_brush, flood_fill, BrushMode, Shape};
+use sglc_hotcode::chunk_allocator::ChunkAllocator;
+use sglc_hotcode::history::{History, Operation};
+use sglc_hotcode::prefab::{PasteMode, Prefab};
+use sglc_hotcode::raycast::{raycast, RayHit};
+
+use sglc_shared::selection::SelectionData;
+
+use crate::{ChunkMapping, Voxels};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Place,
+    Remove,
+    Sphere,
+    Box,
+    Cylinder,
+    Line,
+    Fill,
+    Select,
+}
+
+pub struct Editor {
+    pub tool: Tool,
+    pub mode: BrushMode,
+    pub unit: u32,
+    pub radius: f32,
+    pub line_start: Option<IVec3>,
+    pub region_start: Option<IVec3>,
+    pub region: Option<(IVec3, IVec3)>,
+    pub clipboard: Option<Prefab>,
+    pub hovered: Option<RayHit>,
+    pub allocator: ChunkAllocator,
+    pub history: History,
+    // Voxels edited since the last `take_changes`.
+    changed: Vec<IVec3>,
+}
+
+const HISTORY_BUDGET: usize = 256 * 1024 * 1024;
+const FILL_REACH: i32 = 32;
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            tool: Tool::Place,
+            mode: BrushMode::Add,
+            unit: 3,
+            radius: 4.0,
+            line_start: None,
+            region_start: None,
+            region: None,
+            clipboard: None,
+            hovered: None,
+            allocator: ChunkAllocator::default(),
+            history: History::new(HISTORY_BUDGET),
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl Editor {
+    // Has to be called whenever the world replaces its voxels, since the
+    // allocator's view of which slots are in use is stale after that.
+    pub fn world_changed(&mut self, chunk_mapping: &ChunkMapping) {
+        self.allocator = ChunkAllocator::from_mapping(chunk_mapping);
+        self.history.clear();
+        self.hovered = None;
+        self.region_start = None;
+        self.region = None;
+        self.changed.clear();
+    }
+
+    pub fn take_changes(&mut self) -> Vec<IVec3> {
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn hover(&mut self, ro: Vec3, rd: Vec3, chunk_mapping: &ChunkMapping, voxels: &Voxels) {
+        self.hovered = raycast(ro, rd, chunk_mapping, voxels);
+    }
+
+    pub fn selection(&self) -> SelectionData {
+        let mut selection = SelectionData::default();
+
+        if let Some(hit) = self.hovered {
+            selection.voxel = hit.voxel;
+            selection.active = 1;
+        }
+
+        if let Some((min, max)) = self.region {
+            selection.region_min = min;
+            selection.region_max = max;
+            selection.region_active = 1;
+        }
+
+        selection
+    }
+
+    pub fn next_mode(&mut self) {
+        self.mode = match self.mode {
+            BrushMode::Add => BrushMode::Subtract,
+            BrushMode::Subtract => BrushMode::Paint,
+            BrushMode::Paint => BrushMode::Add,
+        };
+    }
+
+    pub fn apply(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        let Some(hit) = self.hovered else { return };
+
+        let center = hit.voxel.as_vec3() + 0.5;
+        let r = self.radius;
+        let shape = match self.tool {
+            Tool::Place => Shape::Box { min: hit.voxel + hit.normal, max: hit.voxel + hit.normal },
+            Tool::Remove => Shape::Box { min: hit.voxel, max: hit.voxel },
+            Tool::Sphere => Shape::Sphere { center, radius: r },
+            Tool::Box => Shape::Box {
+                min: hit.voxel - r as i32,
+                max: hit.voxel + r as i32,
+            },
+            Tool::Cylinder => Shape::Cylinder {
+                a: center,
+                b: center + hit.normal.as_vec3() * r * 2.0,
+                radius: r,
+            },
+            Tool::Line => {
+                let Some(start) = self.line_start.take() else {
+                    self.line_start = Some(hit.voxel);
+                    return;
+                };
+
+                Shape::line(start.as_vec3() + 0.5, center)
+            },
+            Tool::Fill => Shape::Box { min: hit.voxel, max: hit.voxel },
+            Tool::Select => {
+                match self.region_start.take() {
+                    Some(start) => {
+                        self.region = Some((start.min(hit.voxel), start.max(hit.voxel)));
+                    },
+                    None => {
+                        self.region_start = Some(hit.voxel);
+                        self.region = Some((hit.voxel, hit.voxel));
+                    },
+                }
+                return;
+            },
+        };
+
+        let mode = match self.tool {
+            Tool::Place => BrushMode::Add,
+            Tool::Remove => BrushMode::Subtract,
+            _ => self.mode,
+        };
+
+        let mut operation = Operation::default();
+
+        if self.tool == Tool::Fill {
+            let (start, unit) = match mode {
+                BrushMode::Add => (hit.voxel + hit.normal, self.unit),
+                BrushMode::Subtract => (hit.voxel, 0),
+                BrushMode::Paint => (hit.voxel, self.unit),
+            };
+            let bounds = (start - FILL_REACH, start + FILL_REACH);
+
+            flood_fill(start, unit, bounds, &mut operation, &mut self.allocator, chunk_mapping, voxels);
+        } else {
+            apply_brush(shape, mode, self.unit, &mut operation, &mut self.allocator, chunk_mapping, voxels);
+        }
+
+        operation.finish(chunk_mapping, voxels);
+        self.changed.extend(operation.changed_positions(&chunk_mapping.size));
+
+        self.history.push(operation);
+        self.hovered = None;
+    }
+
+    pub fn copy(&mut self, chunk_mapping: &ChunkMapping, voxels: &Voxels) {
+        let Some((min, max)) = self.region else { return };
+
+        self.clipboard = Some(Prefab::copy(min, max, chunk_mapping, voxels));
+    }
+
+    // Pastes the clipboard on top of the hovered face, centered on it
+    // horizontally.
+    pub fn paste(&mut self, mode: PasteMode, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        let (Some(hit), Some(clipboard)) = (self.hovered, &self.clipboard) else { return };
+
+        let mut at = hit.voxel + hit.normal - clipboard.size / 2;
+        at.y = (hit.voxel + hit.normal).y;
+
+        let mut operation = Operation::default();
+        clipboard.paste(at, mode, &mut operation, &mut self.allocator, chunk_mapping, voxels);
+        operation.finish(chunk_mapping, voxels);
+        self.changed.extend(operation.changed_positions(&chunk_mapping.size));
+
+        self.history.push(operation);
+        self.hovered = None;
+    }
+
+    pub fn rotate_clipboard(&mut self) {
+        if let Some(clipboard) = &mut self.clipboard {
+            *clipboard = clipboard.rotated(1, 1);
+        }
+    }
+
+    pub fn mirror_clipboard(&mut self, axis: usize) {
+        if let Some(clipboard) = &mut self.clipboard {
+            *clipboard = clipboard.mirrored(axis);
+        }
+    }
+
+    pub fn undo(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        if self.history.undo(&mut self.allocator, chunk_mapping, voxels) {
+            self.changed.extend(self.history.last_redo().unwrap().changed_positions(&chunk_mapping.size));
+        }
+        self.hovered = None;
+    }
+
+    pub fn redo(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
+        if self.history.redo(&mut self.allocator, chunk_mapping, voxels) {
+            self.changed.extend(self.history.last_undo().unwrap().changed_positions(&chunk_mapping.size));
+        }
+        self.hovered = None;
+    }
+}
@@ -0,0 +1,381 @@
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+use winit::event::{ElementState, VirtualKeyCode};
+
+use sglc_hotcode::post::post_process;
+use sglc_hotcode::prefab::PasteMode;
+use sglc_hotcode::shade::{render, Scene};
+use sglc_hotcode::world_file::load_world;
+use sglc_renderer::renderer::Renderer;
+use sglc_shared::post_settings::{PostSettings, TONE_MAPPINGS};
+use sglc_shared::render_settings::{DEBUG_MODES, MAX_AO_QUALITY};
+use sglc_shared::sun::Sun;
+
+use crate::app::App;
+use crate::cli::DEFAULT_SAMPLES;
+use crate::editor::Tool;
+use crate::{palette, save_png, save_world_to};
+
+const SAVE_PATH: &str = "world.sgw";
+const REFERENCE_PATH: &str = "reference.png";
+const PALETTE_PATHS: [&str; 3] = ["palette.vox", "palette.png", "palette.hex"];
+const PROFILE_PATH: &str = "profile.csv";
+const MATERIAL_PROPERTIES: [&str; 5] = ["emissive", "roughness", "metalness", "transparency", "ior"];
+
+impl App {
+    pub fn keyboard_input(&mut self, renderer: &Renderer, keycode: VirtualKeyCode, state: ElementState) {
+        use winit::event::VirtualKeyCode::*;
+        use winit::event::ElementState::*;
+
+        if keycode == Grave && state == Pressed {
+            self.console.open = !self.console.open;
+            return;
+        }
+
+        // Keys go to the console while it's open.
+        if self.console.open {
+            match keycode {
+                Return if state == Pressed => {
+                    if let Some(line) = self.console.submit() {
+                        self.pending_commands.push(line);
+                    }
+                },
+                Back if state == Pressed => self.console.backspace(),
+                Up if state == Pressed => self.console.history_up(),
+                Down if state == Pressed => self.console.history_down(),
+                Escape if state == Pressed => self.console.open = false,
+                Tab if state == Pressed => self.complete_command(),
+                _ => (),
+            }
+            return;
+        }
+
+        let camera_quat = self.camera_data.neg_quat();
+
+        // Camera moves and edits restart the accumulation in `frame`, the
+        // keys that change the sun, the sky or how the scene is drawn do it
+        // themselves.
+        match keycode {
+            LShift if state == Pressed => {
+                self.motion_speed = 10.0;
+            },
+            LShift if state == Released => {
+                self.motion_speed = 1.0;
+            },
+            A if state == Pressed => {
+                self.target_position -= camera_quat * Vec3::X * self.motion_speed;
+            },
+            D if state == Pressed => {
+                self.target_position += camera_quat * Vec3::X * self.motion_speed;
+            },
+            Q if state == Pressed => {
+                self.target_position += Vec3::Y * self.motion_speed;
+            },
+            E if state == Pressed => {
+                self.target_position -= Vec3::Y * self.motion_speed;
+            },
+            W if state == Pressed => {
+                self.target_position += camera_quat * Vec3::Z * self.motion_speed;
+            },
+            S if state == Pressed => {
+                self.target_position -= camera_quat * Vec3::Z * self.motion_speed;
+            },
+            R if state == Pressed => {
+                self.camera_data.yaw -= 0.1;
+            },
+            F if state == Pressed => {
+                self.camera_data.yaw += 0.1;
+            },
+            T if state == Pressed => {
+                self.camera_data.pitch -= 0.1;
+            },
+            G if state == Pressed => {
+                self.camera_data.pitch += 0.1;
+            },
+            Z if state == Pressed && self.modifiers.ctrl() => {
+                let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                let voxels = &mut *renderer.voxels.write().unwrap();
+
+                self.editor.undo(chunk_mapping, voxels);
+            },
+            Y if state == Pressed && self.modifiers.ctrl() => {
+                let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                let voxels = &mut *renderer.voxels.write().unwrap();
+
+                self.editor.redo(chunk_mapping, voxels);
+            },
+            Z if state == Pressed => {
+                self.world_index += 1;
+                if self.world_index == self.worlds.len() { self.world_index = 0 }
+                self.worlds[self.world_index].invalidate();
+                *renderer.sky.write().unwrap() = self.worlds[self.world_index].sky();
+                self.time_of_day = None;
+                self.reset_accumulation = true;
+            },
+            Key1 if state == Pressed => {
+                self.editor.tool = Tool::Place;
+            },
+            Key2 if state == Pressed => {
+                self.editor.tool = Tool::Remove;
+            },
+            Key3 if state == Pressed => {
+                self.editor.tool = Tool::Sphere;
+            },
+            Key4 if state == Pressed => {
+                self.editor.tool = Tool::Box;
+            },
+            Key5 if state == Pressed => {
+                self.editor.tool = Tool::Cylinder;
+            },
+            Key6 if state == Pressed => {
+                self.editor.tool = Tool::Line;
+                self.editor.line_start = None;
+            },
+            Key7 if state == Pressed => {
+                self.editor.tool = Tool::Fill;
+            },
+            Key8 if state == Pressed => {
+                self.editor.tool = Tool::Select;
+                self.editor.region_start = None;
+            },
+            C if state == Pressed && self.modifiers.ctrl() => {
+                self.editor.copy(&renderer.chunk_mapping.read().unwrap(), &renderer.voxels.read().unwrap());
+            },
+            V if state == Pressed && self.modifiers.ctrl() => {
+                let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                let voxels = &mut *renderer.voxels.write().unwrap();
+                let mode = if self.modifiers.shift() { PasteMode::Merge } else { PasteMode::Replace };
+
+                self.editor.paste(mode, chunk_mapping, voxels);
+            },
+            O if state == Pressed => {
+                self.editor.rotate_clipboard();
+            },
+            I if state == Pressed => {
+                self.editor.mirror_clipboard(0);
+            },
+            U if state == Pressed => {
+                self.editor.mirror_clipboard(2);
+            },
+            F5 if state == Pressed => {
+                let result = save_world_to(
+                    SAVE_PATH,
+                    &renderer.palette.read().unwrap(),
+                    &renderer.chunk_mapping.read().unwrap(),
+                    &renderer.voxels.read().unwrap(),
+                );
+
+                match result {
+                    Ok(()) => println!("saved world to {SAVE_PATH}"),
+                    Err(e) => println!("failed to save world to {SAVE_PATH}: {e}"),
+                }
+            },
+            F9 if state == Pressed => {
+                let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                let voxels = &mut *renderer.voxels.write().unwrap();
+
+                // Same as the load command, the world is generated first so
+                // it doesn't replace what gets loaded next frame.
+                let generated = self.worlds[self.world_index].fill_in_voxels(chunk_mapping, voxels);
+                let result = std::fs::File::open(SAVE_PATH).and_then(|file| load_world(
+                    &mut std::io::BufReader::new(file),
+                    chunk_mapping,
+                    voxels,
+                ));
+
+                if result.is_ok() || generated {
+                    self.editor.world_changed(chunk_mapping);
+                    self.relight_needed = true;
+                }
+
+                match result {
+                    Ok(loaded) => {
+                        *renderer.palette.write().unwrap() = loaded;
+                        println!("loaded world from {SAVE_PATH}");
+                    },
+                    Err(e) => println!("failed to load world from {SAVE_PATH}: {e}"),
+                }
+            },
+            F6 if state == Pressed => {
+                let Some(path) = PALETTE_PATHS.iter().map(Path::new).find(|p| p.exists()) else {
+                    println!("no palette file, looked for {PALETTE_PATHS:?}");
+                    return;
+                };
+
+                match palette::load_palette(path) {
+                    Ok(loaded) => {
+                        *renderer.palette.write().unwrap() = loaded;
+                        self.relight_needed = true;
+                        println!("loaded palette from {}", path.display());
+                    },
+                    Err(e) => println!("{e}"),
+                }
+            },
+            Tab if state == Pressed => {
+                self.material_property = (self.material_property + 1) % MATERIAL_PROPERTIES.len();
+                println!("editing {} of unit {}", MATERIAL_PROPERTIES[self.material_property], self.editor.unit);
+            },
+            PageUp | PageDown if state == Pressed => {
+                let palette = &mut *renderer.palette.write().unwrap();
+                let material = &mut palette.0[self.editor.unit as usize];
+                let (value, min, max) = match self.material_property {
+                    0 => (&mut material.emissive, 0.0, 16.0),
+                    1 => (&mut material.roughness, 0.0, 1.0),
+                    2 => (&mut material.metalness, 0.0, 1.0),
+                    3 => (&mut material.transparency, 0.0, 1.0),
+                    _ => (&mut material.ior, 1.0, 3.0),
+                };
+
+                let step = if keycode == PageUp { 0.1 } else { -0.1 };
+                *value = (*value + step).clamp(min, max);
+                self.relight_needed = true;
+                println!("{} of unit {} is {:.1}", MATERIAL_PROPERTIES[self.material_property], self.editor.unit, value);
+            },
+            H | J | K | L if state == Pressed => {
+                match keycode {
+                    H => self.sun_azimuth -= 0.1,
+                    J => self.sun_azimuth += 0.1,
+                    K => self.sun_elevation = (self.sun_elevation - 0.1).max(-0.2),
+                    _ => self.sun_elevation = (self.sun_elevation + 0.1).min(std::f32::consts::FRAC_PI_2),
+                }
+
+                renderer.sun.write().unwrap().direction = Sun::direction_from_angles(self.sun_azimuth, self.sun_elevation);
+                self.reset_accumulation = true;
+            },
+            Comma | Period if state == Pressed => {
+                let hours: f32 = self.time_of_day.unwrap_or(12.0) + if keycode == Period { 0.5 } else { -0.5 };
+                let hours = hours.rem_euclid(24.0);
+                self.time_of_day = Some(hours);
+
+                let sun = &mut *renderer.sun.write().unwrap();
+                *renderer.sky.write().unwrap() = self.worlds[self.world_index].sky().at_time_of_day(hours, sun);
+                self.reset_accumulation = true;
+                println!("{hours:04.1}h");
+            },
+            N if state == Pressed => {
+                let sun = &mut *renderer.sun.write().unwrap();
+                sun.shadow_samples = if sun.shadow_samples >= 16 { 1 } else { sun.shadow_samples * 4 };
+                self.reset_accumulation = true;
+                println!("{} shadow samples", sun.shadow_samples);
+            },
+            B if state == Pressed => {
+                let settings = &mut *renderer.render_settings.write().unwrap();
+                settings.ao_quality = (settings.ao_quality + 1) % (MAX_AO_QUALITY + 1);
+                self.reset_accumulation = true;
+                println!("ambient occlusion quality {}", settings.ao_quality);
+            },
+            F1 if state == Pressed => {
+                let settings = &mut *renderer.render_settings.write().unwrap();
+                settings.debug_mode = (settings.debug_mode + 1) % DEBUG_MODES.len() as u32;
+                self.reset_accumulation = true;
+                println!("debug view: {}", DEBUG_MODES[settings.debug_mode as usize]);
+            },
+            F2 if state == Pressed => {
+                let settings = &mut *renderer.render_settings.write().unwrap();
+                settings.beauty = 1 - settings.beauty;
+                self.reset_accumulation = true;
+            },
+            P if state == Pressed => self.save_reference(renderer),
+            Key9 | Key0 if state == Pressed => {
+                let post = &mut *renderer.post_settings.write().unwrap();
+                post.exposure += if keycode == Key0 { 0.25 } else { -0.25 };
+                println!("exposure {:+.2}", post.exposure);
+            },
+            F3 if state == Pressed => {
+                let post = &mut *renderer.post_settings.write().unwrap();
+                post.tone_mapping = (post.tone_mapping + 1) % TONE_MAPPINGS.len() as u32;
+                println!("{} tone mapping", TONE_MAPPINGS[post.tone_mapping as usize]);
+            },
+            F4 if state == Pressed => {
+                let post = &mut *renderer.post_settings.write().unwrap();
+                post.bloom_strength = if post.bloom_strength > 0.0 { 0.0 } else { PostSettings::default().bloom_strength };
+            },
+            F7 if state == Pressed => {
+                let post = &mut *renderer.post_settings.write().unwrap();
+                post.vignette = if post.vignette > 0.0 { 0.0 } else { PostSettings::default().vignette };
+            },
+            F8 if state == Pressed => {
+                let post = &mut *renderer.post_settings.write().unwrap();
+                post.dither = 1 - post.dither;
+            },
+            // X is taken by worlds.
+            Insert if state == Pressed => {
+                self.show_gizmo = !self.show_gizmo;
+            },
+            F11 if state == Pressed => {
+                match self.profiler.toggle_recording(PROFILE_PATH) {
+                    Ok(()) if self.profiler.is_recording() => println!("recording frame times to {PROFILE_PATH}"),
+                    Ok(()) => println!("saved frame times to {PROFILE_PATH}"),
+                    Err(e) => println!("failed to record frame times to {PROFILE_PATH}: {e}"),
+                }
+            },
+            F10 if state == Pressed => {
+                self.show_profile = !self.show_profile;
+            },
+            F12 if state == Pressed => {
+                self.show_hud = !self.show_hud;
+            },
+            M if state == Pressed => {
+                self.editor.next_mode();
+            },
+            Equals if state == Pressed => {
+                self.editor.radius = (self.editor.radius + 1.0).min(64.0);
+            },
+            Minus if state == Pressed => {
+                self.editor.radius = (self.editor.radius - 1.0).max(1.0);
+            },
+            RBracket if state == Pressed => {
+                self.editor.unit = if self.editor.unit == 255 { 3 } else { self.editor.unit + 1 };
+            },
+            LBracket if state == Pressed => {
+                self.editor.unit = if self.editor.unit == 3 { 255 } else { self.editor.unit - 1 };
+            },
+            _ => {
+                self.worlds[self.world_index].keyboard_input(keycode, state);
+            },
+        }
+    }
+
+    // Path traces the view on the CPU, for comparing against what the GPU
+    // draws.
+    fn save_reference(&self, renderer: &Renderer) {
+        let [width, height] = renderer.resolution;
+        let resolution = Vec2::new(width as f32, height as f32);
+        let camera_data = &self.camera_data;
+        let chunk_mapping = &*renderer.chunk_mapping.read().unwrap();
+        let voxels = &*renderer.voxels.read().unwrap();
+
+        let pixels = render(
+            width,
+            height,
+            camera_data.position,
+            |pixel| camera_data.ray_direction(pixel, resolution),
+            self.samples.unwrap_or(DEFAULT_SAMPLES),
+            Scene {
+                sun: &renderer.sun.read().unwrap(),
+                settings: &renderer.render_settings.read().unwrap(),
+                sky: &renderer.sky.read().unwrap(),
+                palette: &renderer.palette.read().unwrap(),
+                chunk_mapping,
+                voxels,
+                light_mapping: &renderer.light_mapping.read().unwrap(),
+                light_chunks: &renderer.light_chunks.read().unwrap(),
+            },
+        );
+
+        let pixels = post_process(
+            &pixels,
+            width,
+            height,
+            &renderer.post_settings.read().unwrap(),
+            &renderer.palette.read().unwrap(),
+            &renderer.overlay.read().unwrap(),
+        );
+
+        match save_png(Path::new(REFERENCE_PATH), &pixels, width, height) {
+            Ok(()) => println!("saved reference render to {REFERENCE_PATH}"),
+            Err(e) => println!("failed to save reference render to {REFERENCE_PATH}: {e}"),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 #![feature(type_name_of_val)]
 
+use std::path::Path;
 use std::sync::Arc;
 
 pub trait Length {
@@ -10,294 +11,184 @@ impl<T, const LENGTH: usize> Length for [T; LENGTH] {
     const LEN: usize = LENGTH;
 }
 
-use camera_data::CameraData;
-use command_buffer::get_command_buffers;
-use pick_physical_device::{pick_best_physical_device, REQUIRED_EXTENSIONS};
-use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
-use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
-use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
-use vulkano::{VulkanLibrary, swapchain, sync};
-use vulkano::buffer::{Buffer, BufferUsage, BufferCreateInfo};
-use vulkano::command_buffer::allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
-use vulkano::device::{QueueCreateInfo, DeviceCreateInfo, Device};
-use vulkano::image::view::ImageView;
-use vulkano::image::{ImageUsage, SwapchainImage, AttachmentImage};
-use vulkano::instance::{Instance, InstanceCreateInfo};
-use vulkano::memory::allocator::{StandardMemoryAllocator, MemoryUsage, AllocationCreateInfo};
-use vulkano::pipeline::{GraphicsPipeline, Pipeline};
-use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
-use vulkano::pipeline::graphics::vertex_input::Vertex;
-use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::render_pass::{RenderPass, Framebuffer, FramebufferCreateInfo, Subpass};
-use vulkano::shader::ShaderModule;
-use vulkano::swapchain::{SwapchainCreateInfo, Swapchain, CompositeAlphas, CompositeAlpha, AcquireError, SwapchainPresentInfo};
+use cli::{parse_args, Options, USAGE};
 use winit::dpi::LogicalSize;
-use winit::event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode};
-use winit::window::WindowBuilder;
+use winit::event::{Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode, MouseButton};
+use winit::window::{Window, WindowBuilder};
 use winit::event_loop::{ControlFlow, EventLoop};
 use vulkano_win::VkSurfaceBuild;
-use vulkano::sync::{GpuFuture, FlushError};
-use glam::{Vec3, Mat4};
+use glam::{Vec2, Vec3, Vec4};
+use vulkano::instance::Instance;
 
-mod pick_physical_device;
-mod shaders;
-mod command_buffer;
-mod camera_data;
 mod worlds;
-
-use sglc_hotcode::create_vertex_buffer::set_vertex_buffer;
+mod editor;
+mod palette;
+mod read_vox;
+mod profiler;
+mod cli;
+mod app;
+mod commands;
+mod keys;
+
+use app::App;
+use editor::Editor;
+use sglc_hotcode::prefab::PasteMode;
+use sglc_hotcode::clear::clear;
+use sglc_hotcode::chunk_allocator::ChunkAllocator;
+use sglc_hotcode::world_file::{load_world, read_world_size, save_world};
+use sglc_hotcode::shade::{render, Scene};
+use sglc_hotcode::light::{relight, LightAllocator};
+use sglc_hotcode::post::post_process;
+use sglc_hotcode::overlay::Canvas;
+use sglc_renderer::error::RendererError;
+use sglc_renderer::pick_physical_device::report_physical_devices;
+use sglc_renderer::renderer::{create_instance, Renderer, RendererOptions};
 use worlds::hills::Hills;
 use worlds::spheres::Spheres;
-use sglc_shared::{WORLD_SIZE_ONE, CHUNK_COUNT_ONE, CHUNK_SIZE_ONE, ChunkMapping, Voxels, MyVertex, Vertices};
-
-const WIDTH: u32 = 320;
-const HEIGHT: u32 = 180;
-const ASPECT_RATIO: f32 = WIDTH as f32 / HEIGHT as f32;
+use sglc_shared::camera_data::{focal_length, CameraData};
+use sglc_shared::sun::Sun;
+use sglc_shared::render_settings::RenderSettings;
+use sglc_shared::sky::Sky;
+use sglc_shared::palette::Palette;
+use sglc_shared::light::{LightChunks, LightMapping};
+use sglc_shared::post_settings::PostSettings;
+use sglc_shared::{CHUNK_SIZE_ONE, ChunkMapping, Voxels, WorldSize};
+
+// Where the world goes when the window or the GPU gets lost.
+const RECOVERY_PATH: &str = "recovery.sgw";
 
 fn main() {
-    let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
-    let required_extensions = vulkano_win::required_extensions(&library);
-    let instance = Instance::new(
-        library,
-        InstanceCreateInfo {
-            enabled_extensions: required_extensions,
-            ..Default::default()
-        },
-    )
-    .expect("failed to create instance");
-
-    let event_loop = EventLoop::new();
-    let surface = WindowBuilder::new()
-        .with_title("poopoo haha")
-        .with_inner_size(LogicalSize::new(WIDTH * 3, HEIGHT * 3))
-        .build_vk_surface(&event_loop, instance.clone())
-        .unwrap();
-
-    let physical_device = pick_best_physical_device(&instance, &surface);
-
-    let (device, mut queues) = Device::new(
-        physical_device.device.clone(),
-        DeviceCreateInfo {
-            queue_create_infos: vec![QueueCreateInfo {
-                queue_family_index: physical_device.graphics_queue_index,
-                ..Default::default()
-            }],
-            enabled_extensions: REQUIRED_EXTENSIONS,
-            ..Default::default()
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
         },
-    )
-    .expect("failed to create device");
-
-    let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
-    let cmd_buffer_allocator = StandardCommandBufferAllocator::new(
-        device.clone(),
-        StandardCommandBufferAllocatorCreateInfo::default(),
-    );
-
-    let queue = queues.next().unwrap();
-
-    let capabilities = physical_device.device
-        .surface_capabilities(&surface, Default::default())
-        .expect("failed to get surface capabilities");
-
-    let dimensions = [WIDTH, HEIGHT];
-    let composite_alpha = 
-        pick_best_composite_alpha(capabilities.supported_composite_alpha).unwrap();
-    let image_format = Some(
-        physical_device.device
-            .surface_formats(&surface, Default::default())
-            .unwrap()[0]
-            .0,
-    );
-
-    let depth_buffer = ImageView::new_default(
-        AttachmentImage::transient(&memory_allocator, dimensions, vulkano::format::Format::D16_UNORM).unwrap(),
-    ).unwrap();
-
-    let (swapchain, images) = Swapchain::new(
-        device.clone(),
-        surface.clone(),
-        SwapchainCreateInfo {
-            // How many buffers to use in the swapchain
-            min_image_count: capabilities.min_image_count + 1,
-            image_format,
-            image_extent: dimensions.into(),
-            // What the images are going to be used for
-            image_usage: ImageUsage::COLOR_ATTACHMENT,
-            composite_alpha,
-            ..Default::default()
-        },
-    )
-    .unwrap();
+    };
 
-    let render_pass = get_render_pass(device.clone(), &swapchain);
-    let framebuffers = get_framebuffers(&images, &render_pass, &depth_buffer);
+    if options.help {
+        println!("{USAGE}");
+        return;
+    }
 
-    let vertex_buffer = Buffer::new_unsized::<Vertices>(
-        &memory_allocator,
-        BufferCreateInfo {
-            usage: BufferUsage::VERTEX_BUFFER,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            usage: MemoryUsage::Upload,
-            ..Default::default()
-        },
-        std::mem::size_of::<Vertices>() as u64,
-    )
-    .unwrap();
-
-    let (chunk_mapping_buffer, voxels_buffer) = {
-        let chunk_mapping_buffer = Buffer::new_unsized::<ChunkMapping>(
-            &memory_allocator,
-            BufferCreateInfo {
-                usage: BufferUsage::UNIFORM_BUFFER | BufferUsage::STORAGE_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: MemoryUsage::Upload,
-                ..Default::default()
+    if let Some(path) = &options.screenshot {
+        let result = if options.gpu { gpu_screenshot(&options, path) } else { screenshot(&options, path) };
+        match result {
+            Ok(()) => println!("saved screenshot to {}", path.display()),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
             },
-            std::mem::size_of::<ChunkMapping>() as u64,
-        ).unwrap();
-
-        let voxels_buffer = Buffer::new_unsized::<Voxels>(
-            &memory_allocator,
-            BufferCreateInfo {
-                usage: BufferUsage::UNIFORM_BUFFER | BufferUsage::STORAGE_BUFFER,
-                ..Default::default()
-            },
-            AllocationCreateInfo {
-                usage: MemoryUsage::Upload,
-                ..Default::default()
-            },
-            std::mem::size_of::<Voxels>() as u64,
-        ).unwrap();
-
-        (chunk_mapping_buffer, voxels_buffer)
-    };
-
-    let pallete = {
-        let mut pallete = Vec::<[f32; 4]>::new();
-        
-        while pallete.len() != 256 {
-            pallete.push([
-                fastrand::f32(), 
-                fastrand::f32(),
-                fastrand::f32(),
-                fastrand::f32()
-            ]);
         }
+        return;
+    }
 
-        pallete
-    };
-
-    let camera_data = CameraData {
-        aspect_ratio: HEIGHT as f32 / WIDTH as f32, 
-        proj: Mat4::perspective_rh(0.7, std::f32::consts::FRAC_PI_2, 0.1, 1.0),
-        position: Vec3::new(0.0, 0.0, -1.0),
-        ..Default::default()
-    };
+    if let Err(e) = run(options) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
 
-    let pallete_buffer = Buffer::from_iter(
-        &memory_allocator,
-        BufferCreateInfo {
-            usage: BufferUsage::UNIFORM_BUFFER,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            usage: MemoryUsage::Upload,
-            ..Default::default()
-        },
-        pallete.into_iter(),
-    ).unwrap();
-
-    let camera_data_buffer = Buffer::from_iter(
-        &memory_allocator,
-        BufferCreateInfo {
-            usage: BufferUsage::UNIFORM_BUFFER,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            usage: MemoryUsage::Upload,
-            ..Default::default()
-        },
-        [camera_data].into_iter(),
-    ).unwrap();
+// Sets up the window and Vulkan and runs until the window closes.
+fn run(options: Options) -> Result<(), RendererError> {
+    let [width, height] = options.resolution;
+    let resolution = Vec2::new(width as f32, height as f32);
 
-    let vs = shaders::vs::load(device.clone()).expect("failed to create shader module");
-    let fs = shaders::fs::load(device.clone()).expect("failed to create shader module");
+    let instance = create_instance(options.debug)?;
 
-    let viewport = Viewport {
-        origin: [0.0, 0.0],
-        dimensions: [WIDTH as f32, HEIGHT as f32],
-        depth_range: 0.0..1.0,
-    };
+    // Listing doesn't open a window, so whether a device can present isn't
+    // checked.
+    if options.list_devices {
+        let device_reports = report_physical_devices(&instance, None, starting_world_size(&options))
+            .map_err(RendererError::NoDevice)?;
 
-    let pipeline = get_pipeline(
-        device.clone(),
-        vs.clone(),
-        fs.clone(),
-        render_pass.clone(),
-        viewport.clone(),
-    );
+        for (i, report) in device_reports.iter().enumerate() {
+            let properties = report.device.properties();
+            println!("{i}: {} ({:?}, Vulkan {})", report.name(), properties.device_type, properties.api_version);
 
-    let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
-    let pipeline_layout = pipeline.layout();
-    let descriptor_set_layouts = pipeline_layout.set_layouts();
-
-    let blocks_descriptor_set = PersistentDescriptorSet::new(
-        &descriptor_set_allocator,
-        descriptor_set_layouts.get(0).unwrap().clone(),
-        [
-            WriteDescriptorSet::buffer(0, chunk_mapping_buffer.clone()),
-            WriteDescriptorSet::buffer(1, voxels_buffer.clone()),
-        ],
-    ).unwrap();
-    let render_descriptor_set = PersistentDescriptorSet::new(
-        &descriptor_set_allocator,
-        descriptor_set_layouts.get(1).unwrap().clone(),
-        [
-            WriteDescriptorSet::buffer(0, pallete_buffer.clone()),
-            WriteDescriptorSet::buffer(1, camera_data_buffer.clone()),
-        ],
-    ).unwrap();
-
-    let command_buffers = get_command_buffers(
-        &queue,
-        &pipeline,
-        &framebuffers,
-        &vertex_buffer,
-        &blocks_descriptor_set,
-        &render_descriptor_set,
-        &cmd_buffer_allocator,
-    );
+            for problem in &report.problems {
+                println!("    can't be used: {problem}");
+            }
+            for problem in &report.limit_problems {
+                println!("    over a limit: {problem}");
+            }
+        }
+        return Ok(());
+    }
 
-    let mut worlds: Vec<Box<dyn World>> = vec![
-        Box::new(Spheres::default()),
-        Box::new(Hills::default()),
-    ];
-    let mut world_index = 0;
+    let event_loop = EventLoop::new();
+    let surface = WindowBuilder::new()
+        .with_title("sglc")
+        .with_inner_size(LogicalSize::new(width * options.scale, height * options.scale))
+        .build_vk_surface(&event_loop, instance.clone())?;
+    let window = surface.object().unwrap().clone().downcast::<Window>().unwrap();
+
+    let renderer_options = RendererOptions {
+        resolution: options.resolution,
+        device: options.device.clone(),
+        debug: options.debug,
+        world_size: starting_world_size(&options),
+    };
+    let renderer = Renderer::new(surface, &renderer_options)?;
+    *renderer.palette.write().unwrap() = palette::random_palette();
 
-    let mut recreate_swapchain = false;
+    let mut app = App::new(&options);
+    *renderer.sky.write().unwrap() = app.worlds[app.world_index].sky();
 
-    let mut time_avg = 0;
-    let mut vertex_count = 0;
-    let mut passed_frames = 0;
-    let mut target_position = Vec3::default();
-    let mut motion_speed = 1.0;
+    // Only empty while a lost renderer is being replaced.
+    let mut renderer_slot = Some(renderer);
     event_loop.run(move |event, _, control_flow| {
+        let Some(renderer) = renderer_slot.as_mut() else { return };
+
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => {
-                if recreate_swapchain {
-                    println!("recreation is supposed to happen rn");
-                    recreate_swapchain = false;
-                }
                 *control_flow = ControlFlow::Exit;
             },
+            Event::WindowEvent {
+                event: WindowEvent::Resized(_),
+                ..
+            } => {
+                renderer.recreate_swapchain = true;
+            },
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(new_modifiers),
+                ..
+            } => {
+                app.modifiers = new_modifiers;
+            },
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } if app.console.open => {
+                app.console.type_char(c);
+            },
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                let size = window.inner_size();
+                app.cursor_position = Vec2::new(
+                    position.x as f32 / size.width as f32 * resolution.x,
+                    position.y as f32 / size.height as f32 * resolution.y,
+                );
+            },
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput {
+                    state: ElementState::Pressed,
+                    button: MouseButton::Left,
+                    ..
+                },
+                ..
+            } if !app.console.open => {
+                let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+                let voxels = &mut *renderer.voxels.write().unwrap();
+
+                app.editor.apply(chunk_mapping, voxels);
+                app.reset_accumulation = true;
+            },
             Event::WindowEvent { 
                 event: WindowEvent::KeyboardInput { 
                     input: KeyboardInput {
@@ -309,222 +200,296 @@ fn main() {
                 }, 
                 .. 
             }  => {
-                use winit::event::VirtualKeyCode::*;
-                use winit::event::ElementState::*;
-
-                let camera_data = &mut camera_data_buffer.write().unwrap()[0];
-                let camera_quat = camera_data.neg_quat();
-
-                match keycode {
-                    LShift if state == Pressed => {
-                        motion_speed = 10.0;
-                    },
-                    LShift if state == Released => {
-                        motion_speed = 1.0;
-                    },
-                    A if state == Pressed => {
-                        target_position -= camera_quat * Vec3::X * motion_speed;
-                    },
-                    D if state == Pressed => {
-                        target_position += camera_quat * Vec3::X * motion_speed;
-                    },
-                    Q if state == Pressed => {
-                        target_position += Vec3::Y * motion_speed;
-                    },
-                    E if state == Pressed => {
-                        target_position -= Vec3::Y * motion_speed;
-                    },
-                    W if state == Pressed => {
-                        target_position += camera_quat * Vec3::Z * motion_speed;
-                    },
-                    S if state == Pressed => {
-                        target_position -= camera_quat * Vec3::Z * motion_speed;
-                    },
-                    R if state == Pressed => {
-                        camera_data.yaw -= 0.1;
-                    },
-                    F if state == Pressed => {
-                        camera_data.yaw += 0.1;
-                    },
-                    T if state == Pressed => {
-                        camera_data.pitch -= 0.1;
-                    },
-                    G if state == Pressed => {
-                        camera_data.pitch += 0.1;
-                    },
-                    Z => {
-                        world_index += 1;
-                        if world_index == worlds.len() { world_index = 0 }
-                    },
-                    _ => {
-                        worlds[world_index].keyboard_input(keycode, state);
-                    },
-                }
+                app.keyboard_input(renderer, keycode, state);
             }
-            Event::MainEventsCleared => {
-                let execution_time = std::time::Instant::now();
+            Event::MainEventsCleared => match app.frame(renderer) {
+                Ok(()) => (),
+                Err(e) if e.is_recoverable() => {
+                    eprintln!("{e}, starting over");
+                    if !start_over(&mut app, &mut renderer_slot, &instance, &window, &renderer_options) {
+                        *control_flow = ControlFlow::ExitWithCode(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    *control_flow = ControlFlow::ExitWithCode(1);
+                }
+            },
+            _ => ()
+        }
+    });
+}
 
-                set_vertex_buffer(
-                    &*chunk_mapping_buffer.read().unwrap(),
-                    &mut *vertex_buffer.write().unwrap(),
-                    &mut vertex_count,
-                );
+fn all_worlds() -> Vec<Box<dyn World>> {
+    vec![
+        Box::new(Spheres::default()),
+        Box::new(Hills::default()),
+    ]
+}
 
-                {
-                    let camera_data = &mut camera_data_buffer.write().unwrap()[0];
-                    let camera_position = camera_data.position;
-                    let camera_rotation = camera_data.quat();
+// Renders on the CPU like P does, so no window or GPU is needed.
+fn screenshot(options: &Options, path: &Path) -> Result<(), String> {
+    let [width, height] = options.resolution;
+    let mut worlds = all_worlds();
+    let world = match &options.world {
+        Some(name) => worlds
+            .iter_mut()
+            .find(|world| world.name() == name)
+            .ok_or_else(|| format!("no world called {name}"))?,
+        None => &mut worlds[0],
+    };
 
-                    let camera_matrix = Mat4::from_quat(camera_rotation) * Mat4::from_translation(-camera_position);
-                    camera_data.camera = camera_matrix;
-                    camera_data.proj = Mat4::perspective_lh(0.7, ASPECT_RATIO, 1.0, 10000.0);
-                    camera_data.rot = Mat4::from_quat(camera_data.quat_frag());
+    if let Some(seed) = options.seed {
+        world.set_seed(seed);
+    }
 
-                    const MOTION_SPEED: f32 = 0.3;
-                    camera_data.position = 
-                        camera_data.position * (1. - MOTION_SPEED) + 
-                        target_position * MOTION_SPEED;
-                }
+    let world_size = starting_world_size(options);
+    let mut chunk_mapping = ChunkMapping::new(world_size);
+    let mut voxels = Voxels::new(world_size);
+    world.fill_in_voxels(&mut chunk_mapping, &mut voxels);
 
-                {
-                    let chunk_mapping = &mut *chunk_mapping_buffer.write().unwrap();
-                    let voxels = &mut *voxels_buffer.write().unwrap();
+    let palette = match &options.load {
+        Some(load) => load_world_from(load, &mut chunk_mapping, &mut voxels)
+            .map_err(|e| format!("failed to load {}: {e}", load.display()))?,
+        None => palette::random_palette(),
+    };
 
-                    worlds[world_index].fill_in_voxels(chunk_mapping, voxels);
-                }
+    let mut light_mapping = LightMapping::new(world_size);
+    let mut light_chunks = LightChunks::new();
+    relight(&palette, &chunk_mapping, &voxels, &mut LightAllocator::default(), &mut light_mapping, &mut light_chunks);
 
-                let (image_i, suboptimal, acquire_future) =
-                    match swapchain::acquire_next_image(swapchain.clone(), None) {
-                        Ok(r) => r,
-                        Err(AcquireError::OutOfDate) => {
-                            recreate_swapchain = true;
-                            return;
-                        }
-                        Err(e) => panic!("failed to acquire next image: {e}"),
-                    };
-
-                if suboptimal {
-                    recreate_swapchain = true;
-                }
+    let (position, yaw, pitch) = options.camera.unwrap_or((Vec3::new(0.0, 0.0, -1.0), 0.0, 0.0));
+    let aspect_ratio = height as f32 / width as f32;
+    let camera_data = CameraData {
+        aspect_ratio,
+        focal_length: focal_length(0.7, aspect_ratio),
+        position,
+        yaw,
+        pitch,
+        ..Default::default()
+    };
+    let resolution = Vec2::new(width as f32, height as f32);
+
+    let pixels = render(
+        width,
+        height,
+        position,
+        |pixel| camera_data.ray_direction(pixel, resolution),
+        options.samples.unwrap_or(1),
+        Scene {
+            sun: &Sun::default(),
+            settings: &RenderSettings { beauty: options.samples.is_some() as u32, ..Default::default() },
+            sky: &world.sky(),
+            palette: &palette,
+            chunk_mapping: &chunk_mapping,
+            voxels: &voxels,
+            light_mapping: &light_mapping,
+            light_chunks: &light_chunks,
+        },
+    );
 
-                let execution = sync::now(device.clone())
-                    .join(acquire_future)
-                    .then_execute(queue.clone(), command_buffers[image_i as usize].clone())
-                    .unwrap()
-                    .then_swapchain_present(
-                        queue.clone(),
-                        SwapchainPresentInfo::swapchain_image_index(swapchain.clone(), image_i),
-                    )
-                    .then_signal_fence_and_flush();
-
-                match execution {
-                    Ok(future) => {
-                        future.wait(None).unwrap();  // wait for the GPU to finish
-                        let time = execution_time.elapsed().as_micros();
-                        time_avg += time;
-                        passed_frames += 1;
-
-                        if time_avg >= 1000000 {
-                            let time_per_frame_in_millis = time_avg / passed_frames;
-                            println!("{}fps", 1000000 / time_per_frame_in_millis);
-                            time_avg = 0;
-                            passed_frames = 0;
-                        }
-                    }
-                    Err(FlushError::OutOfDate) => {
-                        recreate_swapchain = true;
-                    }
-                    Err(e) => {
-                        println!("Failed to flush future: {e}");
-                    }
-                }
-            }
-            _ => ()
-        }
+    let mut overlay = vec![0; (width * height) as usize];
+    if options.hud {
+        let lines = app::hud_lines(&**world, position, &Editor::default(), None);
+        Canvas { pixels: &mut overlay, width: width as usize }.draw_panel(1, 1, &lines);
+    }
+    let pixels = post_process(&pixels, width, height, &PostSettings::default(), &palette, &overlay);
+
+    save_png(path, &pixels, width, height).map_err(|e| format!("failed to save screenshot to {}: {e}", path.display()))
+}
+
+// Like `screenshot`, but drawn by the renderer the way the window shows it,
+// into an image instead of a window.
+fn gpu_screenshot(options: &Options, path: &Path) -> Result<(), String> {
+    let [width, height] = options.resolution;
+    let instance = create_instance(options.debug).map_err(|e| e.to_string())?;
+    let renderer_options = RendererOptions {
+        resolution: options.resolution,
+        device: options.device.clone(),
+        debug: options.debug,
+        world_size: starting_world_size(options),
+    };
+    let mut renderer = Renderer::new_offscreen(instance, &renderer_options).map_err(|e| e.to_string())?;
+    *renderer.palette.write().unwrap() = palette::random_palette();
+
+    let mut app = App::new(options);
+    app.show_hud = options.hud;
+    *renderer.sky.write().unwrap() = app.worlds[app.world_index].sky();
+    renderer.render_settings.write().unwrap().beauty = options.samples.is_some() as u32;
+
+    // The first frame runs the commands and fills in the world, which the
+    // chunks drawn only catch up with on the next one. From there every
+    // frame adds a sample.
+    app.frame(&mut renderer).map_err(|e| e.to_string())?;
+    app.reset_accumulation = true;
+    for _ in 0..options.samples.unwrap_or(1) {
+        app.frame(&mut renderer).map_err(|e| e.to_string())?;
+    }
+
+    let pixels = renderer.pixels.as_ref().unwrap().read().map_err(|e| e.to_string())?.to_vec();
+    image::RgbaImage::from_raw(width, height, pixels)
+        .unwrap()
+        .save(path)
+        .map_err(|e| format!("failed to save screenshot to {}: {e}", path.display()))
+}
+
+fn save_png(path: &Path, pixels: &[Vec4], width: u32, height: u32) -> image::ImageResult<()> {
+    let image = image::RgbaImage::from_fn(width, height, |x, y| {
+        let color = pixels[(x + y * width) as usize].clamp(Vec4::ZERO, Vec4::ONE);
+        image::Rgba((color * 255.0).round().to_array().map(|c| c as u8))
     });
+
+    image.save(path)
 }
 
-fn get_render_pass(device: Arc<Device>, swapchain: &Arc<Swapchain>) -> Arc<RenderPass> {
-    vulkano::single_pass_renderpass!(
-        device,
-        attachments: {
-            color: {
-                load: Clear,
-                store: Store,
-                format: swapchain.image_format(), // set the format the same as the swapchain
-                samples: 1,
-            },
-             depth: {
-                load: Clear,
-                store: DontCare,
-                format: vulkano::format::Format::D16_UNORM,
-                samples: 1,
-            }
-        },
-        pass: {
-            color: [color],
-            depth_stencil: {depth},
-        },
-    )
-    .unwrap()
+// --world-size if it was given, otherwise the size of the saved world to
+// --load, since the world can't change size once it's set up.
+fn starting_world_size(options: &Options) -> WorldSize {
+    options.world_size
+        .or_else(|| options.load.as_ref().and_then(|path| saved_world_size(path).ok()))
+        .unwrap_or_default()
 }
 
-fn get_framebuffers(
-    images: &[Arc<SwapchainImage>],
-    render_pass: &Arc<RenderPass>,
-    depth_buffer: &Arc<ImageView<AttachmentImage>>,
-) -> Vec<Arc<Framebuffer>> {
-    images
-        .iter()
-        .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
-            Framebuffer::new(
-                render_pass.clone(),
-                FramebufferCreateInfo {
-                    attachments: vec![view, depth_buffer.clone()],
-                    ..Default::default()
-                },
-            )
-            .unwrap()
-        })
-        .collect::<Vec<_>>()
+fn saved_world_size(path: &Path) -> std::io::Result<WorldSize> {
+    let file = std::fs::File::open(path)?;
+    read_world_size(&mut std::io::BufReader::new(file))
 }
 
-fn get_pipeline(
-    device: Arc<Device>,
-    vs: Arc<ShaderModule>,
-    fs: Arc<ShaderModule>,
-    render_pass: Arc<RenderPass>,
-    viewport: Viewport,
-) -> Arc<GraphicsPipeline> {
-    GraphicsPipeline::start()
-        .vertex_input_state(MyVertex::per_vertex())
-        .vertex_shader(vs.entry_point("main").unwrap(), ())
-        .input_assembly_state(InputAssemblyState::new())
-        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fs.entry_point("main").unwrap(), ())
-        .depth_stencil_state(DepthStencilState::simple_depth_test())
-        .render_pass(Subpass::from(render_pass, 0).unwrap())
-        .build(device)
-        .unwrap()
+// Saved worlds, or .vox models put in the middle of an empty world.
+fn load_world_from(path: &Path, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> std::io::Result<Palette> {
+    if path.extension().is_some_and(|extension| extension == "vox") {
+        let bytes = std::fs::read(path)?;
+        let model = read_vox::read_vox_model(&bytes)?;
+        let palette = read_vox::read_vox_palette(&bytes)?;
+
+        clear(chunk_mapping, voxels);
+        let at = (chunk_mapping.size.voxels() - model.size) / 2;
+        model.stamp(at, PasteMode::Merge, &mut ChunkAllocator::from_mapping(chunk_mapping), chunk_mapping, voxels);
+        return Ok(palette);
+    }
+
+    let file = std::fs::File::open(path)?;
+    load_world(&mut std::io::BufReader::new(file), chunk_mapping, voxels)
+}
+
+fn save_world_to(path: &str, palette: &Palette, chunk_mapping: &ChunkMapping, voxels: &Voxels) -> std::io::Result<()> {
+    let file = std::fs::File::create(path)?;
+    save_world(&mut std::io::BufWriter::new(file), palette, chunk_mapping, voxels)
+}
+
+// Keeps the world safe in case starting over after losing the surface or the
+// device doesn't work out. Returns true if it was saved.
+fn save_recovery(renderer: &Renderer) -> bool {
+    let saved = match (renderer.palette.read(), renderer.chunk_mapping.read(), renderer.voxels.read()) {
+        (Ok(palette), Ok(chunk_mapping), Ok(voxels)) => {
+            save_world_to(RECOVERY_PATH, &palette, &chunk_mapping, &voxels).map_err(|e| e.to_string())
+        },
+        _ => Err("the GPU still has the buffers".to_string()),
+    };
+
+    match saved {
+        Ok(()) => eprintln!("saved the world to {RECOVERY_PATH}"),
+        Err(e) => eprintln!("failed to save the world: {e}"),
+    }
+    saved.is_ok()
 }
 
-pub fn pick_best_composite_alpha(from: CompositeAlphas) -> Option<CompositeAlpha> {
-    if from.intersects(CompositeAlphas::OPAQUE) {
-        Some(CompositeAlpha::Opaque)
-    } else if from.intersects(CompositeAlphas::INHERIT) {
-        Some(CompositeAlpha::Inherit)
-    } else if from.intersects(CompositeAlphas::PRE_MULTIPLIED) {
-        Some(CompositeAlpha::PreMultiplied)
-    } else if from.intersects(CompositeAlphas::POST_MULTIPLIED) {
-        Some(CompositeAlpha::PostMultiplied)
+// A new surface for the same window and a renderer on it, possibly on another
+// device than before.
+fn restart_renderer(
+    instance: &Arc<Instance>,
+    window: &Arc<Window>,
+    options: &RendererOptions,
+) -> Result<Renderer, RendererError> {
+    let surface = vulkano_win::create_surface_from_winit(window.clone(), instance.clone())
+        .map_err(|e| RendererError::Window(e.to_string()))?;
+
+    Renderer::new(surface, options)
+}
+
+// Saves the world, replaces the lost renderer with one on a new surface and
+// brings the world and the settings back into it. False if there's no
+// renderer to go on with.
+fn start_over(
+    app: &mut App,
+    renderer_slot: &mut Option<Renderer>,
+    instance: &Arc<Instance>,
+    window: &Arc<Window>,
+    options: &RendererOptions,
+) -> bool {
+    let Some(lost) = renderer_slot.take() else { return false };
+    let saved = save_recovery(&lost);
+    let sun = lost.sun.read().map(|sun| *sun).unwrap_or_default();
+    let settings = lost.render_settings.read().map(|settings| *settings).unwrap_or_default();
+    let post = lost.post_settings.read().map(|post| *post).unwrap_or_default();
+    let sky = lost.sky.read().map(|sky| *sky).unwrap_or_else(|_| app.worlds[app.world_index].sky());
+
+    // The window can only have one swapchain, so the old renderer has to go
+    // before there's a new one.
+    drop(lost);
+    let renderer = match restart_renderer(instance, window, options) {
+        Ok(renderer) => renderer_slot.insert(renderer),
+        Err(e) => {
+            eprintln!("couldn't start over: {e}");
+            if saved {
+                eprintln!("run with --load {RECOVERY_PATH} to pick up from where it stopped");
+            }
+            return false;
+        }
+    };
+
+    *renderer.sun.write().unwrap() = sun;
+    *renderer.render_settings.write().unwrap() = settings;
+    *renderer.post_settings.write().unwrap() = post;
+    *renderer.sky.write().unwrap() = sky;
+
+    let chunk_mapping = &mut *renderer.chunk_mapping.write().unwrap();
+    let voxels = &mut *renderer.voxels.write().unwrap();
+    let loaded = if saved {
+        load_world_from(Path::new(RECOVERY_PATH), chunk_mapping, voxels).map_err(|e| e.to_string())
     } else {
-        None
+        Err("it wasn't saved".to_string())
+    };
+
+    match loaded {
+        Ok(palette) => *renderer.palette.write().unwrap() = palette,
+        Err(e) => {
+            eprintln!("couldn't bring the world back, {e}");
+            *renderer.palette.write().unwrap() = palette::random_palette();
+            app.worlds[app.world_index].invalidate();
+        }
     }
+
+    app.editor.world_changed(chunk_mapping);
+    app.relight_needed = true;
+    app.reset_accumulation = true;
+    true
 }
 
 pub trait World {
-    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels);
+    // Shown in the overlay.
+    fn name(&self) -> &str;
+
+    // What generation starts from, for worlds that are random.
+    fn seed(&self) -> Option<u64> { None }
+
+    // Returns true if the voxels were replaced, rather than left as they were.
+    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool;
+
+    // Makes the next `fill_in_voxels` generate the world again.
+    fn invalidate(&mut self) { }
+
+    // The sky and fog the world is seen with.
+    fn sky(&self) -> Sky { Sky::default() }
+
+    // Only called for worlds that have a seed.
+    fn set_seed(&mut self, _: u64) { }
+
+    // Console commands the world adds, with what they take.
+    fn commands(&self) -> &'static [(&'static str, &'static str)] { &[] }
+
+    // Runs one of `commands`, given the words after its name.
+    fn run_command(&mut self, _: &str, _: &[&str]) -> Result<(), String> { Ok(()) }
 
     fn keyboard_input(&mut self, _: VirtualKeyCode, _: ElementState) { }
 }
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use sglc_shared::palette::{Material, Palette, PALETTE_SIZE};
+
+use crate::read_vox::read_vox_palette;
+
+// Lists of colors (hex files and png strips) don't say which unit each color
+// is for, so they start at the first unit that gets drawn.
+const FIRST_LISTED_UNIT: usize = 3;
+
+pub fn random_palette() -> Palette {
+    let mut palette = Palette::default();
+
+    for material in &mut palette.0 {
+        material.color = [
+            fastrand::f32(),
+            fastrand::f32(),
+            fastrand::f32(),
+            1.0,
+        ];
+    }
+
+    palette
+}
+
+fn from_list(colors: impl IntoIterator<Item = [u8; 4]>) -> Palette {
+    let mut palette = Palette::default();
+
+    for (material, color) in palette.0[FIRST_LISTED_UNIT..].iter_mut().zip(colors) {
+        *material = Material::from_rgba8(color);
+    }
+
+    palette
+}
+
+// One color per line, as `rrggbb` or `rrggbbaa`, optionally starting with `#`.
+// Empty lines and lines starting with `;` are skipped.
+pub fn palette_from_hex(text: &str) -> Result<Palette, String> {
+    let colors = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(';'))
+        .map(|line| {
+            let hex = line.trim_start_matches('#');
+            let value = u32::from_str_radix(hex, 16).map_err(|e| format!("bad color {line:?}: {e}"))?;
+
+            match hex.len() {
+                6 => Ok((value << 8 | 0xff).to_be_bytes()),
+                8 => Ok(value.to_be_bytes()),
+                _ => Err(format!("bad color {line:?}: expected 6 or 8 hex digits")),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if colors.len() > PALETTE_SIZE - FIRST_LISTED_UNIT {
+        return Err(format!(
+            "{} colors don't fit, at most {} do",
+            colors.len(),
+            PALETTE_SIZE - FIRST_LISTED_UNIT,
+        ));
+    }
+
+    Ok(from_list(colors))
+}
+
+// Reads the top row of the image, left to right.
+pub fn palette_from_png(image: &image::RgbaImage) -> Palette {
+    from_list((0..image.width()).map(|x| image.get_pixel(x, 0).0))
+}
+
+pub fn load_palette(path: &Path) -> Result<Palette, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let describe = |e: &dyn std::fmt::Display| format!("failed to load palette {}: {e}", path.display());
+
+    match extension {
+        "vox" => {
+            let bytes = std::fs::read(path).map_err(|e| describe(&e))?;
+            read_vox_palette(&bytes).map_err(|e| describe(&e))
+        },
+        "png" => {
+            let image = image::open(path).map_err(|e| describe(&e))?;
+            Ok(palette_from_png(&image.into_rgba8()))
+        },
+        _ => {
+            let text = std::fs::read_to_string(path).map_err(|e| describe(&e))?;
+            palette_from_hex(&text).map_err(|e| describe(&e))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors_start_at_the_first_drawn_unit() {
+        let palette = palette_from_hex("#ff0000\n; a comment\n\n  00ff0080\n0000FF\n").unwrap();
+
+        assert_eq!(palette.0[2], Material::default());
+        assert_eq!(palette.0[3], Material::from_rgba8([255, 0, 0, 255]));
+        assert_eq!(palette.0[4], Material::from_rgba8([0, 255, 0, 128]));
+        assert_eq!(palette.0[5], Material::from_rgba8([0, 0, 255, 255]));
+        assert_eq!(palette.0[6], Material::default());
+    }
+
+    #[test]
+    fn bad_hex_files_are_errors() {
+        assert!(palette_from_hex("ff00zz").is_err());
+        assert!(palette_from_hex("ff00").is_err());
+        assert!(palette_from_hex("ff0000ff00").is_err());
+
+        let fits = "000000\n".repeat(PALETTE_SIZE - FIRST_LISTED_UNIT);
+        assert!(palette_from_hex(&fits).is_ok());
+        assert!(palette_from_hex(&(fits + "000000")).is_err());
+    }
+
+    #[test]
+    fn png_palettes_are_read_from_the_top_row() {
+        let image = image::RgbaImage::from_fn(300, 2, |x, y| image::Rgba([x as u8, y as u8, 7, 255]));
+        let palette = palette_from_png(&image);
+
+        assert_eq!(palette.0[2], Material::default());
+        assert_eq!(palette.0[3], Material::from_rgba8([0, 0, 7, 255]));
+        assert_eq!(palette.0[255], Material::from_rgba8([252, 0, 7, 255]));
+    }
+
+    #[test]
+    fn load_palette_goes_by_extension() {
+        let dir = std::env::temp_dir().join(format!("sglc-palette-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let hex = dir.join("palette.hex");
+        std::fs::write(&hex, "123456\n").unwrap();
+        assert_eq!(load_palette(&hex).unwrap().0[3], Material::from_rgba8([0x12, 0x34, 0x56, 255]));
+
+        let png = dir.join("palette.png");
+        image::RgbaImage::from_pixel(1, 1, image::Rgba([1, 2, 3, 4])).save(&png).unwrap();
+        assert_eq!(load_palette(&png).unwrap().0[3], Material::from_rgba8([1, 2, 3, 4]));
+
+        let bytes = include_bytes!("../voxes/pipes.vox");
+        let vox = dir.join("palette.vox");
+        std::fs::write(&vox, bytes).unwrap();
+        assert_eq!(load_palette(&vox).unwrap(), read_vox_palette(bytes).unwrap());
+
+        assert!(load_palette(&dir.join("missing.hex")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
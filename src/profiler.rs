@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, Instant};
+
+// Everything timed in a frame, in milliseconds, in the order the CSV has them.
+pub const TIMINGS: [&str; 8] = [
+    "frame", "wait", "set_vertex_buffer", "fill_in_voxels", "light", "gpu_scene", "gpu_post", "upload",
+];
+pub const FRAME: usize = 0;
+pub const WAIT: usize = 1;
+pub const SET_VERTEX_BUFFER: usize = 2;
+pub const FILL_IN_VOXELS: usize = 3;
+pub const LIGHT: usize = 4;
+pub const GPU_SCENE: usize = 5;
+pub const GPU_POST: usize = 6;
+// The buffers are all host visible, so uploading is the CPU writing the
+// camera, selection, gizmo and overlay into them; there's no transfer on the
+// GPU to put timestamps around.
+pub const UPLOAD: usize = 7;
+
+pub fn millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct FrameStats {
+    pub timings: [f32; TIMINGS.len()],
+    pub allocated_chunks: usize,
+    pub vertex_count: usize,
+    pub light_chunks: usize,
+}
+
+// Averages frames over a second for the HUD, and writes every frame to a CSV
+// file while recording.
+pub struct Profiler {
+    sum: [f32; TIMINGS.len()],
+    frames: u32,
+    since: Instant,
+    frame: u64,
+    pub average: FrameStats,
+    csv: Option<BufWriter<File>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            sum: [0.0; TIMINGS.len()],
+            frames: 0,
+            since: Instant::now(),
+            frame: 0,
+            average: FrameStats::default(),
+            csv: None,
+        }
+    }
+}
+
+impl Profiler {
+    pub fn record(&mut self, stats: FrameStats) {
+        for (sum, timing) in self.sum.iter_mut().zip(stats.timings) {
+            *sum += timing;
+        }
+        self.frames += 1;
+        self.frame += 1;
+
+        if let Some(csv) = &mut self.csv {
+            let timings = stats.timings.map(|timing| format!("{timing:.3}")).join(",");
+            let result = writeln!(
+                csv,
+                "{},{timings},{},{},{}",
+                self.frame, stats.allocated_chunks, stats.vertex_count, stats.light_chunks,
+            );
+
+            if let Err(e) = result {
+                println!("stopped recording the profile: {e}");
+                self.csv = None;
+            }
+        }
+
+        if self.since.elapsed() >= Duration::from_secs(1) {
+            self.average = FrameStats {
+                timings: self.sum.map(|sum| sum / self.frames as f32),
+                ..stats
+            };
+            self.sum = [0.0; TIMINGS.len()];
+            self.frames = 0;
+            self.since = Instant::now();
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.csv.is_some()
+    }
+
+    // Starts writing frames to `path`, or stops if it already was.
+    pub fn toggle_recording(&mut self, path: &str) -> std::io::Result<()> {
+        if let Some(mut csv) = self.csv.take() {
+            return csv.flush();
+        }
+
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "frame,{},allocated_chunks,vertex_count,light_chunks", TIMINGS.join(","))?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    pub fn hud_lines(&self) -> Vec<String> {
+        let t = &self.average.timings;
+
+        vec![
+            format!("FRAME {:.2}{}", t[FRAME], if self.is_recording() { " REC" } else { "" }),
+            format!("GPU SCENE {:.2} POST {:.2}", t[GPU_SCENE], t[GPU_POST]),
+            format!("CPU VERTS {:.2} WORLD {:.2} LIGHT {:.2}", t[SET_VERTEX_BUFFER], t[FILL_IN_VOXELS], t[LIGHT]),
+            format!("CPU UPLOAD {:.2} WAIT {:.2}", t[UPLOAD], t[WAIT]),
+            format!("CHUNKS {} VERTS {} LIGHT {}", self.average.allocated_chunks, self.average.vertex_count, self.average.light_chunks),
+        ]
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::io;
+
+use glam::IVec3;
+use sglc_hotcode::prefab::Prefab;
+use sglc_shared::palette::{Material, Palette, PALETTE_SIZE};
+
+// Reader for MagicaVoxel .vox files, see
+// https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+pub struct VoxChunk<'a> {
+    pub id: [u8; 4],
+    pub content: &'a [u8],
+    pub children: &'a [u8],
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> io::Result<i32> {
+    bytes
+        .get(at..at.saturating_add(4))
+        .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| invalid("unexpected end of .vox data"))
+}
+
+// Sizes and counts are stored as i32, negative ones mean the file is broken.
+fn read_size(bytes: &[u8], at: usize) -> io::Result<usize> {
+    usize::try_from(read_i32(bytes, at)?).map_err(|_| invalid("negative size in .vox data"))
+}
+
+pub fn read_chunks(mut bytes: &[u8]) -> io::Result<Vec<VoxChunk<'_>>> {
+    let mut chunks = Vec::new();
+
+    while !bytes.is_empty() {
+        let id = bytes.get(0..4).ok_or_else(|| invalid("truncated .vox chunk"))?;
+        let content_size = read_size(bytes, 4)?;
+        let children_size = read_size(bytes, 8)?;
+        let end = content_size
+            .checked_add(children_size)
+            .and_then(|size| size.checked_add(12))
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| invalid("truncated .vox chunk"))?;
+
+        chunks.push(VoxChunk {
+            id: id.try_into().unwrap(),
+            content: &bytes[12..12 + content_size],
+            children: &bytes[12 + content_size..end],
+        });
+
+        bytes = &bytes[end..];
+    }
+
+    Ok(chunks)
+}
+
+// The chunks inside of the MAIN chunk, which is where everything lives.
+pub fn read_main_chunks(bytes: &[u8]) -> io::Result<Vec<VoxChunk<'_>>> {
+    if bytes.get(0..4) != Some(b"VOX ") {
+        return Err(invalid("not a .vox file"));
+    }
+
+    let main = read_chunks(bytes.get(8..).unwrap_or_default())?
+        .into_iter()
+        .find(|c| &c.id == b"MAIN")
+        .ok_or_else(|| invalid(".vox file has no MAIN chunk"))?;
+
+    read_chunks(main.children)
+}
+
+fn read_string(bytes: &[u8], at: &mut usize) -> io::Result<String> {
+    let length = read_size(bytes, *at)?;
+    let end = length
+        .checked_add(*at + 4)
+        .ok_or_else(|| invalid("unexpected end of .vox data"))?;
+    let string = bytes
+        .get(*at + 4..end)
+        .ok_or_else(|| invalid("unexpected end of .vox data"))?;
+    *at = end;
+
+    Ok(String::from_utf8_lossy(string).into_owned())
+}
+
+fn read_dict(bytes: &[u8], at: &mut usize) -> io::Result<HashMap<String, String>> {
+    let count = read_i32(bytes, *at)?;
+    *at += 4;
+
+    (0..count)
+        .map(|_| Ok((read_string(bytes, at)?, read_string(bytes, at)?)))
+        .collect()
+}
+
+// Units 1 and 2 mean outside of the world and air, which leaves 253 units
+// for the 255 colors a file can have. Colors the model uses get units first,
+// in order, then the rest. Only when a model uses more than 253 colors do the
+// last of them share the unit of the closest color they can.
+const FIRST_UNIT: usize = 3;
+
+struct ColorUnits {
+    // Indexed by the color index of the file, 0 being empty.
+    units: [u32; 256],
+    // Whether the color has its unit to itself, so it decides its material.
+    owned: [bool; 256],
+}
+
+fn find_chunk<'a>(chunks: &[VoxChunk<'a>], id: &[u8; 4]) -> Option<&'a [u8]> {
+    chunks.iter().find(|c| &c.id == id).map(|c| c.content)
+}
+
+// The voxels of the first model, x, y, z and color index each.
+fn model_voxels(xyzi: &[u8]) -> io::Result<&[u8]> {
+    let count = read_size(xyzi, 0)?;
+    count
+        .checked_mul(4)
+        .and_then(|size| xyzi.get(4..size.checked_add(4)?))
+        .ok_or_else(|| invalid("unexpected end of .vox data"))
+}
+
+fn color_units(chunks: &[VoxChunk<'_>]) -> io::Result<ColorUnits> {
+    let mut used = [false; 256];
+    if let Some(xyzi) = find_chunk(chunks, b"XYZI") {
+        for voxel in model_voxels(xyzi)?.chunks_exact(4) {
+            used[voxel[3] as usize] = true;
+        }
+    }
+
+    let mut color_units = ColorUnits { units: [0; 256], owned: [false; 256] };
+    let order = (1..256).filter(|&c| used[c]).chain((1..256).filter(|&c| !used[c]));
+    let mut left_over = Vec::new();
+
+    for (i, color) in order.enumerate() {
+        if FIRST_UNIT + i < PALETTE_SIZE {
+            color_units.units[color] = (FIRST_UNIT + i) as u32;
+            color_units.owned[color] = true;
+        } else {
+            left_over.push(color);
+        }
+    }
+
+    // Entry `i` is for color index `i + 1`. Files without colors use
+    // MagicaVoxel's default palette, which isn't known here, so then any
+    // unit is as close as any other.
+    let rgba = find_chunk(chunks, b"RGBA").unwrap_or_default();
+    let rgb = |color: usize| -> [i32; 3] {
+        rgba.get((color - 1) * 4..(color - 1) * 4 + 3)
+            .map_or([0; 3], |c| [c[0] as i32, c[1] as i32, c[2] as i32])
+    };
+
+    for color in left_over {
+        let closest = (1..256)
+            .filter(|&c| color_units.owned[c])
+            .min_by_key(|&c| {
+                let (a, b) = (rgb(c), rgb(color));
+                (0..3).map(|i| (a[i] - b[i]).pow(2)).sum::<i32>()
+            })
+            .unwrap();
+        color_units.units[color] = color_units.units[closest];
+    }
+
+    Ok(color_units)
+}
+
+// Colors end up at the units `read_vox_model` gives the voxels of the same
+// file, see `color_units`.
+pub fn read_vox_palette(bytes: &[u8]) -> io::Result<Palette> {
+    let chunks = read_main_chunks(bytes)?;
+    let color_units = color_units(&chunks)?;
+    let mut palette = Palette::default();
+
+    let rgba = find_chunk(&chunks, b"RGBA").ok_or_else(|| invalid(".vox file has no RGBA chunk"))?;
+
+    for (i, color) in rgba.chunks_exact(4).take(255).enumerate() {
+        if color_units.owned[i + 1] {
+            palette.0[color_units.units[i + 1] as usize] = Material::from_rgba8(color.try_into().unwrap());
+        }
+    }
+
+    for matl in chunks.iter().filter(|c| &c.id == b"MATL") {
+        let index = read_size(matl.content, 0)?;
+        let properties = read_dict(matl.content, &mut 4)?;
+        if !color_units.owned.get(index).copied().unwrap_or(false) {
+            continue;
+        }
+        let material = &mut palette.0[color_units.units[index] as usize];
+
+        let get = |key: &str| properties.get(key).and_then(|v| v.parse::<f32>().ok());
+        let kind = properties.get("_type").map(String::as_str);
+
+        if kind == Some("_emit") {
+            material.emissive = get("_emit").unwrap_or(1.0) * (1.0 + get("_flux").unwrap_or(0.0));
+        }
+        if kind == Some("_metal") {
+            material.metalness = get("_metal").unwrap_or(1.0);
+        }
+        if kind == Some("_glass") {
+            material.transparency = get("_trans").or(get("_alpha")).unwrap_or(1.0);
+        }
+        // `_ior` is stored as the index minus one, older files have `_ri`.
+        if let Some(ior) = get("_ri").or(get("_ior").map(|ior| ior + 1.0)) {
+            material.ior = ior;
+        }
+        if let Some(roughness) = get("_rough") {
+            material.roughness = roughness;
+        }
+    }
+
+    Ok(palette)
+}
+
+// MagicaVoxel doesn't make models any bigger.
+const MAX_MODEL_SIZE: i32 = 256;
+
+// The first model of the file. .vox files are z up while +y is down here, so
+// the model gets turned upright on the way in.
+pub fn read_vox_model(bytes: &[u8]) -> io::Result<Prefab> {
+    let chunks = read_main_chunks(bytes)?;
+
+    let size = find_chunk(&chunks, b"SIZE").ok_or_else(|| invalid(".vox file has no SIZE chunk"))?;
+    let xyzi = find_chunk(&chunks, b"XYZI").ok_or_else(|| invalid(".vox file has no XYZI chunk"))?;
+
+    let (x, y, z) = (read_i32(size, 0)?, read_i32(size, 4)?, read_i32(size, 8)?);
+    if x <= 0 || y <= 0 || z <= 0 {
+        return Err(invalid(".vox model has no size"));
+    }
+    if x > MAX_MODEL_SIZE || y > MAX_MODEL_SIZE || z > MAX_MODEL_SIZE {
+        return Err(invalid(format!(".vox model is {x}x{y}x{z}, more than {MAX_MODEL_SIZE} along a side")));
+    }
+
+    let color_units = color_units(&chunks)?;
+    let mut prefab = Prefab::new(IVec3::new(x, z, y));
+
+    for voxel in model_voxels(xyzi)?.chunks_exact(4) {
+        let [vx, vy, vz, color] = voxel.try_into().unwrap();
+        let pos = IVec3::new(vx as i32, z - 1 - vz as i32, vy as i32);
+
+        // Color 0 is empty, which the prefab already is.
+        if color != 0 && pos.cmplt(prefab.size).all() && pos.cmpge(IVec3::ZERO).all() {
+            prefab.set(pos, color_units.units[color as usize]);
+        }
+    }
+
+    Ok(prefab)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend((content.len() as i32).to_le_bytes());
+        bytes.extend((children.len() as i32).to_le_bytes());
+        bytes.extend(content);
+        bytes.extend(children);
+        bytes
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = (s.len() as i32).to_le_bytes().to_vec();
+        bytes.extend(s.as_bytes());
+        bytes
+    }
+
+    // A 16x16x16 model with the given voxels, colors for all 255 color
+    // indices and materials for some of them.
+    fn vox(voxels: &[[u8; 4]], rgba: &dyn Fn(usize) -> [u8; 4], materials: &[(i32, &[(&str, &str)])]) -> Vec<u8> {
+        let mut children = chunk(b"SIZE", &[16, 0, 0, 0, 16, 0, 0, 0, 16, 0, 0, 0], &[]);
+
+        let mut xyzi = (voxels.len() as i32).to_le_bytes().to_vec();
+        xyzi.extend(voxels.iter().flatten());
+        children.extend(chunk(b"XYZI", &xyzi, &[]));
+
+        let colors = (1..=256).flat_map(|color| rgba(color)).collect::<Vec<_>>();
+        children.extend(chunk(b"RGBA", &colors, &[]));
+
+        for (index, properties) in materials {
+            let mut matl = index.to_le_bytes().to_vec();
+            matl.extend((properties.len() as i32).to_le_bytes());
+            for (key, value) in properties.iter() {
+                matl.extend(string(key));
+                matl.extend(string(value));
+            }
+            children.extend(chunk(b"MATL", &matl, &[]));
+        }
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend(150i32.to_le_bytes());
+        bytes.extend(chunk(b"MAIN", &[], &children));
+        bytes
+    }
+
+    // Every color index looks different.
+    fn rainbow(color: usize) -> [u8; 4] {
+        [color as u8, 255 - color as u8, (color * 7) as u8, 255]
+    }
+
+    fn unit_at(model: &Prefab, x: u8) -> u32 {
+        model.get(IVec3::new(x as i32, 15, 0))
+    }
+
+    #[test]
+    fn voxels_and_colors_end_up_at_the_same_units() {
+        let voxels = [[0, 0, 0, 5], [1, 0, 0, 254], [2, 0, 0, 255], [3, 0, 0, 1]];
+        let bytes = vox(&voxels, &rainbow, &[]);
+        let model = read_vox_model(&bytes).unwrap();
+        let palette = read_vox_palette(&bytes).unwrap();
+
+        let mut units = Vec::new();
+        for [x, _, _, color] in voxels {
+            let unit = unit_at(&model, x);
+            assert!(unit >= 3, "color {color} is unit {unit}");
+            assert_eq!(palette.0[unit as usize], Material::from_rgba8(rainbow(color as usize)), "color {color}");
+            units.push(unit);
+        }
+
+        units.sort_unstable();
+        units.dedup();
+        assert_eq!(units.len(), voxels.len());
+    }
+
+    #[test]
+    fn colors_past_what_fits_share_the_closest_unit() {
+        // Colors 254 and 255 look almost like 10 and 20, and the model uses
+        // every color.
+        let rgba = |color: usize| match color {
+            254 => [10, 245, 71, 255],
+            255 => [21, 235, 140, 255],
+            color => rainbow(color),
+        };
+        let voxels = (1..=255u8).map(|color| [color % 16, color / 16, 0, color]).collect::<Vec<_>>();
+        let bytes = vox(&voxels, &rgba, &[]);
+        let model = read_vox_model(&bytes).unwrap();
+        let palette = read_vox_palette(&bytes).unwrap();
+
+        let unit = |color: u8| model.get(IVec3::new((color % 16) as i32, 15, (color / 16) as i32));
+        assert_eq!(unit(254), unit(10));
+        assert_eq!(unit(255), unit(20));
+        assert_eq!(palette.0[unit(10) as usize], Material::from_rgba8(rainbow(10)));
+
+        let mut units = (1..=255).map(unit).collect::<Vec<_>>();
+        units.sort_unstable();
+        units.dedup();
+        assert_eq!(units, (3..=255).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn materials_follow_their_color() {
+        let materials: [(i32, &[(&str, &str)]); 3] = [
+            (254, &[("_type", "_emit"), ("_emit", "0.5"), ("_flux", "1")]),
+            (3, &[("_type", "_glass"), ("_trans", "0.25"), ("_ior", "0.5")]),
+            (0, &[("_type", "_metal")]),
+        ];
+        let bytes = vox(&[[0, 0, 0, 254], [1, 0, 0, 3]], &rainbow, &materials);
+        let model = read_vox_model(&bytes).unwrap();
+        let palette = read_vox_palette(&bytes).unwrap();
+
+        let lamp = &palette.0[unit_at(&model, 0) as usize];
+        assert_eq!(lamp.emissive, 1.0);
+        assert_eq!(lamp.transparency, 0.0);
+
+        let glass = &palette.0[unit_at(&model, 1) as usize];
+        assert_eq!(glass.transparency, 0.25);
+        assert_eq!(glass.ior, 1.5);
+        assert!(palette.0.iter().all(|material| material.metalness == 0.0));
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        let bytes = vox(&[[0, 0, 0, 1]], &rainbow, &[]);
+
+        assert!(read_vox_model(b"VOX").is_err());
+        assert!(read_vox_palette(b"not a vox file").is_err());
+        for end in [bytes.len() - 1, bytes.len() / 2, 20] {
+            assert!(read_vox_model(&bytes[..end]).is_err(), "cut at {end}");
+        }
+
+        // A voxel count past the end of the chunk.
+        let mut bytes = bytes;
+        let xyzi = bytes.windows(4).position(|w| w == b"XYZI").unwrap();
+        bytes[xyzi + 12] = 100;
+        assert!(read_vox_model(&bytes).is_err());
+    }
+}
@@ -1,32 +1,76 @@
 use std::f32::consts::PI;
 
-use crate::{World, CHUNK_COUNT_ONE, ChunkMapping, Voxels, CHUNK_SIZE_ONE};
+use crate::{World, ChunkMapping, Voxels, CHUNK_SIZE_ONE};
+use glam::{IVec3, Vec3};
+use sglc_hotcode::chunk_allocator::ChunkAllocator;
+use sglc_hotcode::clear::clear;
+use sglc_hotcode::pos_to_index::pos_to_index;
+use sglc_hotcode::prefab::{PasteMode, Prefab};
+use sglc_shared::sky::Sky;
 
-#[derive(Copy, Clone, Default)]
-pub struct Hills;
+const TREES: usize = 24;
+const TRUNK: u32 = 19;
+const LEAVES: u32 = 20;
+
+#[derive(Copy, Clone)]
+pub struct Hills {
+    has_generated: bool,
+    seed: u64,
+}
+
+impl Default for Hills {
+    fn default() -> Self {
+        Self { has_generated: false, seed: 12 }
+    }
+}
 
 impl World for Hills {
-    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
-        for x in 0..CHUNK_COUNT_ONE {
-            for y in 0..CHUNK_COUNT_ONE {
-                chunk_mapping.0[x + y * CHUNK_COUNT_ONE * CHUNK_COUNT_ONE + CHUNK_COUNT_ONE] = 
-                    5;
+    fn name(&self) -> &str { "hills" }
+
+    fn seed(&self) -> Option<u64> { Some(self.seed) }
+
+    fn set_seed(&mut self, seed: u64) { self.seed = seed }
+
+    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool {
+        if self.has_generated { return false };
+
+        self.has_generated = true;
+
+        clear(chunk_mapping, voxels);
+
+        // The hills and the layer above them take slots 1 to 5, which tiny
+        // worlds or devices don't have.
+        if voxels.0.len() < 6 {
+            return true;
+        }
+
+        let extent = chunk_mapping.size.extent();
+
+        if extent[1] > 1 {
+            for x in 0..extent[0] {
+                for z in 0..extent[2] {
+                    chunk_mapping.chunks[pos_to_index((x, 1, z), extent)] = 
+                        5;
+                }
             }
         }
 
-        for x in 0..CHUNK_COUNT_ONE {
-            for y in 0..CHUNK_COUNT_ONE {
-                chunk_mapping.0[x + y * CHUNK_COUNT_ONE * CHUNK_COUNT_ONE] = 
-                    ((x % 2) + (y % 2) * 2 + 1) as u32;
+        for x in 0..extent[0] {
+            for z in 0..extent[2] {
+                chunk_mapping.chunks[pos_to_index((x, 0, z), extent)] = 
+                    ((x % 2) + (z % 2) * 2 + 1) as u32;
             }
         }
 
-        fastrand::seed(12);
+        fastrand::seed(self.seed);
+
+        // Units below 3 mean outside of the world and air, the hills get
+        // 3 to 18 and the trees what comes after.
 
         for x in 0..CHUNK_SIZE_ONE {
             for y in 0..CHUNK_SIZE_ONE {
                 for z in 0..CHUNK_SIZE_ONE {
-                    voxels.0[5][x + y * CHUNK_SIZE_ONE + z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE] = fastrand::u32(1..5);
+                    voxels.0[5][x + y * CHUNK_SIZE_ONE + z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE] = fastrand::u32(3..7);
                 }
             }
         }
@@ -47,10 +91,69 @@ impl World for Hills {
                             continue;
                         }
 
-                        voxels.0[n + 1][x + y * CHUNK_SIZE_ONE + z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE] = fastrand::u32(1..5) + n as u32 * 4;
+                        voxels.0[n + 1][x + y * CHUNK_SIZE_ONE + z * CHUNK_SIZE_ONE * CHUNK_SIZE_ONE] = fastrand::u32(3..7) + n as u32 * 4;
                     }
                 }
             }
         }
+
+        // Trees on top of the layer above the hills, each turned its own way.
+        // They only get chunks while there are slots left.
+        let world = chunk_mapping.size.voxels();
+        if extent[1] > 2 && world.x > 5 && world.z > 5 {
+            let tree = tree();
+            let mut allocator = ChunkAllocator::from_mapping(chunk_mapping);
+
+            for _ in 0..TREES {
+                let turned = tree.rotated(1, fastrand::i32(0..4));
+                let at = IVec3::new(
+                    fastrand::i32(0..world.x - turned.size.x),
+                    2 * CHUNK_SIZE_ONE as i32,
+                    fastrand::i32(0..world.z - turned.size.z),
+                );
+                turned.stamp(at, PasteMode::Merge, &mut allocator, chunk_mapping, voxels);
+            }
+        }
+
+        true
+    }
+
+    fn invalidate(&mut self) {
+        self.has_generated = false;
+    }
+
+    // Hazy, so the repeating hills fade out before they end.
+    fn sky(&self) -> Sky {
+        Sky {
+            horizon_color: Vec3::new(0.85, 0.8, 0.7),
+            fog_color: Vec3::new(0.85, 0.8, 0.7),
+            fog_density: 0.01,
+            fog_start: 16.0,
+            ..Default::default()
+        }
+    }
+}
+
+// A trunk with a branch off to one side, under a ball of leaves.
+fn tree() -> Prefab {
+    let mut tree = Prefab::new(IVec3::new(5, 8, 5));
+
+    for y in 0..6 {
+        tree.set(IVec3::new(2, y, 2), TRUNK);
+    }
+    tree.set(IVec3::new(3, 3, 2), TRUNK);
+    tree.set(IVec3::new(4, 4, 2), LEAVES);
+
+    for x in 0..5 {
+        for y in 5..8 {
+            for z in 0..5 {
+                let pos = IVec3::new(x, y, z);
+                if (pos - IVec3::new(2, 6, 2)).length_squared() <= 5 && tree.get(pos) == 0 {
+                    tree.set(pos, LEAVES);
+                }
+            }
+        }
     }
+
+    tree
 }
@@ -5,7 +5,9 @@ pub struct Noise {
 }
 
 impl World for Noise {
-    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
-        
+    fn name(&self) -> &str { "noise" }
+
+    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool {
+        false
     }
 }
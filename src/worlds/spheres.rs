@@ -6,27 +6,56 @@ use winit::event::VirtualKeyCode;
 use winit::event::ElementState;
 
 
-#[derive(Default)]
 pub struct Spheres {
     has_generated: bool,
-    chunk_count: usize,
+    seed: u64,
+    count: usize,
+}
+
+impl Default for Spheres {
+    fn default() -> Self {
+        Self { has_generated: false, seed: fastrand::u64(..), count: 200 }
+    }
 }
 
 impl World for Spheres {
-    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) {
-        if self.has_generated { return };
+    fn name(&self) -> &str { "spheres" }
+
+    fn seed(&self) -> Option<u64> { Some(self.seed) }
+
+    fn set_seed(&mut self, seed: u64) { self.seed = seed }
+
+    fn commands(&self) -> &'static [(&'static str, &'static str)] {
+        &[("spheres", "<count>")]
+    }
+
+    fn run_command(&mut self, _: &str, args: &[&str]) -> Result<(), String> {
+        let [count] = args else { return Err("usage: spheres <count>".to_string()) };
+
+        self.count = count.parse().map_err(|e| format!("{count}: {e}"))?;
+        self.has_generated = false;
+        Ok(())
+    }
+
+    fn fill_in_voxels(&mut self, chunk_mapping: &mut ChunkMapping, voxels: &mut Voxels) -> bool {
+        if self.has_generated { return false };
 
         self.has_generated = true;
 
-        clear(self.chunk_count, chunk_mapping, voxels); 
+        clear(chunk_mapping, voxels); 
 
         let mut new_chunk = 1;
+        fastrand::seed(self.seed);
 
-        for n in 0..200 {
+        for n in 0..self.count {
             place_one_sphere(n % 10, &mut new_chunk, chunk_mapping, voxels);
         }
 
-        self.chunk_count = new_chunk;
+        true
+    }
+
+    fn invalidate(&mut self) {
+        self.has_generated = false;
     }
 
     fn keyboard_input(&mut self, code: VirtualKeyCode, state: ElementState) {
@@ -35,6 +64,7 @@ impl World for Spheres {
 
         match code {
             X if state == Pressed => {
+                self.seed = fastrand::u64(..);
                 self.has_generated = false;
             },
             _ => {},